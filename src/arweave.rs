@@ -0,0 +1,244 @@
+//! Creating, signing and uploading Arweave [`Transaction`]s through a gateway.
+
+use crate::{
+    crypto::Provider,
+    error::Error,
+    merkle::{deep_hash, hash_sha256_old},
+    transaction::{merklize, Base64, FromUtf8Strs, Tag, ToItems, Transaction},
+};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Confirmation of a mined transaction, as returned by `/tx/{id}/status`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TransactionStatus {
+    pub block_height: u64,
+    pub number_of_confirmations: u64,
+}
+
+/// Extracts the transaction id from a gateway data URL such as `https://arweave.net/{id}` or
+/// an `ar://{id}` URI.
+pub fn tx_id_from_url(url: &str) -> Option<Base64> {
+    let path = url.split(|c| c == '?' || c == '#').next()?;
+    let id = path.trim_end_matches('/').rsplit('/').next()?;
+    Base64::from_str(id).ok().filter(|id| id.0.len() == 32)
+}
+
+#[derive(Debug, Clone)]
+pub struct ArweaveClient {
+    gateway: String,
+    client: reqwest::Client,
+}
+
+impl ArweaveClient {
+    pub fn new(gateway: &str) -> Self {
+        Self {
+            gateway: gateway.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Gateway URL serving the data of transaction `id`.
+    pub fn data_url(&self, id: &Base64) -> String {
+        format!("{}/{}", self.gateway, id)
+    }
+
+    pub async fn get_price(&self, bytes: u64) -> Result<u64, Error> {
+        let price = self
+            .client
+            .get(format!("{}/price/{}", self.gateway, bytes))
+            .send()
+            .await
+            .map_err(Error::ArweaveGetPriceError)?
+            .text()
+            .await
+            .map_err(Error::ArweaveGetPriceError)?;
+        price.parse().map_err(|_| Error::StatusCodeNotOk)
+    }
+
+    pub async fn get_tx_anchor(&self) -> Result<Base64, Error> {
+        let anchor = self
+            .client
+            .get(format!("{}/tx_anchor", self.gateway))
+            .send()
+            .await?
+            .text()
+            .await?;
+        Ok(Base64::from_str(&anchor)?)
+    }
+
+    /// Fetches the header of transaction `id`, including its `data_root` but not its data.
+    /// Data items bundled into another transaction have no header of their own and are not
+    /// found.
+    pub async fn get_transaction(&self, id: &Base64) -> Result<Transaction, Error> {
+        let response = self
+            .client
+            .get(format!("{}/tx/{}", self.gateway, id))
+            .send()
+            .await?;
+        match response.status().as_u16() {
+            200 => Ok(response.json().await?),
+            202 => Err(Error::ArweaveTransactionPending(id.to_string())),
+            404 => Err(Error::TransactionNotFound(id.to_string())),
+            _ => Err(Error::StatusCodeNotOk),
+        }
+    }
+
+    /// Confirmation of transaction `id`, or `None` while it is pending.
+    pub async fn get_status(&self, id: &Base64) -> Result<Option<TransactionStatus>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/tx/{}/status", self.gateway, id))
+            .send()
+            .await?;
+        match response.status().as_u16() {
+            200 => Ok(Some(response.json().await?)),
+            202 => Ok(None),
+            _ => Err(Error::StatusCodeNotOk),
+        }
+    }
+
+    /// Downloads `url`, which may be on any gateway.
+    pub async fn get_data(&self, url: &str) -> Result<Vec<u8>, Error> {
+        let response = self.client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::StatusCodeNotOk);
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Merklizes `data` and returns a signed transaction tagged with `content_type`.
+    pub async fn create_transaction(
+        &self,
+        provider: &Provider,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = merklize(data)?;
+        transaction.owner = provider.keypair_modulus()?;
+        transaction.tags = vec![Tag::<Base64>::from_utf8_strs("Content-Type", content_type)?];
+        transaction.last_tx = self.get_tx_anchor().await?;
+        transaction.reward = self.get_price(transaction.data_size).await?;
+        sign_transaction(provider, transaction)
+    }
+
+    /// Posts the transaction header followed by each of its chunks.
+    pub async fn post_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(format!("{}/tx", self.gateway))
+            .json(&transaction.clone_with_no_data()?)
+            .send()
+            .await
+            .map_err(Error::ArweavePostError)?;
+        if !response.status().is_success() {
+            return Err(Error::StatusCodeNotOk);
+        }
+        for idx in 0..transaction.chunks.len() {
+            let response = self
+                .client
+                .post(format!("{}/chunk", self.gateway))
+                .json(&transaction.get_chunk(idx)?)
+                .send()
+                .await
+                .map_err(Error::ArweavePostError)?;
+            if !response.status().is_success() {
+                return Err(Error::StatusCodeNotOk);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads and uploads a selected file, tagged with its MIME type.
+    pub async fn upload_file(
+        &self,
+        provider: &Provider,
+        file: &gloo_file::File,
+    ) -> Result<Base64, Error> {
+        let bytes = gloo_file::futures::read_as_bytes(file)
+            .await
+            .map_err(|error| Error::JsError(error.to_string()))?;
+        self.upload(provider, bytes, &file.raw_mime_type()).await
+    }
+
+    /// Creates, signs and uploads `data`, returning the id of the Arweave transaction.
+    pub async fn upload(
+        &self,
+        provider: &Provider,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<Base64, Error> {
+        let transaction = self
+            .create_transaction(provider, data, content_type)
+            .await?;
+        self.post_transaction(&transaction).await?;
+        Ok(transaction.id)
+    }
+}
+
+/// Signs the deep hash of `transaction` and sets its id to the hash of the signature.
+pub fn sign_transaction(
+    provider: &Provider,
+    mut transaction: Transaction,
+) -> Result<Transaction, Error> {
+    let deep_hash = deep_hash(transaction.to_deep_hash_item()?)?;
+    let signature = provider.sign(&deep_hash)?;
+    transaction.id = Base64(hash_sha256_old(&signature)?.to_vec());
+    transaction.signature = Base64(signature);
+    Ok(transaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{block_on, serve};
+
+    const ID: &str = "xDqNnLw8RbSyRfmIuNt0Ywv9Q7ZWBh3RmBDi2FmNnEA";
+
+    #[test]
+    fn extracts_tx_ids() {
+        let id = Base64::from_str(ID).unwrap();
+        for url in [
+            format!("https://arweave.net/{}", ID),
+            format!("https://arweave.net/{}/", ID),
+            format!("https://arweave.net/{}?ext=png", ID),
+            format!("https://gateway.example/{}#image", ID),
+            format!("ar://{}", ID),
+        ] {
+            assert_eq!(tx_id_from_url(&url), Some(id.clone()), "{}", url);
+        }
+        assert_eq!(tx_id_from_url("https://arweave.net/"), None);
+        assert_eq!(tx_id_from_url("https://example.com/image.png"), None);
+        assert_eq!(tx_id_from_url(&format!("ar://{}", &ID[..40])), None);
+    }
+
+    #[test]
+    fn fetches_transaction_headers() {
+        let transaction = merklize(b"hello".to_vec()).unwrap();
+        let (url, requests) = serve(vec![
+            (200, serde_json::to_string(&transaction).unwrap()),
+            (202, "Pending".to_string()),
+            (404, "Not Found.".to_string()),
+            (500, String::new()),
+        ]);
+        let arweave = ArweaveClient::new(&url);
+        let id = Base64::from_str(ID).unwrap();
+        assert_eq!(
+            block_on(arweave.get_transaction(&id)).unwrap().data_root,
+            transaction.data_root
+        );
+        assert_eq!(requests.recv().unwrap().path, format!("/tx/{}", ID));
+        assert!(matches!(
+            block_on(arweave.get_transaction(&id)),
+            Err(Error::ArweaveTransactionPending(pending)) if pending == ID
+        ));
+        assert!(matches!(
+            block_on(arweave.get_transaction(&id)),
+            Err(Error::TransactionNotFound(missing)) if missing == ID
+        ));
+        assert!(matches!(
+            block_on(arweave.get_transaction(&id)),
+            Err(Error::StatusCodeNotOk)
+        ));
+    }
+}
@@ -0,0 +1,559 @@
+//! Batch transfers: a sheet of recipients, amounts and optional mints paid out of the connected
+//! wallet. Rows are packed into as few transactions as fit, and the progress of every row is
+//! kept so that an interrupted run can be resumed without paying anyone twice.
+
+use crate::{
+    components::transfer::{format_units, parse_units},
+    confirmation::never_landed,
+    csv,
+    error::Error,
+    programs::{associated_token, token},
+    solana_rpc::{RpcClient, MAX_MULTIPLE_ACCOUNTS},
+    transaction::{option_stringify, stringify},
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, message::Message, pubkey::Pubkey, signature::Signature,
+    system_instruction, transaction::Transaction,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+const RECIPIENT_COLUMN: &str = "recipient";
+const AMOUNT_COLUMN: &str = "amount";
+const MINT_COLUMN: &str = "mint";
+
+const SOL_DECIMALS: u8 = 9;
+/// Largest serialized transaction the cluster accepts.
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+/// Room left in each transaction for the compute budget instructions of a priority fee.
+const PRIORITY_FEE_RESERVE: usize = 64;
+
+/// How far a transfer has progressed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "stage", rename_all = "camelCase")]
+pub enum TransferStatus {
+    Pending,
+    /// Submitted but not yet confirmed. The transaction may still land until `blockhash`
+    /// expires, so the row must not be sent again before then.
+    Sent {
+        #[serde(with = "stringify")]
+        signature: Signature,
+        #[serde(with = "stringify")]
+        blockhash: Hash,
+    },
+    Confirmed {
+        #[serde(with = "stringify")]
+        signature: Signature,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransferRow {
+    #[serde(with = "stringify")]
+    pub recipient: Pubkey,
+    /// Amount in lamports or base units of `mint`.
+    #[serde(with = "stringify")]
+    pub amount: u64,
+    /// Token to transfer, SOL if `None`.
+    #[serde(default, with = "option_stringify")]
+    pub mint: Option<Pubkey>,
+    pub decimals: u8,
+    pub status: TransferStatus,
+    /// Error from the last attempt.
+    pub error: Option<String>,
+}
+
+impl TransferRow {
+    /// The amount with its unit, such as `1.5 SOL`.
+    pub fn amount_label(&self) -> String {
+        match &self.mint {
+            Some(mint) => format!("{} {}", format_units(self.amount, self.decimals), mint),
+            None => format!("{} SOL", format_units(self.amount, self.decimals)),
+        }
+    }
+
+    /// Moves a sent row to confirmed, or back to pending if its transaction can no longer pay
+    /// it. Other errors are recorded and the row stays sent, to be confirmed by a later run.
+    pub fn settle(&mut self, outcome: Result<(), &Error>) {
+        if let TransferStatus::Sent { signature, .. } = self.status {
+            match outcome {
+                Ok(()) => {
+                    self.status = TransferStatus::Confirmed { signature };
+                    self.error = None;
+                }
+                Err(error) if never_landed(error) => {
+                    self.status = TransferStatus::Pending;
+                    self.error = Some(error.to_string());
+                }
+                Err(error) => self.error = Some(error.to_string()),
+            }
+        }
+    }
+
+    /// Token account receiving an SPL transfer.
+    pub fn destination(&self) -> Option<Pubkey> {
+        self.mint
+            .map(|mint| associated_token::get_associated_token_address(&self.recipient, &mint))
+    }
+
+    /// Instructions paying this row from `payer`, creating the recipient's token account first
+    /// if `create_account` is set.
+    pub fn instructions(&self, payer: &Pubkey, create_account: bool) -> Vec<Instruction> {
+        match &self.mint {
+            Some(mint) => {
+                let mut instructions = Vec::with_capacity(2);
+                if create_account {
+                    instructions.push(
+                        associated_token::create_associated_token_account_idempotent(
+                            payer,
+                            &self.recipient,
+                            mint,
+                        ),
+                    );
+                }
+                instructions.push(token::transfer_checked(
+                    &associated_token::get_associated_token_address(payer, mint),
+                    mint,
+                    &associated_token::get_associated_token_address(&self.recipient, mint),
+                    payer,
+                    self.amount,
+                    self.decimals,
+                ));
+                instructions
+            }
+            None => vec![system_instruction::transfer(
+                payer,
+                &self.recipient,
+                self.amount,
+            )],
+        }
+    }
+}
+
+/// A batch transfer. Persisted after every change so it survives reloads.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BatchTransferRun {
+    pub rows: Vec<TransferRow>,
+}
+
+impl BatchTransferRun {
+    pub fn is_complete(&self) -> bool {
+        self.rows
+            .iter()
+            .all(|row| matches!(row.status, TransferStatus::Confirmed { .. }))
+    }
+}
+
+/// Parses a CSV sheet with `recipient` and `amount` columns and an optional `mint` column.
+/// Amounts are decimal SOL, or decimal tokens for rows with a mint, whose decimals are looked
+/// up on chain. All problems are reported together.
+pub async fn load(rpc: &RpcClient, text: &str) -> Result<BatchTransferRun, Error> {
+    let (header, records) = csv::parse_with_header(text)?;
+    let column = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let recipient = column(RECIPIENT_COLUMN)
+        .ok_or_else(|| Error::InvalidCsv(format!("missing {} column", RECIPIENT_COLUMN)))?;
+    let amount = column(AMOUNT_COLUMN)
+        .ok_or_else(|| Error::InvalidCsv(format!("missing {} column", AMOUNT_COLUMN)))?;
+    let mint = column(MINT_COLUMN);
+    if records.is_empty() {
+        return Err(Error::InvalidBatchTransfer("sheet has no rows".to_string()));
+    }
+
+    let mut problems = Vec::new();
+    let mut parsed = Vec::with_capacity(records.len());
+    for (i, record) in records.iter().enumerate() {
+        let row = i + 1;
+        let recipient = Pubkey::from_str(record[recipient].trim());
+        if recipient.is_err() {
+            problems.push(format!("row {}: invalid recipient", row));
+        }
+        let mint = match mint
+            .map(|i| record[i].trim())
+            .filter(|mint| !mint.is_empty())
+        {
+            Some(mint) => match Pubkey::from_str(mint) {
+                Ok(mint) => Some(mint),
+                Err(_) => {
+                    problems.push(format!("row {}: invalid mint", row));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        if let Ok(recipient) = recipient {
+            parsed.push((row, recipient, record[amount].trim(), mint));
+        }
+    }
+
+    let decimals = mint_decimals(rpc, parsed.iter().filter_map(|(_, _, _, mint)| *mint)).await?;
+    let mut rows = Vec::with_capacity(parsed.len());
+    for (row, recipient, amount, mint) in parsed {
+        let decimals = match mint {
+            Some(mint) => match decimals.get(&mint) {
+                Some(decimals) => *decimals,
+                None => {
+                    problems.push(format!("row {}: {} is not a token mint", row, mint));
+                    continue;
+                }
+            },
+            None => SOL_DECIMALS,
+        };
+        match parse_units(amount, decimals) {
+            Ok(0) => problems.push(format!("row {}: amount must be greater than zero", row)),
+            Ok(amount) => rows.push(TransferRow {
+                recipient,
+                amount,
+                mint,
+                decimals,
+                status: TransferStatus::Pending,
+                error: None,
+            }),
+            Err(_) => problems.push(format!("row {}: invalid amount {}", row, amount)),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(BatchTransferRun { rows })
+    } else {
+        Err(Error::InvalidBatchTransfer(problems.join("; ")))
+    }
+}
+
+/// Decimals of each of `mints` that is a token mint.
+async fn mint_decimals(
+    rpc: &RpcClient,
+    mints: impl Iterator<Item = Pubkey>,
+) -> Result<HashMap<Pubkey, u8>, Error> {
+    let mints: Vec<Pubkey> = mints.collect::<HashSet<_>>().into_iter().collect();
+    let mut decimals = HashMap::new();
+    for chunk in mints.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc.get_multiple_accounts(chunk).await?.value;
+        for (mint, account) in chunk.iter().zip(accounts) {
+            if let Some(account) = account.filter(|account| account.owner == token::id()) {
+                if let Some(mint_decimals) = token::mint_decimals(&account.data) {
+                    decimals.insert(*mint, mint_decimals);
+                }
+            }
+        }
+    }
+    Ok(decimals)
+}
+
+/// Recipient token accounts of `rows` that do not exist yet.
+pub async fn missing_token_accounts(
+    rpc: &RpcClient,
+    rows: &[&TransferRow],
+) -> Result<HashSet<Pubkey>, Error> {
+    let destinations: Vec<Pubkey> = rows
+        .iter()
+        .filter_map(|row| row.destination())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut missing = HashSet::new();
+    for chunk in destinations.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc.get_multiple_accounts(chunk).await?.value;
+        for (destination, account) in chunk.iter().zip(accounts) {
+            if account.is_none() {
+                missing.insert(*destination);
+            }
+        }
+    }
+    Ok(missing)
+}
+
+fn transaction_size(instructions: &[Instruction], payer: &Pubkey, blockhash: &Hash) -> usize {
+    let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+        instructions,
+        Some(payer),
+        blockhash,
+    ));
+    bincode::serialized_size(&transaction).map_or(usize::MAX, |size| size as usize)
+}
+
+/// Packs the instructions of each row, keyed by row index, into as few transactions paid by
+/// `payer` as fit the size limit, in order. Returns the rows paid by each transaction.
+pub fn pack(
+    payer: &Pubkey,
+    blockhash: &Hash,
+    rows: Vec<(usize, Vec<Instruction>)>,
+) -> Vec<(Vec<usize>, Transaction)> {
+    let limit = MAX_TRANSACTION_SIZE - PRIORITY_FEE_RESERVE;
+    let mut packed = Vec::new();
+    let mut indexes: Vec<usize> = Vec::new();
+    let mut instructions: Vec<Instruction> = Vec::new();
+    for (i, row_instructions) in rows {
+        let len = instructions.len();
+        instructions.extend(row_instructions);
+        if !indexes.is_empty() && transaction_size(&instructions, payer, blockhash) > limit {
+            let row_instructions = instructions.split_off(len);
+            packed.push((std::mem::take(&mut indexes), instructions));
+            instructions = row_instructions;
+        }
+        indexes.push(i);
+    }
+    if !indexes.is_empty() {
+        packed.push((indexes, instructions));
+    }
+    packed
+        .into_iter()
+        .map(|(indexes, instructions)| {
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &instructions,
+                Some(payer),
+                blockhash,
+            ));
+            (indexes, transaction)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        confirmation::poll,
+        solana_rpc::Commitment,
+        stub_server::{block_on, serve},
+    };
+    use serde_json::json;
+
+    const LIMIT: usize = MAX_TRANSACTION_SIZE - PRIORITY_FEE_RESERVE;
+
+    fn row(mint: Option<Pubkey>) -> TransferRow {
+        TransferRow {
+            recipient: Pubkey::new_unique(),
+            amount: 1_000,
+            mint,
+            decimals: 6,
+            status: TransferStatus::Pending,
+            error: None,
+        }
+    }
+
+    /// RPC answering a single `getMultipleAccounts` request with `accounts`.
+    fn rpc(accounts: Vec<Option<Vec<u8>>>) -> RpcClient {
+        let value: Vec<_> = accounts
+            .into_iter()
+            .map(|data| {
+                data.map(|data| {
+                    json!({
+                        "lamports": 1,
+                        "owner": token::id().to_string(),
+                        "data": [base64::encode(data), "base64"],
+                        "executable": false,
+                        "rentEpoch": 0
+                    })
+                })
+            })
+            .collect();
+        let (url, _requests) = serve(vec![(
+            200,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": { "context": { "slot": 1 }, "value": value }
+            })
+            .to_string(),
+        )]);
+        RpcClient::new(&url)
+    }
+
+    fn mint_account(decimals: u8) -> Vec<u8> {
+        let mut data = vec![0; token::MINT_LEN];
+        data[44] = decimals;
+        data
+    }
+
+    #[test]
+    fn packs_rows_within_the_size_limit() {
+        let payer = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let mint = Pubkey::new_unique();
+        let rows: Vec<(usize, Vec<Instruction>)> = (0..40)
+            .map(|i| {
+                let row = row(if i % 3 == 0 { None } else { Some(mint) });
+                (i, row.instructions(&payer, i % 2 == 0))
+            })
+            .collect();
+        let packed = pack(&payer, &blockhash, rows.clone());
+        assert!(packed.len() > 1);
+
+        let indexes: Vec<usize> = packed.iter().flat_map(|(batch, _)| batch.clone()).collect();
+        assert_eq!(indexes, (0..40).collect::<Vec<_>>());
+        for (n, (batch, transaction)) in packed.iter().enumerate() {
+            let instructions: Vec<Instruction> =
+                batch.iter().flat_map(|i| rows[*i].1.clone()).collect();
+            assert_eq!(transaction.message.instructions.len(), instructions.len());
+            assert_eq!(transaction.message.recent_blockhash, blockhash);
+            assert_eq!(transaction.message.account_keys[0], payer);
+            assert!(bincode::serialized_size(transaction).unwrap() as usize <= LIMIT);
+            // Batches are only split when the next row would not fit.
+            if let Some((next, _)) = packed.get(n + 1) {
+                let mut grown = instructions;
+                grown.extend(rows[next[0]].1.clone());
+                assert!(transaction_size(&grown, &payer, &blockhash) > LIMIT);
+            }
+        }
+    }
+
+    #[test]
+    fn packs_nothing_without_rows() {
+        assert!(pack(&Pubkey::new_unique(), &Hash::new_unique(), Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn loads_sol_rows_without_rpc() {
+        let recipient = Pubkey::new_unique();
+        let text = format!(
+            "Recipient,Amount\n{},1.5\n{},0.000000001\n",
+            recipient, recipient
+        );
+        let run = block_on(load(&RpcClient::new("http://127.0.0.1:1"), &text)).unwrap();
+        assert_eq!(run.rows.len(), 2);
+        assert_eq!(run.rows[0].recipient, recipient);
+        assert_eq!(run.rows[0].amount, 1_500_000_000);
+        assert_eq!(run.rows[0].mint, None);
+        assert_eq!(run.rows[0].decimals, SOL_DECIMALS);
+        assert_eq!(run.rows[1].amount, 1);
+        assert!(run
+            .rows
+            .iter()
+            .all(|row| row.status == TransferStatus::Pending));
+    }
+
+    #[test]
+    fn loads_token_decimals() {
+        let mint = Pubkey::new_unique();
+        let text = format!(
+            "recipient,amount,mint\n{},2.5,{}\n{},1,\n",
+            Pubkey::new_unique(),
+            mint,
+            Pubkey::new_unique()
+        );
+        let run = block_on(load(&rpc(vec![Some(mint_account(6))]), &text)).unwrap();
+        assert_eq!(run.rows[0].mint, Some(mint));
+        assert_eq!((run.rows[0].amount, run.rows[0].decimals), (2_500_000, 6));
+        assert_eq!(
+            (run.rows[1].mint, run.rows[1].amount),
+            (None, 1_000_000_000)
+        );
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let mint = Pubkey::new_unique();
+        let text = format!(
+            "recipient,amount,mint\nnot-a-key,1,\n{},0,\n{},1.0000001,{}\n{},1,bad\n{},1,\n",
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            mint,
+            Pubkey::new_unique(),
+            Pubkey::new_unique()
+        );
+        match block_on(load(&rpc(vec![Some(mint_account(6))]), &text)) {
+            Err(Error::InvalidBatchTransfer(problems)) => assert_eq!(
+                problems,
+                "row 1: invalid recipient; row 4: invalid mint; \
+                 row 2: amount must be greater than zero; row 3: invalid amount 1.0000001"
+            ),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn rejects_accounts_that_are_not_mints() {
+        let mint = Pubkey::new_unique();
+        let text = format!(
+            "recipient,amount,mint\n{},1,{}\n",
+            Pubkey::new_unique(),
+            mint
+        );
+        match block_on(load(&rpc(vec![None]), &text)) {
+            Err(Error::InvalidBatchTransfer(problems)) => {
+                assert_eq!(problems, format!("row 1: {} is not a token mint", mint))
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    fn sent(row: &mut TransferRow, signature: Signature) {
+        row.status = TransferStatus::Sent {
+            signature,
+            blockhash: Hash::new_unique(),
+        };
+    }
+
+    #[test]
+    fn keeps_rows_that_landed_before_the_status_cache() {
+        let signature = Signature::new(&[1; 64]);
+        let mut row = row(None);
+        sent(&mut row, signature);
+        let landed = json!({
+            "slot": 7,
+            "confirmations": null,
+            "err": null,
+            "confirmationStatus": "finalized"
+        });
+        let (url, _requests) = serve(
+            [json!([null]), json!(false), json!([landed])]
+                .into_iter()
+                .map(|value| {
+                    let result = json!({ "context": { "slot": 100 }, "value": value });
+                    (
+                        200,
+                        json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string(),
+                    )
+                })
+                .collect(),
+        );
+        let blockhash = Hash::new_unique();
+        let state = block_on(poll(&RpcClient::new(&url), &[signature], Some(&blockhash)))
+            .unwrap()
+            .remove(0);
+        let outcome = state.outcome(Commitment::Confirmed).unwrap();
+        row.settle(outcome.as_ref().map(|_| ()));
+        assert_eq!(row.status, TransferStatus::Confirmed { signature });
+    }
+
+    #[test]
+    fn settles_only_definitive_failures_back_to_pending() {
+        let signature = Signature::new(&[2; 64]);
+        for error in [Error::ConfirmationTimeout, Error::StatusCodeNotOk] {
+            let mut row = row(None);
+            sent(&mut row, signature);
+            let status = row.status.clone();
+            row.settle(Err(&error));
+            assert_eq!(row.status, status);
+            assert_eq!(row.error, Some(error.to_string()));
+        }
+        for error in [
+            Error::BlockhashExpired,
+            Error::TransactionFailed("err".to_string()),
+        ] {
+            let mut row = row(None);
+            sent(&mut row, signature);
+            row.settle(Err(&error));
+            assert_eq!(row.status, TransferStatus::Pending);
+        }
+        let mut row = row(None);
+        row.settle(Ok(()));
+        assert_eq!(row.status, TransferStatus::Pending);
+    }
+
+    #[test]
+    fn requires_columns_and_rows() {
+        let rpc = RpcClient::new("http://127.0.0.1:1");
+        assert!(matches!(
+            block_on(load(&rpc, "recipient\nabc\n")),
+            Err(Error::InvalidCsv(_))
+        ));
+        assert!(matches!(
+            block_on(load(&rpc, "recipient,amount\n")),
+            Err(Error::InvalidBatchTransfer(_))
+        ));
+    }
+}
@@ -0,0 +1,315 @@
+//! Collection launches: the metadata sheet describing each item and the progress of minting
+//! every item, kept so that an interrupted run can be resumed.
+
+use crate::{
+    confirmation::never_landed,
+    csv,
+    error::Error,
+    programs::metadata::{JsonAttribute, MAX_NAME_LENGTH},
+    transaction::{option_stringify, stringify},
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature};
+use std::collections::HashSet;
+
+const FILE_COLUMN: &str = "file";
+const NAME_COLUMN: &str = "name";
+const DESCRIPTION_COLUMN: &str = "description";
+
+/// How far minting an item has progressed. Each stage keeps what is needed to pick up from it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "stage", rename_all = "camelCase")]
+pub enum ItemStatus {
+    Pending,
+    AssetUploaded {
+        image_uri: String,
+        content_type: String,
+    },
+    MetadataUploaded {
+        image_uri: String,
+        metadata_uri: String,
+    },
+    /// Submitted but not yet confirmed. The transaction may still land until `blockhash`
+    /// expires.
+    Sent {
+        image_uri: String,
+        metadata_uri: String,
+        #[serde(with = "stringify")]
+        mint: Pubkey,
+        #[serde(with = "stringify")]
+        signature: Signature,
+        #[serde(with = "stringify")]
+        blockhash: Hash,
+    },
+    Minted {
+        #[serde(with = "stringify")]
+        mint: Pubkey,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionItem {
+    pub file: String,
+    pub name: String,
+    pub description: String,
+    pub attributes: Vec<JsonAttribute>,
+    pub status: ItemStatus,
+    /// Error from the last attempt at the next stage.
+    pub error: Option<String>,
+}
+
+/// The transaction minting the collection NFT, kept until it is confirmed so the collection
+/// is not minted twice.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionSent {
+    #[serde(with = "stringify")]
+    pub mint: Pubkey,
+    #[serde(with = "stringify")]
+    pub signature: Signature,
+    #[serde(with = "stringify")]
+    pub blockhash: Hash,
+}
+
+/// A collection launch. Persisted after every change so it survives reloads.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CollectionRun {
+    /// The collection NFT, once confirmed.
+    #[serde(default, with = "option_stringify")]
+    pub collection_mint: Option<Pubkey>,
+    /// Uploaded JSON metadata of the collection NFT.
+    #[serde(default)]
+    pub collection_metadata_uri: Option<String>,
+    #[serde(default)]
+    pub collection_sent: Option<CollectionSent>,
+    pub items: Vec<CollectionItem>,
+}
+
+impl CollectionRun {
+    pub fn new(items: Vec<CollectionItem>) -> Self {
+        Self {
+            collection_mint: None,
+            collection_metadata_uri: None,
+            collection_sent: None,
+            items,
+        }
+    }
+
+    /// Records the collection NFT as minted, or forgets its transaction if it can no longer
+    /// land so it is minted again. Other errors leave it sent, to be confirmed by a later run.
+    pub fn settle_collection(&mut self, outcome: Result<(), &Error>) {
+        match (&self.collection_sent, outcome) {
+            (Some(sent), Ok(())) => {
+                self.collection_mint = Some(sent.mint);
+                self.collection_sent = None;
+            }
+            (Some(_), Err(error)) if never_landed(error) => self.collection_sent = None,
+            _ => {}
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.items
+            .iter()
+            .all(|item| matches!(item.status, ItemStatus::Minted { .. }))
+    }
+}
+
+#[derive(Deserialize)]
+struct SheetRow {
+    file: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    attributes: Vec<JsonAttribute>,
+}
+
+impl From<SheetRow> for CollectionItem {
+    fn from(row: SheetRow) -> Self {
+        CollectionItem {
+            file: row.file,
+            name: row.name,
+            description: row.description,
+            attributes: row.attributes,
+            status: ItemStatus::Pending,
+            error: None,
+        }
+    }
+}
+
+/// Parses a metadata sheet, either a JSON array of items or a CSV file with `file` and `name`
+/// columns, an optional `description` column and any other columns as attributes.
+pub fn parse_sheet(sheet_name: &str, text: &str) -> Result<Vec<CollectionItem>, Error> {
+    if sheet_name.to_lowercase().ends_with(".json") {
+        let rows: Vec<SheetRow> = serde_json::from_str(text)?;
+        return Ok(rows.into_iter().map(CollectionItem::from).collect());
+    }
+
+    let (header, records) = csv::parse_with_header(text)?;
+    let column = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let file = column(FILE_COLUMN)
+        .ok_or_else(|| Error::InvalidCsv(format!("missing {} column", FILE_COLUMN)))?;
+    let name = column(NAME_COLUMN)
+        .ok_or_else(|| Error::InvalidCsv(format!("missing {} column", NAME_COLUMN)))?;
+    let description = column(DESCRIPTION_COLUMN);
+    Ok(records
+        .into_iter()
+        .map(|record| CollectionItem {
+            file: record[file].trim().to_string(),
+            name: record[name].trim().to_string(),
+            description: description
+                .map(|i| record[i].trim().to_string())
+                .unwrap_or_default(),
+            attributes: header
+                .iter()
+                .zip(&record)
+                .enumerate()
+                .filter(|(i, (_, value))| {
+                    ![Some(file), Some(name), description].contains(&Some(*i))
+                        && !value.trim().is_empty()
+                })
+                .map(|(_, (trait_type, value))| JsonAttribute {
+                    trait_type: trait_type.clone(),
+                    value: value.trim().to_string(),
+                })
+                .collect(),
+            status: ItemStatus::Pending,
+            error: None,
+        })
+        .collect())
+}
+
+/// Checks that every item has a valid name and exactly one matching asset, and that every
+/// asset is described by the sheet. All problems are reported together.
+pub fn validate(items: &[CollectionItem], assets: &[String]) -> Result<(), Error> {
+    let mut problems = Vec::new();
+    if items.is_empty() {
+        problems.push("sheet has no items".to_string());
+    }
+    let assets: HashSet<&str> = assets.iter().map(String::as_str).collect();
+    let mut files = HashSet::new();
+    for (i, item) in items.iter().enumerate() {
+        let row = i + 1;
+        if item.name.is_empty() {
+            problems.push(format!("row {}: missing name", row));
+        } else if item.name.len() > MAX_NAME_LENGTH {
+            problems.push(format!(
+                "row {}: name is longer than {} bytes",
+                row, MAX_NAME_LENGTH
+            ));
+        }
+        if !files.insert(item.file.as_str()) {
+            problems.push(format!(
+                "row {}: {} is listed more than once",
+                row, item.file
+            ));
+        }
+        if !assets.contains(item.file.as_str()) {
+            problems.push(format!("row {}: {} is not in the folder", row, item.file));
+        }
+    }
+    let mut unlisted: Vec<&&str> = assets.difference(&files).collect();
+    unlisted.sort();
+    for asset in unlisted {
+        problems.push(format!("{} is not in the sheet", asset));
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidCollection(problems.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets(files: &[&str]) -> Vec<String> {
+        files.iter().map(|file| file.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_csv_sheet_with_attributes() {
+        let items = parse_sheet(
+            "sheet.csv",
+            "File,Name,Description,Color\na.png,A,First,red\nb.png,B,,\n",
+        )
+        .unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].description, "First");
+        assert_eq!(
+            items[0].attributes,
+            vec![JsonAttribute {
+                trait_type: "Color".to_string(),
+                value: "red".to_string(),
+            }]
+        );
+        assert!(items[1].attributes.is_empty());
+        assert!(validate(&items, &assets(&["b.png", "a.png"])).is_ok());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let long_name = "x".repeat(MAX_NAME_LENGTH + 1);
+        let sheet = format!("file,name\na.png,\na.png,A\nb.png,{}\nc.png,C\n", long_name);
+        let items = parse_sheet("sheet.csv", &sheet).unwrap();
+        match validate(&items, &assets(&["a.png", "b.png", "d.png"])) {
+            Err(Error::InvalidCollection(problems)) => assert_eq!(
+                problems,
+                format!(
+                    "row 1: missing name; row 2: a.png is listed more than once; \
+                     row 3: name is longer than {} bytes; row 4: c.png is not in the folder; \
+                     d.png is not in the sheet",
+                    MAX_NAME_LENGTH
+                )
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn settles_the_collection_nft() {
+        let sent = CollectionSent {
+            mint: Pubkey::new_unique(),
+            signature: Signature::new(&[1; 64]),
+            blockhash: Hash::new_unique(),
+        };
+        let mut run = CollectionRun::new(Vec::new());
+        run.collection_sent = Some(sent.clone());
+
+        for error in [Error::ConfirmationTimeout, Error::StatusCodeNotOk] {
+            run.settle_collection(Err(&error));
+            assert_eq!(run.collection_sent, Some(sent.clone()));
+            assert_eq!(run.collection_mint, None);
+        }
+        run.settle_collection(Ok(()));
+        assert_eq!(run.collection_mint, Some(sent.mint));
+        assert_eq!(run.collection_sent, None);
+
+        let mut run = CollectionRun::new(Vec::new());
+        run.collection_metadata_uri = Some("https://arweave.net/metadata".to_string());
+        run.collection_sent = Some(sent);
+        run.settle_collection(Err(&Error::BlockhashExpired));
+        assert_eq!(run.collection_sent, None);
+        assert_eq!(run.collection_mint, None);
+        assert!(run.collection_metadata_uri.is_some());
+    }
+
+    #[test]
+    fn reads_runs_saved_before_the_collection_was_tracked() {
+        let mint = Pubkey::new_unique();
+        let run: CollectionRun =
+            serde_json::from_str(&format!(r#"{{"collection_mint":"{}","items":[]}}"#, mint))
+                .unwrap();
+        assert_eq!(run.collection_mint, Some(mint));
+        assert_eq!(run.collection_sent, None);
+    }
+
+    #[test]
+    fn rejects_empty_sheet() {
+        assert!(matches!(
+            validate(&[], &[]),
+            Err(Error::InvalidCollection(problems)) if problems == "sheet has no items"
+        ));
+    }
+}
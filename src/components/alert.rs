@@ -0,0 +1,25 @@
+use crate::store::{reducer, Action, ErrorMessage};
+use sycamore::prelude::*;
+
+#[component]
+pub fn ErrorAlert<G: Html>(ctx: ScopeRef) -> View<G> {
+    let error = ctx.use_context::<Signal<ErrorMessage>>();
+    let message = ctx.create_memo(|| error.get().0.clone().unwrap_or_default());
+
+    view! {ctx,
+        (if error.get().0.is_some() {
+            view! {ctx,
+                div(class="flex items-center justify-between px-5 py-3 rounded-lg bg-rose-800 text-slate-100") {
+                    p {(message.get())}
+                    button(class="text-sm uppercase tracking-wider font-semibold",
+                        on:click=|_| reducer(ctx, Action::ErrorSet(None))
+                    ) {
+                        "Dismiss"
+                    }
+                }
+            }
+        } else {
+            View::empty()
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{components::transfer::format_sol, preview::TransactionPreview, store::Approval};
+use sycamore::prelude::*;
+
+fn preview_view<G: Html>(ctx: ScopeRef, preview: &TransactionPreview) -> View<G> {
+    let instructions = View::new_fragment(
+        preview
+            .instructions
+            .iter()
+            .map(|instruction| {
+                let title = format!("{}: {}", instruction.program, instruction.action);
+                let fields = View::new_fragment(
+                    instruction
+                        .fields
+                        .iter()
+                        .map(|(label, value)| {
+                            let field = format!("{}: {}", label, value);
+                            view! {ctx, li(class="font-mono text-sm truncate") {(field)} }
+                        })
+                        .collect(),
+                );
+                view! {ctx,
+                    li {
+                        p(class="font-semibold") {(title)}
+                        ul(class="pl-4") {(fields)}
+                    }
+                }
+            })
+            .collect(),
+    );
+    let signers = preview
+        .signers
+        .iter()
+        .map(|signer| signer.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    let fee = preview
+        .fee_lamports
+        .map(format_sol)
+        .unwrap_or_else(|| "unknown, blockhash expired".to_string());
+    let rent = format_sol(preview.rent_lamports);
+    let simulation = match &preview.simulation {
+        Some(simulation) => {
+            let units = simulation
+                .units_consumed
+                .map(|units| units.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            let outcome = match &simulation.error {
+                Some(error) => {
+                    let error = format!("Simulation failed: {}", error);
+                    view! {ctx, p(class="text-sm text-red-400") {(error)} }
+                }
+                None => view! {ctx, p(class="text-sm text-green-400") {"Simulation succeeded"} },
+            };
+            let logs = simulation.logs.join("\n");
+            view! {ctx,
+                (outcome)
+                p(class="text-sm") {"Compute units: " (units)}
+                details {
+                    summary(class="text-sm cursor-pointer") {"Program logs"}
+                    pre(class="text-xs overflow-x-auto") {(logs)}
+                }
+            }
+        }
+        None => View::empty(),
+    };
+
+    view! {ctx,
+        div(class="space-y-2 px-5 py-3 rounded-lg bg-slate-700") {
+            ol(class="space-y-2 list-decimal list-inside") {(instructions)}
+            p(class="text-sm truncate") {"Signers: " (signers)}
+            p(class="text-sm") {"Estimated fee: " (fee)}
+            p(class="text-sm") {"Rent for new accounts: " (rent)}
+            (simulation)
+        }
+    }
+}
+
+/// Decoded preview of the transactions about to be sent to Phantom, with their estimated
+/// costs and simulated outcome. Nothing reaches the wallet until it is approved here, and
+/// transactions that fail simulation can only be approved after an explicit override.
+#[component]
+pub fn TransactionApproval<G: Html>(ctx: ScopeRef) -> View<G> {
+    let approval = ctx.use_context::<Signal<Approval>>();
+    let overridden = ctx.create_signal(false);
+
+    view! {ctx,
+        (match (*approval.get()).clone() {
+            Some(pending) => {
+                let previews = View::new_fragment(
+                    pending.previews.iter().map(|preview| preview_view(ctx, preview)).collect(),
+                );
+                let total = format_sol(pending.previews.iter().map(|p| p.cost_lamports()).sum());
+                let approve = pending.clone();
+                let failed = pending.previews.iter().any(|p| p.simulation_failed());
+                overridden.set(false);
+                view! {ctx,
+                    div(class="space-y-2 px-5 py-3 rounded-lg border border-indigo-700 text-slate-200") {
+                        p(class="text-lg font-semibold") {"Review before approving in Phantom"}
+                        (match pending.notice.clone() {
+                            Some(notice) => view! {ctx, p(class="text-sm text-amber-400") {(notice)} },
+                            None => View::empty(),
+                        })
+                        (previews)
+                        p(class="font-semibold") {"Total fees and rent: " (total)}
+                        (if failed {
+                            view! {ctx,
+                                label(class="flex items-center space-x-2 text-sm text-red-400") {
+                                    input(type="checkbox", bind:checked=overridden)
+                                    span {"Send anyway, the fee is charged even if it fails"}
+                                }
+                            }
+                        } else {
+                            View::empty()
+                        })
+                        div(class="flex space-x-4") {
+                            button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                                focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                                font-semibold sm:text-base disabled:opacity-50",
+                                disabled=failed && !*overridden.get(),
+                                on:click=move |_| approve.resolve(true)
+                            ) {
+                                "Continue to Phantom"
+                            }
+                            button(class="px-5 py-3 rounded-lg shadow-lg bg-slate-700 hover:bg-slate-600 active:bg-slate-800
+                                focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                                font-semibold sm:text-base",
+                                on:click=move |_| pending.resolve(false)
+                            ) {
+                                "Reject"
+                            }
+                        }
+                    }
+                }
+            }
+            None => View::empty(),
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{
+    components::phantom_wallet::report_error,
+    crypto::Provider,
+    error::Error,
+    store::{reducer, Action, ArweaveWallet},
+};
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement};
+
+/// Reads an Arweave JWK keyfile and stores the keypair for paying uploads.
+pub fn load_arweave_keyfile(ctx: ScopeRef<'_>, file: gloo_file::File) {
+    ctx.spawn_local(async move {
+        let result = async {
+            let jwk = gloo_file::futures::read_as_text(&file)
+                .await
+                .map_err(|error| Error::JsError(error.to_string()))?;
+            Provider::from_jwk(&jwk)
+        };
+        match result.await {
+            Ok(provider) => reducer(ctx, Action::ArweaveWalletSet(provider)),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+}
+
+#[component]
+pub fn ArweaveKeyfile<G: Html>(ctx: ScopeRef) -> View<G> {
+    let arweave_wallet = ctx.use_context::<Signal<ArweaveWallet>>();
+    let arweave_address = ctx.create_memo(|| {
+        arweave_wallet
+            .get()
+            .0
+            .as_ref()
+            .and_then(|provider| provider.wallet_address().ok())
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| "No Arweave keyfile loaded".to_string())
+    });
+
+    view! {ctx,
+        div(class="flex space-x-4 items-center") {
+            label(for="arweave-keyfile", class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                font-semibold sm:text-base") {
+                "Arweave Keyfile..."
+                input(id="arweave-keyfile", class="hidden", type="file", accept=".json", on:change=|event: Event| {
+                    let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+                    if let Some(file) = target.files().and_then(|files| files.get(0)) {
+                        load_arweave_keyfile(ctx, gloo_file::File::from(file));
+                    }
+                })
+            }
+            span(class="text-slate-200 font-mono text-sm truncate") {(arweave_address.get())}
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    batch_transfer::{self, pack, BatchTransferRun, TransferRow, TransferStatus},
+    components::phantom_wallet::{report_error, PhantomStatus, PhantomWallet},
+    error::Error,
+    solana_rpc::{Commitment, RpcClient},
+    store::{reducer, rpc_client, Action},
+};
+use solana_sdk::{hash::Hash, signature::Keypair, signature::Signature};
+use std::collections::{HashMap, HashSet};
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement};
+
+/// Reads a transfer sheet and starts a new run, replacing any previous progress.
+fn load_sheet(ctx: ScopeRef<'_>, file: gloo_file::File) {
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let text = gloo_file::futures::read_as_text(&file)
+                .await
+                .map_err(|error| Error::JsError(error.to_string()))?;
+            batch_transfer::load(&rpc, &text).await
+        };
+        match result.await {
+            Ok(run) => reducer(ctx, Action::BatchTransferSet(run)),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+}
+
+/// Sends every pending row, packed into as few transactions as fit and signed with one
+/// approval. Rows are recorded as sent before their transactions are submitted, and rows sent
+/// by an earlier run are confirmed first, so calling this again resumes an interrupted run
+/// without paying anyone twice.
+fn send_transfers<'a>(ctx: ScopeRef<'a>, running: &'a Signal<bool>) -> Result<(), Error> {
+    let wallet = ctx.use_context::<Signal<PhantomWallet>>().get();
+    if wallet.status != PhantomStatus::Connected {
+        return Err(Error::WalletNotConnected);
+    }
+    let mut run = (*ctx.use_context::<Signal<BatchTransferRun>>().get()).clone();
+    if run.rows.is_empty() {
+        return Err(Error::InvalidBatchTransfer(
+            "load a transfer sheet".to_string(),
+        ));
+    }
+    let rpc = rpc_client(ctx);
+    running.set(true);
+    ctx.spawn_local(async move {
+        let save = |run: &BatchTransferRun| reducer(ctx, Action::BatchTransferSet(run.clone()));
+        let result = async {
+            resume_sent(&rpc, &mut run).await;
+            save(&run);
+
+            let pending: Vec<usize> = (0..run.rows.len())
+                .filter(|i| run.rows[*i].status == TransferStatus::Pending)
+                .collect();
+            if pending.is_empty() {
+                return Ok(());
+            }
+            let rows: Vec<&TransferRow> = pending.iter().map(|i| &run.rows[*i]).collect();
+            let mut missing = batch_transfer::missing_token_accounts(&rpc, &rows).await?;
+            // Each missing account is created along with the first transfer to it.
+            let instructions = pending
+                .iter()
+                .map(|i| {
+                    let row = &run.rows[*i];
+                    let create_account = row
+                        .destination()
+                        .map_or(false, |destination| missing.remove(&destination));
+                    (*i, row.instructions(&wallet.public_key, create_account))
+                })
+                .collect();
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let (batches, transactions): (Vec<Vec<usize>>, Vec<_>) =
+                pack(&wallet.public_key, &blockhash, instructions)
+                    .into_iter()
+                    .unzip();
+
+            let signers: Vec<Vec<&Keypair>> = vec![Vec::new(); transactions.len()];
+            let (signed, blockhash) = wallet.sign_all(ctx, &rpc, transactions, &signers).await?;
+            let mut signatures = Vec::with_capacity(signed.len());
+            for (batch, transaction) in batches.iter().zip(&signed) {
+                let signature = transaction.signatures[0];
+                for i in batch {
+                    run.rows[*i].status = TransferStatus::Sent {
+                        signature,
+                        blockhash,
+                    };
+                    run.rows[*i].error = None;
+                }
+                signatures.push(signature);
+            }
+            save(&run);
+
+            for (batch, transaction) in batches.iter().zip(&signed) {
+                // The transaction may have reached the cluster despite the error, so the rows
+                // stay sent until confirmation tells.
+                if let Err(error) = rpc.send_transaction(transaction).await {
+                    for i in batch {
+                        run.rows[*i].error = Some(error.to_string());
+                    }
+                }
+            }
+            save(&run);
+
+            let outcomes =
+                PhantomWallet::confirm_all(&rpc, &signatures, &blockhash, Commitment::Confirmed)
+                    .await;
+            for (batch, outcome) in batches.into_iter().zip(outcomes) {
+                for i in batch {
+                    run.rows[i].settle(outcome.as_ref().map(|_| ()));
+                }
+            }
+            save(&run);
+            Ok::<_, Error>(())
+        };
+        if let Err(error) = result.await {
+            report_error(ctx, error);
+        }
+        running.set(false);
+    });
+    Ok(())
+}
+
+/// Settles rows left as sent by an interrupted run: confirmed rows are done, rows whose
+/// transaction failed or expired go back to pending and the rest stay sent.
+async fn resume_sent(rpc: &RpcClient, run: &mut BatchTransferRun) {
+    let mut sent: HashMap<Hash, Vec<Signature>> = HashMap::new();
+    for row in &run.rows {
+        if let TransferStatus::Sent {
+            signature,
+            blockhash,
+        } = row.status
+        {
+            let signatures = sent.entry(blockhash).or_default();
+            if !signatures.contains(&signature) {
+                signatures.push(signature);
+            }
+        }
+    }
+    for (blockhash, signatures) in sent {
+        let outcomes =
+            PhantomWallet::confirm_all(rpc, &signatures, &blockhash, Commitment::Confirmed).await;
+        let outcomes: HashMap<Signature, Result<(), Error>> =
+            signatures.into_iter().zip(outcomes).collect();
+        for row in run.rows.iter_mut() {
+            if let TransferStatus::Sent { signature, .. } = row.status {
+                if let Some(outcome) = outcomes.get(&signature) {
+                    row.settle(outcome.as_ref().map(|_| ()));
+                }
+            }
+        }
+    }
+}
+
+fn status_label(row: &TransferRow) -> String {
+    let stage = match &row.status {
+        TransferStatus::Pending => "Pending",
+        TransferStatus::Sent { .. } => "Sent",
+        TransferStatus::Confirmed { .. } => "Confirmed",
+    };
+    match &row.error {
+        Some(error) => format!("{}: {}", stage, error),
+        None => stage.to_string(),
+    }
+}
+
+/// Pays out a CSV sheet of recipients and amounts in SOL or SPL tokens.
+#[component]
+pub fn BatchTransferForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let running = ctx.create_signal(false);
+    let run = ctx.use_context::<Signal<BatchTransferRun>>();
+
+    let rows = ctx.create_memo(|| run.get().rows.clone());
+    let progress = ctx.create_memo(|| {
+        let run = run.get();
+        let confirmed = run
+            .rows
+            .iter()
+            .filter(|row| matches!(row.status, TransferStatus::Confirmed { .. }))
+            .count();
+        let recipients = run
+            .rows
+            .iter()
+            .map(|row| row.recipient)
+            .collect::<HashSet<_>>()
+            .len();
+        if run.rows.is_empty() {
+            String::new()
+        } else if run.is_complete() {
+            format!("All {} transfers confirmed", confirmed)
+        } else {
+            format!(
+                "{} of {} transfers to {} recipients confirmed",
+                confirmed,
+                run.rows.len(),
+                recipients
+            )
+        }
+    });
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                label(for="transfer-sheet", class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base") {
+                    "Transfer Sheet..."
+                    input(id="transfer-sheet", class="hidden", type="file", accept=".csv", on:change=|event: Event| {
+                        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+                        if let Some(file) = target.files().and_then(|files| files.get(0)) {
+                            load_sheet(ctx, gloo_file::File::from(file));
+                        }
+                    })
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    disabled=*running.get(),
+                    on:click=|_| {
+                        if *running.get() {
+                            return;
+                        }
+                        if let Err(error) = send_transfers(ctx, running) {
+                            report_error(ctx, error);
+                        }
+                    }
+                ) {
+                    "Send Transfers"
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-slate-700 hover:bg-slate-600 active:bg-slate-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    disabled=*running.get(),
+                    on:click=|_| reducer(ctx, Action::BatchTransferSet(BatchTransferRun::default()))
+                ) {
+                    "Reset"
+                }
+            }
+            p(class="text-slate-200 font-semibold") {(progress.get())}
+            ul(class="text-slate-200 font-mono text-sm") {
+                Indexed {
+                    iterable: rows,
+                    view: |ctx, row| {
+                        let label = status_label(&row);
+                        view! {ctx,
+                            li { (format!("{} to {}: {}", row.amount_label(), row.recipient, label)) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::store::{reducer, Action, Cluster};
+use std::str::FromStr;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement};
+
+const CLUSTERS: [(&str, &str); 4] = [
+    ("mainnet-beta", "Mainnet Beta"),
+    ("devnet", "Devnet"),
+    ("testnet", "Testnet"),
+    ("localnet", "Localnet"),
+];
+
+#[component]
+pub fn ClusterSelector<G: Html>(ctx: ScopeRef) -> View<G> {
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+    let is_custom = ctx.create_memo(|| matches!(*cluster.get(), Cluster::Custom(_)));
+    // Choosing "Custom" only shows the url input: the cluster is switched once a valid url
+    // is entered, so requests never go to an empty url.
+    let editing_custom = ctx.create_signal(false);
+    let show_custom = ctx.create_memo(|| *is_custom.get() || *editing_custom.get());
+    let custom_url = ctx.create_memo(|| match &*cluster.get() {
+        Cluster::Custom(url) => url.clone(),
+        _ => String::new(),
+    });
+
+    let options = View::new_fragment(
+        CLUSTERS
+            .iter()
+            .map(|&(value, label)| {
+                view! {ctx,
+                    option(value=value, selected=cluster.get().to_string() == value) {(label)}
+                }
+            })
+            .collect(),
+    );
+
+    view! {ctx,
+        div(class="flex space-x-4 items-center") {
+            select(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200",
+                on:change=|event: Event| {
+                    let target: HtmlSelectElement = event.target().unwrap().unchecked_into();
+                    match Cluster::from_str(&target.value()) {
+                        Ok(new_cluster) => {
+                            editing_custom.set(false);
+                            reducer(ctx, Action::ClusterSet(new_cluster));
+                        }
+                        Err(_) => editing_custom.set(true),
+                    }
+                }
+            ) {
+                (options)
+                option(value="custom", selected=*show_custom.get()) {"Custom"}
+            }
+            (if *show_custom.get() {
+                view! {ctx,
+                    input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                        type="url",
+                        placeholder="Custom RPC URL",
+                        value=custom_url.get().to_string(),
+                        on:change=|event: Event| {
+                            let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+                            match Cluster::from_str(&target.value()) {
+                                Ok(new_cluster) => {
+                                    editing_custom.set(false);
+                                    reducer(ctx, Action::ClusterSet(new_cluster));
+                                }
+                                Err(error) => reducer(ctx, Action::ErrorSet(Some(error.to_string()))),
+                            }
+                        }
+                    )
+                }
+            } else {
+                View::empty()
+            })
+        }
+    }
+}
@@ -0,0 +1,652 @@
+use crate::{
+    arweave::ArweaveClient,
+    collection::{
+        parse_sheet, validate, CollectionItem, CollectionRun, CollectionSent, ItemStatus,
+    },
+    components::phantom_wallet::{report_error, PhantomStatus, PhantomWallet},
+    confirmation::never_landed,
+    crypto::{generate_keypair, Provider},
+    error::Error,
+    programs::{
+        metadata::{
+            self, Collection, Creator, Data, JsonCollection, JsonCreator, JsonFile, JsonMetadata,
+            JsonProperties,
+        },
+        token,
+    },
+    solana_rpc::{Commitment, RpcClient},
+    store::{
+        arweave_client, arweave_provider, reducer, rpc_client, selected_file, Action, Cluster,
+        FilesVec,
+    },
+};
+use solana_sdk::{
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use std::collections::HashMap;
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement};
+
+/// Items minted per `signAllTransactions` approval.
+const MINT_BATCH_SIZE: usize = 8;
+
+/// Collection-wide settings shared by the collection NFT and every item.
+struct CollectionSettings {
+    name: String,
+    symbol: String,
+    seller_fee_basis_points: u16,
+    creator: Pubkey,
+}
+
+impl CollectionSettings {
+    fn data(&self, name: &str, uri: String, collection: Option<Pubkey>) -> Data {
+        Data {
+            name: name.to_string(),
+            symbol: self.symbol.clone(),
+            uri,
+            seller_fee_basis_points: self.seller_fee_basis_points,
+            creators: Some(vec![Creator {
+                address: self.creator,
+                verified: true,
+                share: 100,
+            }]),
+            collection: collection.map(|key| Collection {
+                verified: false,
+                key,
+            }),
+        }
+    }
+
+    fn json(
+        &self,
+        name: &str,
+        description: &str,
+        image_uri: &str,
+        content_type: &str,
+    ) -> JsonMetadata {
+        JsonMetadata {
+            name: name.to_string(),
+            symbol: self.symbol.clone(),
+            description: description.to_string(),
+            seller_fee_basis_points: self.seller_fee_basis_points,
+            image: image_uri.to_string(),
+            properties: JsonProperties {
+                files: vec![JsonFile {
+                    uri: image_uri.to_string(),
+                    content_type: content_type.to_string(),
+                }],
+                category: content_type
+                    .split_once('/')
+                    .map(|(category, _)| category.to_string()),
+                creators: vec![JsonCreator {
+                    address: self.creator.to_string(),
+                    share: 100,
+                }],
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Reads a metadata sheet, validates it against the selected folder and starts a new run,
+/// replacing any previous progress.
+pub fn load_sheet(ctx: ScopeRef<'_>, file: gloo_file::File) {
+    let files_vec = ctx.use_context::<Signal<FilesVec>>();
+    ctx.spawn_local(async move {
+        let result = async {
+            let text = gloo_file::futures::read_as_text(&file)
+                .await
+                .map_err(|error| Error::JsError(error.to_string()))?;
+            let items = parse_sheet(&file.name(), &text)?;
+            let assets: Vec<String> = files_vec
+                .get()
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect();
+            validate(&items, &assets)?;
+            Ok::<_, Error>(items)
+        };
+        match result.await {
+            Ok(items) => reducer(ctx, Action::CollectionRunSet(CollectionRun::new(items))),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+}
+
+/// Mints the collection NFT, uploads every item's asset and JSON metadata and mints the items
+/// into the collection in batches signed with one approval each. Progress is saved after every
+/// step, so calling this again resumes an interrupted run. The collection NFT and items sent
+/// by an earlier run are confirmed before anything is re-minted.
+pub fn mint_collection<'a>(
+    ctx: ScopeRef<'a>,
+    running: &'a Signal<bool>,
+    name: String,
+    symbol: String,
+    seller_fee_basis_points: u16,
+    collection_image: Option<gloo_file::File>,
+) -> Result<(), Error> {
+    let wallet = ctx.use_context::<Signal<PhantomWallet>>().get();
+    if wallet.status != PhantomStatus::Connected {
+        return Err(Error::WalletNotConnected);
+    }
+    let provider = arweave_provider(ctx)?;
+    let mut run = (*ctx.use_context::<Signal<CollectionRun>>().get()).clone();
+    if run.items.is_empty() {
+        return Err(Error::InvalidCollection(
+            "load a metadata sheet".to_string(),
+        ));
+    }
+    let settings = CollectionSettings {
+        name,
+        symbol,
+        seller_fee_basis_points,
+        creator: wallet.public_key,
+    };
+    settings
+        .data(&settings.name, String::new(), None)
+        .validate()?;
+
+    let arweave = arweave_client(ctx);
+    let rpc = rpc_client(ctx);
+    running.set(true);
+    ctx.spawn_local(async move {
+        let save = |run: &CollectionRun| reducer(ctx, Action::CollectionRunSet(run.clone()));
+        let result = async {
+            let rent = rpc
+                .get_minimum_balance_for_rent_exemption(token::MINT_LEN)
+                .await?;
+            let collection_mint = match run.collection_mint {
+                Some(collection_mint) => collection_mint,
+                None => {
+                    let minted = mint_collection_nft(
+                        ctx,
+                        &wallet,
+                        &rpc,
+                        &arweave,
+                        &provider,
+                        &settings,
+                        collection_image.as_ref(),
+                        rent,
+                        &mut run,
+                    )
+                    .await;
+                    save(&run);
+                    minted?
+                }
+            };
+
+            resume_sent(&rpc, &mut run).await;
+            save(&run);
+
+            for i in 0..run.items.len() {
+                if run.items[i].status == ItemStatus::Pending {
+                    let uploaded = upload_asset(ctx, &arweave, &provider, &mut run.items[i]).await;
+                    run.items[i].error = uploaded.err().map(|error| error.to_string());
+                    save(&run);
+                }
+            }
+            for i in 0..run.items.len() {
+                if let ItemStatus::AssetUploaded { .. } = run.items[i].status {
+                    let uploaded =
+                        upload_metadata(&arweave, &provider, &settings, &mut run.items[i]).await;
+                    run.items[i].error = uploaded.err().map(|error| error.to_string());
+                    save(&run);
+                }
+            }
+
+            let ready: Vec<usize> = (0..run.items.len())
+                .filter(|i| matches!(run.items[*i].status, ItemStatus::MetadataUploaded { .. }))
+                .collect();
+            for batch in ready.chunks(MINT_BATCH_SIZE) {
+                let minted = mint_batch(
+                    ctx,
+                    &wallet,
+                    &rpc,
+                    &settings,
+                    &mut run,
+                    batch,
+                    collection_mint,
+                    rent,
+                )
+                .await;
+                if let Err(error) = minted {
+                    for i in batch {
+                        run.items[*i].error = Some(error.to_string());
+                    }
+                }
+                save(&run);
+            }
+            Ok::<_, Error>(())
+        };
+        if let Err(error) = result.await {
+            report_error(ctx, error);
+        }
+        running.set(false);
+    });
+    Ok(())
+}
+
+/// Uploads the collection image and metadata and mints the collection NFT, waiting for it to
+/// be confirmed so that items can be verified against it. The metadata and the mint
+/// transaction are saved in `run` before they are used, so a collection NFT sent by an earlier
+/// run is confirmed instead of minted again.
+#[allow(clippy::too_many_arguments)]
+async fn mint_collection_nft(
+    ctx: ScopeRef<'_>,
+    wallet: &PhantomWallet,
+    rpc: &RpcClient,
+    arweave: &ArweaveClient,
+    provider: &Provider,
+    settings: &CollectionSettings,
+    image: Option<&gloo_file::File>,
+    rent: u64,
+    run: &mut CollectionRun,
+) -> Result<Pubkey, Error> {
+    let save = |run: &CollectionRun| reducer(ctx, Action::CollectionRunSet(run.clone()));
+    let sent = match run.collection_sent.clone() {
+        Some(sent) => sent,
+        None => {
+            let metadata_uri = match run.collection_metadata_uri.clone() {
+                Some(metadata_uri) => metadata_uri,
+                None => {
+                    let image = image.ok_or_else(|| {
+                        Error::InvalidCollection("select a collection image".to_string())
+                    })?;
+                    let content_type = image.raw_mime_type();
+                    let image_uri = arweave.data_url(&arweave.upload_file(provider, image).await?);
+                    let json = settings.json(&settings.name, "", &image_uri, &content_type);
+                    let metadata_id = arweave
+                        .upload(provider, serde_json::to_vec(&json)?, "application/json")
+                        .await?;
+                    let metadata_uri = arweave.data_url(&metadata_id);
+                    run.collection_metadata_uri = Some(metadata_uri.clone());
+                    save(run);
+                    metadata_uri
+                }
+            };
+            let data = settings.data(&settings.name, metadata_uri, None);
+            data.validate()?;
+
+            let mint = generate_keypair()?;
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let instructions =
+                metadata::create_nft(&wallet.public_key, &mint.pubkey(), &data, rent);
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &instructions,
+                Some(&wallet.public_key),
+                &blockhash,
+            ));
+            let (signed, blockhash) = wallet
+                .sign_all(ctx, rpc, vec![transaction], &[vec![&mint]])
+                .await?;
+            let sent = CollectionSent {
+                mint: mint.pubkey(),
+                signature: signed[0].signatures[0],
+                blockhash,
+            };
+            run.collection_sent = Some(sent.clone());
+            save(run);
+            // The transaction may have reached the cluster despite the error, so it is left
+            // to confirmation.
+            if let Err(error) = rpc.send_transaction(&signed[0]).await {
+                log::error!("{}", error);
+            }
+            sent
+        }
+    };
+    let outcome = PhantomWallet::confirm_all(
+        rpc,
+        &[sent.signature],
+        &sent.blockhash,
+        Commitment::Confirmed,
+    )
+    .await
+    .remove(0);
+    run.settle_collection(outcome.as_ref().map(|_| ()));
+    outcome.map(|_| sent.mint)
+}
+
+/// Settles items left as sent by an interrupted run: confirmed items are minted, items whose
+/// transaction failed or expired go back to being ready to mint and the rest stay sent.
+async fn resume_sent(rpc: &RpcClient, run: &mut CollectionRun) {
+    let mut sent: HashMap<Hash, Vec<(usize, Signature)>> = HashMap::new();
+    for (i, item) in run.items.iter().enumerate() {
+        if let ItemStatus::Sent {
+            signature,
+            blockhash,
+            ..
+        } = item.status
+        {
+            sent.entry(blockhash).or_default().push((i, signature));
+        }
+    }
+    for (blockhash, items) in sent {
+        let signatures: Vec<Signature> = items.iter().map(|(_, signature)| *signature).collect();
+        let outcomes =
+            PhantomWallet::confirm_all(rpc, &signatures, &blockhash, Commitment::Confirmed).await;
+        for ((i, _), outcome) in items.into_iter().zip(outcomes) {
+            settle(&mut run.items[i], outcome);
+        }
+    }
+}
+
+/// Moves a sent item to minted, or back to ready if its transaction can no longer mint it.
+/// Other errors are recorded and the item stays sent, to be confirmed by a later run.
+fn settle(item: &mut CollectionItem, outcome: Result<(), Error>) {
+    if let ItemStatus::Sent {
+        image_uri,
+        metadata_uri,
+        mint,
+        ..
+    } = &item.status
+    {
+        match outcome {
+            Ok(()) => {
+                item.status = ItemStatus::Minted { mint: *mint };
+                item.error = None;
+            }
+            Err(error) if never_landed(&error) => {
+                item.status = ItemStatus::MetadataUploaded {
+                    image_uri: image_uri.clone(),
+                    metadata_uri: metadata_uri.clone(),
+                };
+                item.error = Some(error.to_string());
+            }
+            Err(error) => item.error = Some(error.to_string()),
+        }
+    }
+}
+
+async fn upload_asset(
+    ctx: ScopeRef<'_>,
+    arweave: &ArweaveClient,
+    provider: &Provider,
+    item: &mut CollectionItem,
+) -> Result<(), Error> {
+    let file = selected_file(ctx, &item.file)?;
+    let image_id = arweave.upload_file(provider, &file).await?;
+    item.status = ItemStatus::AssetUploaded {
+        image_uri: arweave.data_url(&image_id),
+        content_type: file.raw_mime_type(),
+    };
+    Ok(())
+}
+
+async fn upload_metadata(
+    arweave: &ArweaveClient,
+    provider: &Provider,
+    settings: &CollectionSettings,
+    item: &mut CollectionItem,
+) -> Result<(), Error> {
+    if let ItemStatus::AssetUploaded {
+        image_uri,
+        content_type,
+    } = &item.status
+    {
+        let mut json = settings.json(&item.name, &item.description, image_uri, content_type);
+        json.attributes = item.attributes.clone();
+        json.collection = Some(JsonCollection {
+            name: settings.name.clone(),
+            family: None,
+        });
+        let metadata_id = arweave
+            .upload(provider, serde_json::to_vec(&json)?, "application/json")
+            .await?;
+        item.status = ItemStatus::MetadataUploaded {
+            image_uri: image_uri.clone(),
+            metadata_uri: arweave.data_url(&metadata_id),
+        };
+    }
+    Ok(())
+}
+
+/// Mints the items at `batch`, each in its own transaction that creates the NFT and verifies it
+/// as a member of `collection_mint`, with a single Phantom approval for the whole batch.
+#[allow(clippy::too_many_arguments)]
+async fn mint_batch(
+    ctx: ScopeRef<'_>,
+    wallet: &PhantomWallet,
+    rpc: &RpcClient,
+    settings: &CollectionSettings,
+    run: &mut CollectionRun,
+    batch: &[usize],
+    collection_mint: Pubkey,
+    rent: u64,
+) -> Result<(), Error> {
+    let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+    let mut indexes = Vec::new();
+    let mut mints: Vec<Keypair> = Vec::new();
+    let mut transactions = Vec::new();
+    for i in batch {
+        let item = &run.items[*i];
+        let metadata_uri = match &item.status {
+            ItemStatus::MetadataUploaded { metadata_uri, .. } => metadata_uri.clone(),
+            _ => continue,
+        };
+        let mint = generate_keypair()?;
+        let data = settings.data(&item.name, metadata_uri, Some(collection_mint));
+        data.validate()?;
+        let mut instructions =
+            metadata::create_nft(&wallet.public_key, &mint.pubkey(), &data, rent);
+        instructions.push(metadata::verify_collection(
+            &mint.pubkey(),
+            &wallet.public_key,
+            &wallet.public_key,
+            &collection_mint,
+        ));
+        transactions.push(Transaction::new_unsigned(Message::new_with_blockhash(
+            &instructions,
+            Some(&wallet.public_key),
+            &blockhash,
+        )));
+        indexes.push(*i);
+        mints.push(mint);
+    }
+
+    let signers: Vec<Vec<&Keypair>> = mints.iter().map(|mint| vec![mint]).collect();
+    let (signed, blockhash) = wallet.sign_all(ctx, rpc, transactions, &signers).await?;
+    // Items are recorded as sent before anything is submitted, so a reload mid-batch confirms
+    // them instead of minting them again.
+    let mut signatures = Vec::with_capacity(signed.len());
+    for ((i, mint), transaction) in indexes.iter().zip(&mints).zip(&signed) {
+        let item = &mut run.items[*i];
+        let signature = transaction.signatures[0];
+        if let ItemStatus::MetadataUploaded {
+            image_uri,
+            metadata_uri,
+        } = &item.status
+        {
+            item.status = ItemStatus::Sent {
+                image_uri: image_uri.clone(),
+                metadata_uri: metadata_uri.clone(),
+                mint: mint.pubkey(),
+                signature,
+                blockhash,
+            };
+            item.error = None;
+        }
+        signatures.push(signature);
+    }
+    reducer(ctx, Action::CollectionRunSet(run.clone()));
+
+    for (i, transaction) in indexes.iter().zip(&signed) {
+        if let Err(error) = rpc.send_transaction(transaction).await {
+            run.items[*i].error = Some(error.to_string());
+        }
+    }
+    reducer(ctx, Action::CollectionRunSet(run.clone()));
+
+    let outcomes =
+        PhantomWallet::confirm_all(rpc, &signatures, &blockhash, Commitment::Confirmed).await;
+    for (i, outcome) in indexes.into_iter().zip(outcomes) {
+        settle(&mut run.items[i], outcome);
+    }
+    Ok(())
+}
+
+fn status_label(item: &CollectionItem) -> String {
+    let stage = match &item.status {
+        ItemStatus::Pending => "Pending",
+        ItemStatus::AssetUploaded { .. } => "Asset uploaded",
+        ItemStatus::MetadataUploaded { .. } => "Metadata uploaded",
+        ItemStatus::Sent { .. } => "Sent",
+        ItemStatus::Minted { .. } => "Minted",
+    };
+    match &item.error {
+        Some(error) => format!("{}: {}", stage, error),
+        None => stage.to_string(),
+    }
+}
+
+#[component]
+pub fn CollectionMintForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let name = ctx.create_signal(String::new());
+    let symbol = ctx.create_signal(String::new());
+    let royalty = ctx.create_signal("0".to_string());
+    let collection_image = ctx.create_signal(None::<gloo_file::File>);
+    let running = ctx.create_signal(false);
+    let run = ctx.use_context::<Signal<CollectionRun>>();
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+
+    let items = ctx.create_memo(|| run.get().items.clone());
+    let progress = ctx.create_memo(|| {
+        let run = run.get();
+        let minted = run
+            .items
+            .iter()
+            .filter(|item| matches!(item.status, ItemStatus::Minted { .. }))
+            .count();
+        if run.items.is_empty() {
+            String::new()
+        } else if run.is_complete() {
+            format!("All {} items minted", minted)
+        } else {
+            format!("{} of {} items minted", minted, run.items.len())
+        }
+    });
+    let collection_image_name = ctx.create_memo(|| match &*collection_image.get() {
+        Some(file) => file.name(),
+        None => String::new(),
+    });
+    let collection_url = ctx.create_memo(|| {
+        run.get()
+            .collection_mint
+            .map(|mint| cluster.get().explorer_address_url(&mint))
+            .unwrap_or_default()
+    });
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                label(for="collection-folder", class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base") {
+                    "Asset Folder..."
+                    input(id="collection-folder", class="hidden", type="file", webkitdirectory=true, multiple=true, on:change=|event: Event| {
+                        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+                        if let Some(file_list) = target.files() {
+                            reducer(ctx, Action::FilesSet(file_list));
+                        }
+                    })
+                }
+                label(for="collection-sheet", class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base") {
+                    "Metadata Sheet..."
+                    input(id="collection-sheet", class="hidden", type="file", accept=".csv,.json", on:change=|event: Event| {
+                        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+                        if let Some(file) = target.files().and_then(|files| files.get(0)) {
+                            load_sheet(ctx, gloo_file::File::from(file));
+                        }
+                    })
+                }
+                label(for="collection-image", class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base") {
+                    "Collection Image..."
+                    input(id="collection-image", class="hidden", type="file", accept="image/*", on:change=|event: Event| {
+                        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+                        collection_image.set(target.files().and_then(|files| files.get(0)).map(gloo_file::File::from));
+                    })
+                }
+                span(class="text-slate-200 text-sm truncate") {
+                    (collection_image_name.get())
+                }
+            }
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Collection name",
+                    maxlength=metadata::MAX_NAME_LENGTH.to_string(),
+                    bind:value=name
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    placeholder="Symbol",
+                    maxlength=metadata::MAX_SYMBOL_LENGTH.to_string(),
+                    bind:value=symbol
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-40",
+                    type="number",
+                    min="0",
+                    max="10000",
+                    placeholder="Royalty (bps)",
+                    bind:value=royalty
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    disabled=*running.get(),
+                    on:click=|_| {
+                        if *running.get() {
+                            return;
+                        }
+                        let result = match royalty.get().trim().parse::<u16>() {
+                            Ok(seller_fee_basis_points) => mint_collection(
+                                ctx,
+                                running,
+                                name.get().trim().to_string(),
+                                symbol.get().trim().to_string(),
+                                seller_fee_basis_points,
+                                (*collection_image.get()).clone(),
+                            ),
+                            Err(_) => Err(Error::InvalidAmount(royalty.get().to_string())),
+                        };
+                        if let Err(error) = result {
+                            report_error(ctx, error);
+                        }
+                    }
+                ) {
+                    "Mint Collection"
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-slate-700 hover:bg-slate-600 active:bg-slate-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    disabled=*running.get(),
+                    on:click=|_| reducer(ctx, Action::CollectionRunSet(CollectionRun::default()))
+                ) {
+                    "Reset"
+                }
+            }
+            p(class="text-slate-200 font-semibold") {(progress.get())}
+            a(class="block text-sm font-mono truncate underline text-slate-200", href=collection_url.get(), target="_blank") {
+                (run.get().collection_mint.map(|mint| mint.to_string()).unwrap_or_default())
+            }
+            ul(class="text-slate-200 font-mono text-sm") {
+                Indexed {
+                    iterable: items,
+                    view: |ctx, item| {
+                        let label = status_label(&item);
+                        view! {ctx,
+                            li { (format!("{} ({}): {}", item.name, item.file, label)) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
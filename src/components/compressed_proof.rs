@@ -0,0 +1,304 @@
+use crate::{
+    components::phantom_wallet::report_error,
+    concurrent_merkle::{ConcurrentMerkleTree, Node},
+    error::Error,
+    programs::{
+        bubblegum::{LeafSchema, MetadataArgs},
+        metadata::{Collection, Creator},
+    },
+    store::rpc_client,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+/// Outcome of checking a proof against the on-chain tree.
+#[derive(Debug, Clone, PartialEq)]
+struct ProofCheck {
+    valid: bool,
+    tree: ConcurrentMerkleTree,
+    /// Asset id of the NFT at the leaf.
+    asset: Pubkey,
+}
+
+/// `TokenStandard::NonFungible`, the only standard Bubblegum mints.
+const NON_FUNGIBLE: u8 = 0;
+
+/// The fields of a compressed NFT its leaf commits to.
+struct LeafFields<'a> {
+    owner: &'a Signal<String>,
+    delegate: &'a Signal<String>,
+    name: &'a Signal<String>,
+    symbol: &'a Signal<String>,
+    uri: &'a Signal<String>,
+    seller_fee_basis_points: &'a Signal<String>,
+    creators: &'a Signal<String>,
+    collection: &'a Signal<String>,
+    collection_verified: &'a Signal<bool>,
+    edition_nonce: &'a Signal<String>,
+    primary_sale_happened: &'a Signal<bool>,
+    is_mutable: &'a Signal<bool>,
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey, Error> {
+    Pubkey::from_str(pubkey.trim()).map_err(|_| Error::InvalidPublicKey)
+}
+
+/// Creators as `address,share[,verified]` lines.
+fn parse_creators(text: &str) -> Result<Vec<Creator>, Error> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let invalid = || Error::InvalidMetadata(format!("invalid creator {}", line));
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let (address, share, verified) = match fields.as_slice() {
+                [address, share] => (address, share, "false"),
+                [address, share, verified] => (address, share, *verified),
+                _ => return Err(invalid()),
+            };
+            Ok(Creator {
+                address: parse_pubkey(address)?,
+                share: share.parse().map_err(|_| invalid())?,
+                verified: verified.parse().map_err(|_| invalid())?,
+            })
+        })
+        .collect()
+}
+
+impl<'a> LeafFields<'a> {
+    /// The leaf of the NFT minted to `tree` with `nonce`, recomputed from its fields.
+    fn leaf(&self, tree: &Pubkey, nonce: u64) -> Result<LeafSchema, Error> {
+        let owner = parse_pubkey(&self.owner.get())?;
+        let delegate = if self.delegate.get().trim().is_empty() {
+            owner
+        } else {
+            parse_pubkey(&self.delegate.get())?
+        };
+        let collection = if self.collection.get().trim().is_empty() {
+            None
+        } else {
+            Some(Collection {
+                verified: *self.collection_verified.get(),
+                key: parse_pubkey(&self.collection.get())?,
+            })
+        };
+        let edition_nonce = self.edition_nonce.get();
+        let metadata = MetadataArgs {
+            name: self.name.get().to_string(),
+            symbol: self.symbol.get().to_string(),
+            uri: self.uri.get().to_string(),
+            seller_fee_basis_points: self.seller_fee_basis_points.get().trim().parse().map_err(
+                |_| {
+                    Error::InvalidMetadata(format!(
+                        "invalid seller fee {}",
+                        self.seller_fee_basis_points.get()
+                    ))
+                },
+            )?,
+            primary_sale_happened: *self.primary_sale_happened.get(),
+            is_mutable: *self.is_mutable.get(),
+            edition_nonce: match edition_nonce.trim() {
+                "" => None,
+                nonce => Some(nonce.parse().map_err(|_| {
+                    Error::InvalidMetadata(format!("invalid edition nonce {}", nonce))
+                })?),
+            },
+            token_standard: Some(NON_FUNGIBLE),
+            collection,
+            uses: None,
+            token_program_version: 0,
+            creators: parse_creators(&self.creators.get())?,
+        };
+        Ok(LeafSchema::new(tree, &owner, &delegate, nonce, &metadata))
+    }
+}
+
+fn parse_node(node: &str) -> Result<Node, Error> {
+    let invalid = || Error::InvalidMerkleTree(format!("invalid node {}", node));
+    let bytes = bs58::decode(node).into_vec().map_err(|_| invalid())?;
+    bytes.try_into().map_err(|_| invalid())
+}
+
+/// Recomputes the leaf from the NFT's owner, delegate and metadata and checks it with a proof,
+/// as returned by an indexer, against the roots stored in the tree account, so the indexer
+/// does not have to be trusted.
+fn check_proof<'a>(
+    ctx: ScopeRef<'a>,
+    tree: &str,
+    index: &str,
+    fields: &LeafFields,
+    proof: &str,
+    check: &'a Signal<Option<ProofCheck>>,
+) -> Result<(), Error> {
+    let tree = parse_pubkey(tree)?;
+    let index = index
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| Error::InvalidMerkleTree(format!("invalid leaf index {}", index)))?;
+    // Bubblegum mints each leaf with the number of NFTs minted before it as its nonce.
+    let leaf = fields.leaf(&tree, index as u64)?;
+    let proof = proof
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|node| !node.is_empty())
+        .map(parse_node)
+        .collect::<Result<Vec<_>, _>>()?;
+    let rpc = rpc_client(ctx);
+    check.set(None);
+    ctx.spawn_local(async move {
+        let result = async {
+            let tree = ConcurrentMerkleTree::fetch(&rpc, &tree).await?;
+            let valid = tree.verify_leaf(&leaf.hash(), &proof, index)?;
+            Ok::<_, Error>(ProofCheck {
+                valid,
+                tree,
+                asset: leaf.id,
+            })
+        };
+        match result.await {
+            Ok(result) => check.set(Some(result)),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+/// Verifies compressed NFT ownership proofs against their concurrent merkle tree.
+#[component]
+pub fn CompressedProofForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let tree = ctx.create_signal(String::new());
+    let index = ctx.create_signal(String::new());
+    let fields = LeafFields {
+        owner: ctx.create_signal(String::new()),
+        delegate: ctx.create_signal(String::new()),
+        name: ctx.create_signal(String::new()),
+        symbol: ctx.create_signal(String::new()),
+        uri: ctx.create_signal(String::new()),
+        seller_fee_basis_points: ctx.create_signal(String::new()),
+        creators: ctx.create_signal(String::new()),
+        collection: ctx.create_signal(String::new()),
+        collection_verified: ctx.create_signal(false),
+        edition_nonce: ctx.create_signal(String::new()),
+        primary_sale_happened: ctx.create_signal(false),
+        is_mutable: ctx.create_signal(true),
+    };
+    let fields = ctx.create_ref(fields);
+    let proof = ctx.create_signal(String::new());
+    let check = ctx.create_signal(None::<ProofCheck>);
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Merkle tree address",
+                    bind:value=tree
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    placeholder="Leaf index",
+                    bind:value=index
+                )
+            }
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Owner",
+                    bind:value=fields.owner
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Delegate (defaults to owner)",
+                    bind:value=fields.delegate
+                )
+            }
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Name",
+                    bind:value=fields.name
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    placeholder="Symbol",
+                    bind:value=fields.symbol
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    type="number",
+                    min="0",
+                    max="10000",
+                    placeholder="Royalty bps",
+                    bind:value=fields.seller_fee_basis_points
+                )
+            }
+            input(class="w-full px-5 py-3 rounded-lg bg-slate-700 text-slate-200",
+                placeholder="Metadata URI",
+                bind:value=fields.uri
+            )
+            textarea(class="w-full px-5 py-3 rounded-lg bg-slate-700 text-slate-200 font-mono text-xs",
+                rows="2",
+                placeholder="Creators, one address,share,verified per line",
+                bind:value=fields.creators
+            )
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Collection (optional)",
+                    bind:value=fields.collection
+                )
+                label(class="text-slate-200 space-x-2") {
+                    input(type="checkbox", bind:checked=fields.collection_verified)
+                    span {"Verified"}
+                }
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    placeholder="Edition nonce",
+                    bind:value=fields.edition_nonce
+                )
+            }
+            div(class="flex space-x-4 items-center") {
+                label(class="text-slate-200 space-x-2") {
+                    input(type="checkbox", bind:checked=fields.primary_sale_happened)
+                    span {"Primary sale happened"}
+                }
+                label(class="text-slate-200 space-x-2") {
+                    input(type="checkbox", bind:checked=fields.is_mutable)
+                    span {"Mutable"}
+                }
+            }
+            textarea(class="w-full px-5 py-3 rounded-lg bg-slate-700 text-slate-200 font-mono text-xs",
+                rows="4",
+                placeholder="Proof nodes, one per line",
+                bind:value=proof
+            )
+            button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                font-semibold sm:text-base",
+                on:click=|_| {
+                    if let Err(error) = check_proof(ctx, &tree.get(), &index.get(), fields, &proof.get(), check) {
+                        report_error(ctx, error);
+                    }
+                }
+            ) {
+                "Verify proof"
+            }
+            (match (*check.get()).clone() {
+                Some(check) => {
+                    let (summary, class) = if check.valid {
+                        ("Valid: the NFT is in the tree as described", "font-semibold text-green-400")
+                    } else {
+                        ("Invalid: the leaf and proof do not match a recent root", "font-semibold text-rose-400")
+                    };
+                    let detail = format!(
+                        "asset {}, depth {}, canopy depth {}, {} leaves, root {}",
+                        check.asset,
+                        check.tree.max_depth,
+                        check.tree.canopy_depth(),
+                        check.tree.leaf_count,
+                        bs58::encode(check.tree.root()).into_string()
+                    );
+                    view! {ctx,
+                        div(class="space-y-2 px-5 py-3 rounded-lg bg-slate-700 text-slate-200") {
+                            p(class=class) {(summary)}
+                            p(class="font-mono text-sm truncate") {(detail)}
+                        }
+                    }
+                }
+                None => View::empty(),
+            })
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::store::{Cluster, Confirmation, ConfirmationStage};
+use sycamore::prelude::*;
+
+#[component]
+pub fn ConfirmationProgress<G: Html>(ctx: ScopeRef) -> View<G> {
+    let confirmation = ctx.use_context::<Signal<Confirmation>>();
+    let stage = ctx.create_memo(|| match &confirmation.get().stage {
+        ConfirmationStage::Idle => String::new(),
+        ConfirmationStage::Sending => "Waiting for wallet approval...".to_string(),
+        ConfirmationStage::Sent => "Sent".to_string(),
+        ConfirmationStage::Processed => "Processed".to_string(),
+        ConfirmationStage::Confirmed => "Confirmed".to_string(),
+        ConfirmationStage::Finalized => "Finalized".to_string(),
+        ConfirmationStage::Expired => "Expired: blockhash is no longer valid".to_string(),
+        ConfirmationStage::Failed(error) => format!("Failed: {}", error),
+    });
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+    let signature = ctx.create_memo(|| {
+        confirmation
+            .get()
+            .signature
+            .map(|s| s.to_string())
+            .unwrap_or_default()
+    });
+    let explorer_url = ctx.create_memo(|| {
+        confirmation
+            .get()
+            .signature
+            .map(|s| cluster.get().explorer_tx_url(&s))
+            .unwrap_or_default()
+    });
+
+    view! {ctx,
+        div(class="text-slate-200 space-y-1") {
+            p(class="font-semibold") {(stage.get())}
+            a(class="block text-sm font-mono truncate underline", href=explorer_url.get(), target="_blank") {
+                (signature.get())
+            }
+        }
+    }
+}
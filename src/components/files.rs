@@ -3,7 +3,7 @@ use crate::transaction::{merklize, ToItems, Transaction};
 use sycamore::futures::ScopeSpawnLocal;
 use sycamore::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{Event, HtmlInputElement};
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement};
 
 pub async fn create_transaction(file: gloo_file::File) -> Result<Transaction, crate::error::Error> {
     let bytes = gloo_file::futures::read_as_bytes(&file).await.unwrap();
@@ -81,3 +81,33 @@ pub fn FilesSelector<G: Html>(ctx: ScopeRef) -> View<G> {
             }
     }
 }
+
+#[derive(Prop)]
+pub struct FileSelectProps<'a> {
+    selected: &'a Signal<String>,
+}
+
+/// Dropdown of the selected files, setting `selected` to the chosen file name.
+#[component]
+pub fn FileSelect<'a, G: Html>(ctx: ScopeRef<'a>, props: FileSelectProps<'a>) -> View<G> {
+    let files_vec = ctx.use_context::<Signal<FilesVec>>();
+
+    view! {ctx,
+        select(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200",
+            on:change=|event: Event| {
+                let target: HtmlSelectElement = event.target().unwrap().unchecked_into();
+                props.selected.set(target.value());
+            }
+        ) {
+            option(value="") {"Select file"}
+            Keyed {
+                iterable: files_vec,
+                view: |ctx, (name, _)| {
+                    let value = name.clone();
+                    view! {ctx, option(value=value) {(name)} }
+                },
+                key: |(name, _)| name.clone()
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    components::phantom_wallet::report_error,
+    error::Error,
+    merkle_distributor::{from_hex, parse_allocations, Distribution, LeafHash},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement};
+
+/// Builds a distribution from a CSV of claimants and amounts and writes its JSON export to
+/// `exported`.
+fn build_distribution<'a>(
+    ctx: ScopeRef<'a>,
+    file: gloo_file::File,
+    hash: LeafHash,
+    exported: &'a Signal<String>,
+) {
+    ctx.spawn_local(async move {
+        let result = async {
+            let text = gloo_file::futures::read_as_text(&file)
+                .await
+                .map_err(|error| Error::JsError(error.to_string()))?;
+            let distribution = Distribution::new(hash, &parse_allocations(&text)?)?;
+            Ok::<_, Error>(serde_json::to_string_pretty(&distribution)?)
+        };
+        match result.await {
+            Ok(json) => exported.set(json),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+}
+
+/// Looks up `claimant` in the exported distribution and checks their proof against `root`,
+/// the published root, since the one in the export is no more trusted than the proofs.
+fn verify_claim(exported: &str, root: &str, claimant: &str) -> Result<String, Error> {
+    let distribution: Distribution = serde_json::from_str(exported)?;
+    let root = from_hex(root.trim())?;
+    let claimant = Pubkey::from_str(claimant.trim()).map_err(|_| Error::InvalidPublicKey)?;
+    let claim = match distribution.claim(&claimant) {
+        Some(claim) => claim,
+        None => return Ok(format!("{} is not in this distribution", claimant)),
+    };
+    if distribution.verify(claim, &root)? {
+        Ok(format!(
+            "Valid proof: claim {} of {} base units",
+            claim.index, claim.amount
+        ))
+    } else {
+        Ok("Invalid proof: the claim does not hash to the published merkle root".to_string())
+    }
+}
+
+/// Merkle tree airdrops: build a distribution and its proofs from a CSV with `claimant` and
+/// `amount` columns, export it as JSON and let claimants verify their proof.
+#[component]
+pub fn MerkleDistributorForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let hash = ctx.create_signal(LeafHash::default());
+    let exported = ctx.create_signal(String::new());
+    let claimant = ctx.create_signal(String::new());
+    let root = ctx.create_signal(String::new());
+    let verification = ctx.create_signal(String::new());
+
+    let summary = ctx.create_memo(
+        || match serde_json::from_str::<Distribution>(&exported.get()) {
+            Ok(distribution) => format!(
+                "Root {} over {} claims totalling {}",
+                distribution.merkle_root, distribution.num_nodes, distribution.token_total
+            ),
+            Err(_) => String::new(),
+        },
+    );
+    let download = ctx.create_memo(|| {
+        format!(
+            "data:application/json;charset=utf-8,{}",
+            String::from(js_sys::encode_uri_component(&exported.get()))
+        )
+    });
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                select(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200",
+                    on:change=|event: Event| {
+                        let target: HtmlSelectElement = event.target().unwrap().unchecked_into();
+                        match LeafHash::from_str(&target.value()) {
+                            Ok(new_hash) => hash.set(new_hash),
+                            Err(error) => report_error(ctx, error),
+                        }
+                    }
+                ) {
+                    option(value="keccak") {"Keccak-256"}
+                    option(value="sha256") {"SHA-256"}
+                }
+                label(for="distribution-sheet", class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base") {
+                    "Claimants CSV..."
+                    input(id="distribution-sheet", class="hidden", type="file", accept=".csv", on:change=|event: Event| {
+                        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+                        if let Some(file) = target.files().and_then(|files| files.get(0)) {
+                            build_distribution(ctx, gloo_file::File::from(file), *hash.get(), exported);
+                        }
+                    })
+                }
+                (if exported.get().is_empty() {
+                    View::empty()
+                } else {
+                    view! {ctx,
+                        a(class="underline text-slate-200", href=download.get(), download="distribution.json") {
+                            "Export JSON"
+                        }
+                    }
+                })
+            }
+            p(class="font-mono text-sm text-slate-200 truncate") {(summary.get())}
+            textarea(class="w-full px-5 py-3 rounded-lg bg-slate-700 text-slate-200 font-mono text-xs",
+                rows="6",
+                placeholder="Distribution JSON",
+                bind:value=exported
+            )
+            input(class="w-full px-5 py-3 rounded-lg bg-slate-700 text-slate-200 font-mono text-sm",
+                placeholder="Published merkle root (hex)",
+                bind:value=root
+            )
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Claimant address",
+                    bind:value=claimant
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=|_| match verify_claim(&exported.get(), &root.get(), &claimant.get()) {
+                        Ok(result) => verification.set(result),
+                        Err(error) => report_error(ctx, error),
+                    }
+                ) {
+                    "Verify claim"
+                }
+            }
+            p(class="text-sm text-slate-200") {(verification.get())}
+        }
+    }
+}
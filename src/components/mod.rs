@@ -1,2 +1,20 @@
+pub mod alert;
+pub mod approval;
+pub mod arweave_wallet;
+pub mod batch_transfer;
+pub mod cluster;
+pub mod collection_mint;
+pub mod compressed_proof;
+pub mod confirmation;
 pub mod files;
+pub mod merkle_distributor;
+pub mod nft_integrity;
+pub mod nft_mint;
+pub mod nonce;
+pub mod notarize;
 pub mod phantom_wallet;
+pub mod priority_fee;
+pub mod token_metadata;
+pub mod token_mint;
+pub mod transfer;
+pub mod verify_notarization;
@@ -0,0 +1,136 @@
+use crate::{
+    components::phantom_wallet::report_error,
+    error::Error,
+    integrity::{self, FileCheck, IntegrityReport},
+    store::{arweave_client, rpc_client},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+fn check_nft<'a>(
+    ctx: ScopeRef<'a>,
+    mint: &str,
+    report: &'a Signal<Option<IntegrityReport>>,
+) -> Result<(), Error> {
+    let mint = Pubkey::from_str(mint.trim()).map_err(|_| Error::InvalidPublicKey)?;
+    let rpc = rpc_client(ctx);
+    let arweave = arweave_client(ctx);
+    report.set(None);
+    ctx.spawn_local(async move {
+        match integrity::check_nft(&rpc, &arweave, &mint).await {
+            Ok(result) => report.set(Some(result)),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+fn check_view<G: Html>(ctx: ScopeRef, check: &FileCheck) -> View<G> {
+    let status = match (
+        &check.error,
+        &check.unverifiable,
+        check.is_intact(),
+        check.is_permanent(),
+    ) {
+        (Some(error), _, _, _) => format!("{}: {}", check.label, error),
+        (None, Some(reason), _, _) => format!("{}: not verifiable, {}", check.label, reason),
+        (None, None, false, _) => format!("{}: data root does not match Arweave", check.label),
+        (None, None, true, false) => format!("{}: intact, not yet mined", check.label),
+        (None, None, true, true) => format!(
+            "{}: intact, {} confirmations",
+            check.label,
+            check.confirmations.unwrap_or_default()
+        ),
+    };
+    let class = if check.is_intact() {
+        "text-green-400"
+    } else if check.unverifiable.is_some() {
+        "text-amber-400"
+    } else {
+        "text-rose-400"
+    };
+    let roots = format!(
+        "computed {}, arweave {}",
+        check
+            .data_root
+            .as_ref()
+            .map(|root| root.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        check
+            .arweave_data_root
+            .as_ref()
+            .map(|root| root.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+    );
+    let uri = check.uri.clone();
+    view! {ctx,
+        li {
+            p(class=class) {(status)}
+            a(class="underline truncate block", href=uri.clone(), target="_blank") {(uri)}
+            p(class="truncate text-slate-400") {(roots)}
+        }
+    }
+}
+
+/// Checks that an NFT's metadata and assets are intact and permanently stored on Arweave.
+#[component]
+pub fn NftIntegrityForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let mint = ctx.create_signal(String::new());
+    let report = ctx.create_signal(None::<IntegrityReport>);
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="NFT mint address",
+                    bind:value=mint
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=|_| {
+                        if let Err(error) = check_nft(ctx, &mint.get(), report) {
+                            report_error(ctx, error);
+                        }
+                    }
+                ) {
+                    "Check integrity"
+                }
+            }
+            (match (*report.get()).clone() {
+                Some(report) => {
+                    let summary = if report.has_mismatch() {
+                        format!("{} failed the integrity check", report.name)
+                    } else if report.has_errors() {
+                        format!("{} could not be fully checked", report.name)
+                    } else if !report.is_verifiable() {
+                        format!("{} could not be verified against Arweave", report.name)
+                    } else if report.is_permanent() {
+                        format!("{} is intact and permanently stored", report.name)
+                    } else {
+                        format!("{} is intact but not yet permanently stored", report.name)
+                    };
+                    let class = if report.is_intact() {
+                        "font-semibold text-green-400"
+                    } else if !report.has_mismatch() && !report.has_errors() {
+                        "font-semibold text-amber-400"
+                    } else {
+                        "font-semibold text-rose-400"
+                    };
+                    let checks = View::new_fragment(
+                        report.checks.iter().map(|check| check_view(ctx, check)).collect(),
+                    );
+                    view! {ctx,
+                        div(class="space-y-2 px-5 py-3 rounded-lg bg-slate-700 text-slate-200") {
+                            p(class=class) {(summary)}
+                            ul(class="space-y-2 font-mono text-sm") {(checks)}
+                        }
+                    }
+                }
+                None => View::empty(),
+            })
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    components::{
+        files::FileSelect,
+        phantom_wallet::{report_error, PhantomStatus, PhantomWallet},
+    },
+    crypto::generate_keypair,
+    error::Error,
+    programs::{
+        metadata::{self, Creator, Data, JsonCreator, JsonFile, JsonMetadata, JsonProperties},
+        token,
+    },
+    solana_rpc::Commitment,
+    store::{
+        arweave_client, arweave_provider, reducer, rpc_client, selected_file, Action, Cluster, Nft,
+        Nfts,
+    },
+};
+use solana_sdk::{message::Message, signature::Signer, transaction::Transaction};
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+/// Uploads the selected file and its Metaplex JSON metadata to Arweave, then mints a one-of-one
+/// NFT pointing at the metadata. The connected wallet pays for the mint, is its update
+/// authority and sole verified creator.
+pub fn mint_nft(
+    ctx: ScopeRef<'_>,
+    file_name: &str,
+    name: String,
+    symbol: String,
+    description: String,
+) -> Result<(), Error> {
+    let wallet = ctx.use_context::<Signal<PhantomWallet>>().get();
+    if wallet.status != PhantomStatus::Connected {
+        return Err(Error::WalletNotConnected);
+    }
+    let provider = arweave_provider(ctx)?;
+    let file = selected_file(ctx, file_name)?;
+    let mut data = Data {
+        name: name.clone(),
+        symbol: symbol.clone(),
+        uri: String::new(),
+        seller_fee_basis_points: 0,
+        creators: Some(vec![Creator {
+            address: wallet.public_key,
+            verified: true,
+            share: 100,
+        }]),
+        collection: None,
+    };
+    data.validate()?;
+
+    let arweave = arweave_client(ctx);
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let content_type = file.raw_mime_type();
+            let image_id = arweave.upload_file(&provider, &file).await?;
+            let image_uri = arweave.data_url(&image_id);
+
+            let json = JsonMetadata {
+                name: name.clone(),
+                symbol,
+                description,
+                seller_fee_basis_points: data.seller_fee_basis_points,
+                image: image_uri.clone(),
+                properties: JsonProperties {
+                    files: vec![JsonFile {
+                        uri: image_uri.clone(),
+                        content_type: content_type.clone(),
+                    }],
+                    category: content_type
+                        .split_once('/')
+                        .map(|(category, _)| category.to_string()),
+                    creators: vec![JsonCreator {
+                        address: wallet.public_key.to_string(),
+                        share: 100,
+                    }],
+                },
+                ..Default::default()
+            };
+            let metadata_id = arweave
+                .upload(&provider, serde_json::to_vec(&json)?, "application/json")
+                .await?;
+            data.uri = arweave.data_url(&metadata_id);
+            data.validate()?;
+
+            let mint = generate_keypair()?;
+            let rent = rpc
+                .get_minimum_balance_for_rent_exemption(token::MINT_LEN)
+                .await?;
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let instructions =
+                metadata::create_nft(&wallet.public_key, &mint.pubkey(), &data, rent);
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &instructions,
+                Some(&wallet.public_key),
+                &blockhash,
+            ));
+            wallet
+                .sign_and_send_with_signers(ctx, rpc, transaction, &[&mint], Commitment::Confirmed)
+                .await?;
+            Ok::<_, Error>(Nft {
+                mint: mint.pubkey(),
+                name,
+                metadata_uri: data.uri,
+                image_uri,
+            })
+        };
+        match result.await {
+            Ok(nft) => reducer(ctx, Action::NftAdd(nft)),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+#[component]
+pub fn NftMintForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let file_name = ctx.create_signal(String::new());
+    let name = ctx.create_signal(String::new());
+    let symbol = ctx.create_signal(String::new());
+    let description = ctx.create_signal(String::new());
+    let nfts = ctx.use_context::<Signal<Nfts>>();
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                FileSelect {
+                    selected: file_name
+                }
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Name",
+                    bind:value=name
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    placeholder="Symbol",
+                    bind:value=symbol
+                )
+            }
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Description",
+                    bind:value=description
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=|_| {
+                        if let Err(error) = mint_nft(
+                            ctx,
+                            &file_name.get(),
+                            name.get().trim().to_string(),
+                            symbol.get().trim().to_string(),
+                            description.get().trim().to_string(),
+                        ) {
+                            report_error(ctx, error);
+                        }
+                    }
+                ) {
+                    "Mint NFT"
+                }
+            }
+            ul(class="text-slate-200 font-mono text-sm") {
+                Keyed {
+                    iterable: nfts,
+                    view: move |ctx, nft| {
+                        let url = cluster.get().explorer_address_url(&nft.mint);
+                        view! {ctx,
+                            li(class="space-x-2") {
+                                a(class="underline", href=url, target="_blank") {(nft.name)}
+                                a(class="underline", href=nft.metadata_uri, target="_blank") {"metadata"}
+                                a(class="underline", href=nft.image_uri, target="_blank") {"asset"}
+                            }
+                        }
+                    },
+                    key: |nft| nft.mint
+                }
+            }
+        }
+    }
+}
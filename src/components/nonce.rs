@@ -0,0 +1,349 @@
+use crate::{
+    components::{
+        phantom_wallet::{report_error, PhantomStatus, PhantomWallet},
+        transfer::{format_sol, parse_sol},
+    },
+    crypto::generate_keypair,
+    error::Error,
+    nonce::{self, NonceAccount, NONCE_ACCOUNT_LEN},
+    solana_rpc::{Commitment, RpcClient},
+    store::rpc_client,
+};
+use solana_sdk::{
+    instruction::Instruction, message::Message, pubkey::Pubkey, signature::Signer,
+    system_instruction, transaction::Transaction,
+};
+use std::{rc::Rc, str::FromStr};
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+/// Nonce account state with the account balance, as last queried.
+type NonceInfo = Option<(NonceAccount, u64)>;
+
+fn connected_wallet(ctx: ScopeRef<'_>) -> Result<Rc<PhantomWallet>, Error> {
+    let wallet = ctx.use_context::<Signal<PhantomWallet>>().get();
+    if wallet.status != PhantomStatus::Connected {
+        return Err(Error::WalletNotConnected);
+    }
+    Ok(wallet)
+}
+
+fn parse_address(address: &str) -> Result<Pubkey, Error> {
+    Pubkey::from_str(address.trim()).map_err(|_| Error::InvalidPublicKey)
+}
+
+fn decode_transaction(encoded: &str) -> Result<Transaction, Error> {
+    Ok(bincode::deserialize(&base64::decode(encoded.trim())?)?)
+}
+
+fn encode_transaction(transaction: &Transaction) -> Result<String, Error> {
+    Ok(base64::encode(bincode::serialize(transaction)?))
+}
+
+/// Creates a nonce account with the connected wallet as its authority and fills in its
+/// address once the transaction is sent.
+fn create_nonce_account<'a>(ctx: ScopeRef<'a>, address: &'a Signal<String>) -> Result<(), Error> {
+    let wallet = connected_wallet(ctx)?;
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let nonce_account = generate_keypair()?;
+            let rent = rpc
+                .get_minimum_balance_for_rent_exemption(NONCE_ACCOUNT_LEN)
+                .await?;
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let instructions = system_instruction::create_nonce_account(
+                &wallet.public_key,
+                &nonce_account.pubkey(),
+                &wallet.public_key,
+                rent,
+            );
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &instructions,
+                Some(&wallet.public_key),
+                &blockhash,
+            ));
+            wallet
+                .sign_and_send_with_signers(
+                    ctx,
+                    rpc,
+                    transaction,
+                    &[&nonce_account],
+                    Commitment::Confirmed,
+                )
+                .await?;
+            Ok::<_, Error>(nonce_account.pubkey())
+        };
+        match result.await {
+            Ok(nonce_account) => address.set(nonce_account.to_string()),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+async fn fetch_info(rpc: &RpcClient, address: &Pubkey) -> Result<(NonceAccount, u64), Error> {
+    let nonce = NonceAccount::fetch(rpc, address).await?;
+    let balance = rpc.get_balance(address).await?.value;
+    Ok((nonce, balance))
+}
+
+fn query_nonce_account<'a>(
+    ctx: ScopeRef<'a>,
+    address: &'a Signal<String>,
+    info: &'a Signal<NonceInfo>,
+) -> Result<(), Error> {
+    let address = parse_address(&address.get())?;
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        match fetch_info(&rpc, &address).await {
+            Ok(nonce) => info.set(Some(nonce)),
+            Err(error) => {
+                info.set(None);
+                report_error(ctx, error);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Sends a single instruction signed by the nonce authority, which must be the connected
+/// wallet.
+fn send_authority_instruction<'a>(
+    ctx: ScopeRef<'a>,
+    address: Pubkey,
+    instruction: impl FnOnce(&Pubkey) -> Instruction + 'a,
+) -> Result<(), Error> {
+    let wallet = connected_wallet(ctx)?;
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let nonce = NonceAccount::fetch(&rpc, &address).await?;
+            if nonce.authority != wallet.public_key {
+                return Err(Error::InvalidNonceAccount(format!(
+                    "authority is {}, not the connected wallet",
+                    nonce.authority
+                )));
+            }
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &[instruction(&wallet.public_key)],
+                Some(&wallet.public_key),
+                &blockhash,
+            ));
+            wallet
+                .sign_and_send(ctx, rpc, transaction, Commitment::Confirmed)
+                .await
+        };
+        if let Err(error) = result.await {
+            report_error(ctx, error);
+        }
+    });
+    Ok(())
+}
+
+/// Builds a SOL transfer that uses the nonce account at `address` instead of a recent
+/// blockhash, has Phantom sign it and writes the encoded transaction to `signed`.
+fn sign_durable_transfer<'a>(
+    ctx: ScopeRef<'a>,
+    address: Pubkey,
+    to: Pubkey,
+    lamports: u64,
+    signed: &'a Signal<String>,
+) -> Result<(), Error> {
+    let wallet = connected_wallet(ctx)?;
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let nonce = NonceAccount::fetch(&rpc, &address).await?;
+            let transfer = system_instruction::transfer(&wallet.public_key, &to, lamports);
+            let transaction =
+                nonce::durable_transaction(&[transfer], &wallet.public_key, &address, &nonce);
+            let transaction = wallet.sign_durable(ctx, &rpc, transaction).await?;
+            encode_transaction(&transaction)
+        };
+        match result.await {
+            Ok(encoded) => signed.set(encoded),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+/// Adds the connected wallet's signature to the encoded durable transaction in `signed`.
+fn cosign<'a>(ctx: ScopeRef<'a>, signed: &'a Signal<String>) -> Result<(), Error> {
+    let wallet = connected_wallet(ctx)?;
+    let transaction = decode_transaction(&signed.get())?;
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let transaction = wallet.sign_durable(ctx, &rpc, transaction).await?;
+            encode_transaction(&transaction)
+        };
+        match result.await {
+            Ok(encoded) => signed.set(encoded),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+/// Submits the fully signed, encoded transaction in `signed` and tracks its confirmation.
+fn submit(ctx: ScopeRef<'_>, signed: &Signal<String>) -> Result<(), Error> {
+    let transaction = decode_transaction(&signed.get())?;
+    transaction.verify().map_err(|_| Error::InvalidSignature)?;
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let sent = rpc.send_transaction(&transaction).await;
+        match PhantomWallet::record_sent(ctx, sent) {
+            Ok(signature) => {
+                PhantomWallet::spawn_confirmation(ctx, rpc, signature, None, Commitment::Confirmed)
+            }
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+/// Creates and manages durable nonce accounts, and signs transfers against them that can be
+/// passed to other signers and submitted at any later time.
+#[component]
+pub fn NonceForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let address = ctx.create_signal(String::new());
+    let info = ctx.create_signal(NonceInfo::None);
+    let withdraw_amount = ctx.create_signal(String::new());
+    let recipient = ctx.create_signal(String::new());
+    let amount = ctx.create_signal(String::new());
+    let signed = ctx.create_signal(String::new());
+
+    let run = move |result: Result<(), Error>| {
+        if let Err(error) = result {
+            report_error(ctx, error);
+        }
+    };
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Nonce account address",
+                    bind:value=address
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| run(create_nonce_account(ctx, address))
+                ) {
+                    "Create nonce account"
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-slate-700 hover:bg-slate-600 active:bg-slate-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| run(query_nonce_account(ctx, address, info))
+                ) {
+                    "Query"
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-slate-700 hover:bg-slate-600 active:bg-slate-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| run(parse_address(&address.get()).and_then(|nonce_account| {
+                        send_authority_instruction(ctx, nonce_account, move |authority| {
+                            system_instruction::advance_nonce_account(&nonce_account, authority)
+                        })
+                    }))
+                ) {
+                    "Advance"
+                }
+            }
+            (match (*info.get()).clone() {
+                Some((nonce, balance)) => {
+                    let authority = format!("Authority: {}", nonce.authority);
+                    let blockhash = format!("Nonce: {}", nonce.blockhash);
+                    let fee = format!("Fee per signature: {} lamports", nonce.lamports_per_signature);
+                    let balance = format!("Balance: {}", format_sol(balance));
+                    view! {ctx,
+                        div(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200") {
+                            p(class="font-mono text-sm truncate") {(authority)}
+                            p(class="font-mono text-sm truncate") {(blockhash)}
+                            p(class="text-sm") {(fee)}
+                            p(class="text-sm") {(balance)}
+                        }
+                    }
+                }
+                None => View::empty(),
+            })
+            div(class="flex space-x-4") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-48",
+                    placeholder="Amount in SOL",
+                    bind:value=withdraw_amount
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-slate-700 hover:bg-slate-600 active:bg-slate-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| {
+                        let result = parse_address(&address.get()).and_then(|nonce_account| {
+                            let lamports = parse_sol(&withdraw_amount.get())?;
+                            send_authority_instruction(ctx, nonce_account, move |authority| {
+                                system_instruction::withdraw_nonce_account(
+                                    &nonce_account,
+                                    authority,
+                                    authority,
+                                    lamports,
+                                )
+                            })
+                        });
+                        run(result)
+                    }
+                ) {
+                    "Withdraw to wallet"
+                }
+            }
+            div(class="flex space-x-4") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Recipient address",
+                    bind:value=recipient
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-48",
+                    placeholder="Amount in SOL",
+                    bind:value=amount
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| {
+                        let result = (|| {
+                            let nonce_account = parse_address(&address.get())?;
+                            let to = parse_address(&recipient.get())?;
+                            let lamports = parse_sol(&amount.get())?;
+                            sign_durable_transfer(ctx, nonce_account, to, lamports, signed)
+                        })();
+                        run(result)
+                    }
+                ) {
+                    "Sign durable transfer"
+                }
+            }
+            textarea(class="w-full px-5 py-3 rounded-lg bg-slate-700 text-slate-200 font-mono text-xs",
+                rows="4",
+                placeholder="Signed durable transaction, base64",
+                bind:value=signed
+            )
+            div(class="flex space-x-4") {
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-slate-700 hover:bg-slate-600 active:bg-slate-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| run(cosign(ctx, signed))
+                ) {
+                    "Add my signature"
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| run(submit(ctx, signed))
+                ) {
+                    "Submit"
+                }
+            }
+        }
+    }
+}
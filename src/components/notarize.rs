@@ -0,0 +1,134 @@
+use crate::{
+    components::{
+        files::FileSelect,
+        phantom_wallet::{report_error, PhantomStatus, PhantomWallet},
+    },
+    error::Error,
+    notarization::NotarizationRecord,
+    programs::memo,
+    solana_rpc::{Commitment, RpcClient},
+    store::{reducer, rpc_client, selected_file, Action, Cluster, Notarization, Notarizations},
+    transaction::Base64,
+};
+use solana_sdk::{message::Message, transaction::Transaction};
+use std::str::FromStr;
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+/// Parses an optional Arweave transaction id, a base64url encoded 32 byte hash.
+pub fn parse_arweave_tx(id: &str) -> Result<Option<Base64>, Error> {
+    let id = id.trim();
+    if id.is_empty() {
+        return Ok(None);
+    }
+    match Base64::from_str(id) {
+        Ok(tx) if tx.0.len() == 32 => Ok(Some(tx)),
+        _ => Err(Error::InvalidArweaveTxId(id.to_string())),
+    }
+}
+
+/// Writes the `data_root`, size and optional Arweave transaction of the selected file to a memo
+/// signed by the connected wallet. The notarization is recorded in the store once sent, and its
+/// slot once confirmed.
+pub fn notarize(ctx: ScopeRef<'_>, file_name: &str, arweave_tx: &str) -> Result<(), Error> {
+    let wallet = ctx.use_context::<Signal<PhantomWallet>>().get();
+    if wallet.status != PhantomStatus::Connected {
+        return Err(Error::WalletNotConnected);
+    }
+    let file = selected_file(ctx, file_name)?;
+    let arweave_tx = parse_arweave_tx(arweave_tx)?;
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let data = gloo_file::futures::read_as_bytes(&file)
+                .await
+                .map_err(|error| Error::JsError(error.to_string()))?;
+            let record = NotarizationRecord::new(data, arweave_tx)?;
+            let instruction = memo::build_memo(&record.memo()?, &[&wallet.public_key]);
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &[instruction],
+                Some(&wallet.public_key),
+                &blockhash,
+            ));
+            let (signature, blockhash) = wallet.sign_and_submit(ctx, &rpc, transaction).await?;
+            reducer(
+                ctx,
+                Action::NotarizationAdd(Notarization {
+                    signature,
+                    slot: None,
+                    file: file.name(),
+                    record,
+                }),
+            );
+            let slot = PhantomWallet::track_confirmation(
+                ctx,
+                &rpc,
+                signature,
+                blockhash,
+                Commitment::Confirmed,
+            )
+            .await?;
+            reducer(ctx, Action::NotarizationConfirmed { signature, slot });
+            Ok::<_, Error>(())
+        };
+        if let Err(error) = result.await {
+            report_error(ctx, error);
+        }
+    });
+    Ok(())
+}
+
+#[component]
+pub fn NotarizeForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let file_name = ctx.create_signal(String::new());
+    let arweave_tx = ctx.create_signal(String::new());
+    let notarizations = ctx.use_context::<Signal<Notarizations>>();
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                FileSelect {
+                    selected: file_name
+                }
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Arweave transaction id (optional)",
+                    bind:value=arweave_tx
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=|_| {
+                        if let Err(error) = notarize(ctx, &file_name.get(), &arweave_tx.get()) {
+                            report_error(ctx, error);
+                        }
+                    }
+                ) {
+                    "Notarize"
+                }
+            }
+            ul(class="text-slate-200 font-mono text-sm") {
+                Keyed {
+                    iterable: notarizations,
+                    view: move |ctx, notarization| {
+                        let url = cluster.get().explorer_tx_url(&notarization.signature);
+                        let slot = notarization
+                            .slot
+                            .map(|slot| format!("slot {}", slot))
+                            .unwrap_or_else(|| "pending".to_string());
+                        let data_root = format!("data_root {}", notarization.record.data_root);
+                        view! {ctx,
+                            li(class="space-x-2") {
+                                a(class="underline", href=url, target="_blank") {(notarization.file)}
+                                span {(data_root)}
+                                span {(slot)}
+                            }
+                        }
+                    },
+                    key: |notarization| (notarization.signature, notarization.slot)
+                }
+            }
+        }
+    }
+}
@@ -2,17 +2,47 @@ use std::str::FromStr;
 use sycamore::futures::ScopeSpawnLocal;
 
 use crate::{
+    confirmation::{self, TransactionState},
     error::Error,
-    store::{reducer, Action},
+    nonce,
+    preview::TransactionPreview,
+    priority_fee::PriorityFee,
+    siws::SignInMessage,
+    solana_rpc::{Commitment, RpcClient},
+    store::{reducer, rpc_client, Action, Confirmation, ConfirmationStage, PendingApproval},
 };
 use serde::{Deserialize, Serialize};
-use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+use solana_sdk::{
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
 use sycamore::prelude::*;
 use wasm_bindgen::{prelude::*, JsCast};
 
+const CONFIRMATION_POLL_MS: i32 = 1_000;
+/// Two minutes of polling, well past the time a blockhash stays valid.
+const CONFIRMATION_MAX_POLLS: u32 = 120;
+const SIGN_IN_TTL_SECS: u64 = 10 * 60;
+const BLOCKHASH_EXPIRED_NOTICE: &str = "The blockhash expired before the transaction was \
+    signed. Approve again to rebuild it with a fresh blockhash and sign it in Phantom.";
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum PhantomResult {
+    #[serde(rename_all = "camelCase")]
+    Signatures {
+        public_key: String,
+        signatures: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Signature {
+        public_key: String,
+        signature: String,
+    },
     #[serde(rename_all = "camelCase")]
     Connect {
         public_key: String,
@@ -33,7 +63,9 @@ pub struct PhantomResponse {
 pub enum PhantomRequest {
     Connect,
     SignTransaction { params: PhantomMethodParams },
+    SignAllTransactions { params: PhantomBatchParams },
     SignAndSendTransaction { params: PhantomMethodParams },
+    SignMessage { params: PhantomMessageParams },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,6 +73,19 @@ pub struct PhantomMethodParams {
     message: String,
 }
 
+/// `message` is serialized as a `Uint8Array` as required by `signMessage`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhantomMessageParams {
+    #[serde(with = "serde_bytes")]
+    message: Vec<u8>,
+    display: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhantomBatchParams {
+    message: Vec<String>,
+}
+
 // #[derive(Debug, Serialize, Deserialize)]
 // #[serde(rename_all = "camelCase")]
 // pub struct PhantomParams {
@@ -71,209 +116,698 @@ impl Default for PhantomWallet {
 }
 
 impl PhantomWallet {
-    async fn request(request: PhantomRequest) -> Result<JsValue, Error> {
-        let window = web_sys::window().unwrap();
-        if let Some(solana) = window.get("solana") {
-            let handle_message_str = wasm_bindgen::JsValue::from_str("_handleMessage");
-            let handle_message_method: js_sys::Function =
-                js_sys::Reflect::get(&*solana, &handle_message_str)
-                    .unwrap()
-                    .into();
-
-            window
-                .remove_event_listener_with_callback("message", &handle_message_method)
-                .unwrap();
-            window
-                .add_event_listener_with_callback("message", &handle_message_method)
-                .unwrap();
-            let is_phantom =
-                js_sys::Reflect::get(&*solana, &wasm_bindgen::JsValue::from_str("isPhantom"))
-                    .unwrap();
-            if is_phantom == JsValue::from(true) {
-                let request_str = wasm_bindgen::JsValue::from_str("request");
-                let request_method: js_sys::Function =
-                    js_sys::Reflect::get(&*solana, &request_str).unwrap().into();
-
-                log::debug!("{:?}", request_method.to_string());
-                let value = serde_wasm_bindgen::to_value(&request).unwrap();
-
-                let resp = request_method.call1(&solana, &value).unwrap();
-                let promise = js_sys::Promise::resolve(&resp);
-                let result = wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
-                log::debug!("fu {:?}", result);
-
-                Ok(result)
-            } else {
-                Err(Error::PhantomWalletNotFound)
-            }
+    /// Returns `window.solana` if it is the Phantom provider.
+    fn provider() -> Result<JsValue, Error> {
+        let solana: JsValue = window()?
+            .get("solana")
+            .ok_or(Error::PhantomWalletNotFound)?
+            .into();
+        let is_phantom = js_sys::Reflect::get(&solana, &JsValue::from_str("isPhantom"))?;
+        if is_phantom == JsValue::from(true) {
+            Ok(solana)
         } else {
             Err(Error::PhantomWalletNotFound)
         }
     }
 
+    fn method(solana: &JsValue, name: &str) -> Result<js_sys::Function, Error> {
+        js_sys::Reflect::get(solana, &JsValue::from_str(name))?
+            .dyn_into::<js_sys::Function>()
+            .map_err(|_| Error::PhantomUnsupportedMethod(name.to_string()))
+    }
+
+    /// Reads the base58 `publicKey` from a provider or connect response.
+    fn read_public_key(value: &JsValue) -> Result<Pubkey, Error> {
+        let pubkey_obj = js_sys::Reflect::get(value, &JsValue::from_str("publicKey"))?;
+        let pubkey = PhantomWallet::method(&pubkey_obj, "toString")?.call0(&pubkey_obj)?;
+        let pubkey = pubkey.as_string().ok_or(Error::InvalidPublicKey)?;
+        Pubkey::from_str(&pubkey).map_err(|_| Error::InvalidPublicKey)
+    }
+
+    async fn request(request: PhantomRequest) -> Result<JsValue, Error> {
+        let window = window()?;
+        let solana = PhantomWallet::provider()?;
+        let handle_message_method = PhantomWallet::method(&solana, "_handleMessage")?;
+        window.remove_event_listener_with_callback("message", &handle_message_method)?;
+        window.add_event_listener_with_callback("message", &handle_message_method)?;
+
+        let request_method = PhantomWallet::method(&solana, "request")?;
+        let value = serde_wasm_bindgen::to_value(&request)?;
+        let resp = request_method
+            .call1(&solana, &value)
+            .map_err(Error::from_provider)?;
+        let promise = js_sys::Promise::resolve(&resp);
+        let result = wasm_bindgen_futures::JsFuture::from(promise)
+            .await
+            .map_err(Error::from_provider)?;
+        log::debug!("request result: {:?}", result);
+        Ok(result)
+    }
+
     pub fn connect(ctx: ScopeRef<'_>) -> Result<(), Error> {
         let wallet_signal = ctx.use_context::<Signal<PhantomWallet>>();
         let wallet = wallet_signal.get();
         match wallet.status {
             PhantomStatus::Disconnected => {
-                let window = web_sys::window().unwrap();
-                if let Some(solana) = window.get("solana") {
-                    let is_phantom = js_sys::Reflect::get(
-                        &*solana,
-                        &wasm_bindgen::JsValue::from_str("isPhantom"),
-                    )
-                    .unwrap();
-                    if is_phantom == JsValue::from(true) {
-                        let connect_str = wasm_bindgen::JsValue::from_str("connect");
-                        let connect: js_sys::Function =
-                            js_sys::Reflect::get(&*solana, &connect_str).unwrap().into();
-                        log::debug!("{:?}", connect.to_string());
-                        let resp = connect.call0(&solana).unwrap();
-
-                        ctx.spawn_local(async move {
-                            let promise = js_sys::Promise::resolve(&resp);
-                            let result =
-                                wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
-                            log::debug!("{:?}", result);
-                            let pubkey_str = wasm_bindgen::JsValue::from_str("publicKey");
-                            let pubkey_obj: js_sys::Object =
-                                js_sys::Reflect::get(&result, &pubkey_str).unwrap().into();
-
-                            let bn_str = wasm_bindgen::JsValue::from_str("toString");
-                            let to_string_fn: js_sys::Function =
-                                js_sys::Reflect::get(&pubkey_obj, &bn_str).unwrap().into();
-
-                            log::debug!("pubkey_obj: {:?}", to_string_fn.call0(&pubkey_obj));
-
-                            let pubkey = to_string_fn.call0(&pubkey_obj).unwrap();
-                            let public_key =
-                                Pubkey::from_str(&pubkey.as_string().unwrap()).unwrap();
-                            PhantomWallet::is_connected();
-                            reducer(
-                                ctx,
-                                Action::WalletSet(PhantomWallet {
-                                    status: PhantomStatus::Connected,
-                                    public_key,
-                                }),
-                            );
-                        });
-
-                        Ok(())
-                    } else {
-                        Err(Error::PhantomWalletNotFound)
+                let solana = PhantomWallet::provider()?;
+                let resp = PhantomWallet::method(&solana, "connect")?.call0(&solana)?;
+                ctx.spawn_local(async move {
+                    let promise = js_sys::Promise::resolve(&resp);
+                    let connected = wasm_bindgen_futures::JsFuture::from(promise)
+                        .await
+                        .map_err(Error::from_provider)
+                        .and_then(|result| PhantomWallet::read_public_key(&result));
+                    match connected {
+                        Ok(public_key) => reducer(
+                            ctx,
+                            Action::WalletSet(PhantomWallet {
+                                status: PhantomStatus::Connected,
+                                public_key,
+                            }),
+                        ),
+                        Err(error) => report_error(ctx, error),
                     }
-                } else {
-                    Err(Error::PhantomWalletNotFound)
-                }
+                });
+                Ok(())
             }
             _ => Ok(()),
         }
     }
-    fn disconnect(ctx: ScopeRef<'_>) -> Result<(), Error> {
-        let window = web_sys::window().unwrap();
-        if let Some(solana) = window.get("solana") {
-            let this = JsValue::null();
-            let disconnect_str = wasm_bindgen::JsValue::from_str("disconnect");
-            let disconnect: js_sys::Function = js_sys::Reflect::get(&*solana, &disconnect_str)
-                .unwrap()
-                .into();
-            let resp = disconnect.call0(&this).unwrap();
+
+    /// Silently reconnects on page load if the user has already trusted this site. Phantom
+    /// rejects the request without a popup otherwise, leaving the wallet `Disconnected`.
+    pub fn reconnect_if_trusted(ctx: ScopeRef<'_>) {
+        let resp = PhantomWallet::provider().and_then(|solana| {
+            let options = js_sys::Object::new();
+            js_sys::Reflect::set(
+                &options,
+                &JsValue::from_str("onlyIfTrusted"),
+                &JsValue::from(true),
+            )?;
+            Ok(PhantomWallet::method(&solana, "connect")?.call1(&solana, &options)?)
+        });
+        if let Ok(resp) = resp {
             ctx.spawn_local(async move {
                 let promise = js_sys::Promise::resolve(&resp);
-                wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
-                PhantomWallet::is_connected();
-                reducer(ctx, Action::WalletSet(PhantomWallet::default()))
+                match wasm_bindgen_futures::JsFuture::from(promise).await {
+                    Ok(_) => sync_wallet(ctx),
+                    Err(_) => log::debug!("site not trusted, staying disconnected"),
+                }
             });
-            Ok(())
-        } else {
-            Err(Error::PhantomWalletNotFound)
         }
     }
-    fn is_connected() -> bool {
-        let window = web_sys::window().unwrap();
-        if let Some(solana) = window.get("solana") {
-            let is_connected_str = wasm_bindgen::JsValue::from_str("isConnected");
-            let is_connected = js_sys::Reflect::get(&solana, &is_connected_str)
-                .unwrap()
-                .as_bool()
-                .unwrap();
-            log::debug!("is_connected: {:?}", is_connected);
-            is_connected
-        } else {
-            false
-        }
-    }
-    fn pubkey(&self) -> Result<PhantomWallet, Error> {
-        #[allow(unused_assignments)]
-        let mut is_connected = false;
-        let mut public_key = Pubkey::default();
-        let window = web_sys::window().unwrap();
-        if let Some(solana) = window.get("solana") {
-            let is_connected_str = wasm_bindgen::JsValue::from_str("isConnected");
-            is_connected = js_sys::Reflect::get(&solana, &is_connected_str)
-                .unwrap()
-                .as_bool()
-                .unwrap();
-            log::debug!("is_connected: {:?}", is_connected);
-            if is_connected {
-                let pubkey_str = wasm_bindgen::JsValue::from_str("publicKey");
-                let pubkey_obj: js_sys::Object =
-                    js_sys::Reflect::get(&solana, &pubkey_str).unwrap().into();
-
-                let bn_str = wasm_bindgen::JsValue::from_str("toString");
-                let to_string_fn: js_sys::Function =
-                    js_sys::Reflect::get(&pubkey_obj, &bn_str).unwrap().into();
-
-                log::debug!("pubkey_obj: {:?}", to_string_fn.call0(&pubkey_obj));
-                if let Ok(pubkey) = to_string_fn.call0(&pubkey_obj) {
-                    public_key = Pubkey::from_str(&pubkey.as_string().unwrap()).unwrap();
-                    log::debug!("pubkey: {:?}", public_key);
-                };
+
+    fn disconnect(ctx: ScopeRef<'_>) -> Result<(), Error> {
+        let solana = PhantomWallet::provider()?;
+        let resp = PhantomWallet::method(&solana, "disconnect")?.call0(&solana)?;
+        ctx.spawn_local(async move {
+            let promise = js_sys::Promise::resolve(&resp);
+            match wasm_bindgen_futures::JsFuture::from(promise).await {
+                Ok(_) => reducer(ctx, Action::WalletSet(PhantomWallet::default())),
+                Err(error) => report_error(ctx, Error::from_provider(error)),
             }
+        });
+        Ok(())
+    }
+
+    fn is_connected(solana: &JsValue) -> Result<bool, Error> {
+        let is_connected = js_sys::Reflect::get(solana, &JsValue::from_str("isConnected"))?
+            .as_bool()
+            .unwrap_or(false);
+        log::debug!("is_connected: {:?}", is_connected);
+        Ok(is_connected)
+    }
+
+    fn pubkey() -> Result<PhantomWallet, Error> {
+        let solana = PhantomWallet::provider()?;
+        if PhantomWallet::is_connected(&solana)? {
+            Ok(PhantomWallet {
+                status: PhantomStatus::Connected,
+                public_key: PhantomWallet::read_public_key(&solana)?,
+            })
         } else {
-            return Err(Error::PhantomWalletNotFound);
-        }
-
-        Ok(PhantomWallet {
-            status: PhantomStatus::Connected,
-            public_key,
-        })
-    }
-
-    pub fn sign_transaction_method() -> Result<(), Error> {
-        let window = web_sys::window().unwrap();
-        if let Some(solana) = window.get("solana") {
-            let this = JsValue::null();
-            let sign_trans_str = wasm_bindgen::JsValue::from_str("signTransaction");
-            let sign_trans_method: js_sys::Function =
-                js_sys::Reflect::get(&*solana, &sign_trans_str)
-                    .unwrap()
-                    .into();
-            // let resp = sign_trans.call0(&this).unwrap();
-            log::debug!("sign_trans_method{:?}", sign_trans_method.to_string());
-            log::debug!(
-                "sign_transaction: {:?}",
-                js_sys::Object::get_own_property_names(&solana)
-            );
-            Ok(())
+            Ok(PhantomWallet::default())
+        }
+    }
+
+    /// Requests a signature for `transaction` from Phantom, verifies it against the
+    /// connected `public_key` and returns the transaction with the signature added.
+    pub async fn sign_transaction(
+        &self,
+        mut transaction: Transaction,
+    ) -> Result<Transaction, Error> {
+        if self.status != PhantomStatus::Connected {
+            return Err(Error::WalletNotConnected);
+        }
+        let message_data = transaction.message_data();
+        let request = PhantomRequest::SignTransaction {
+            params: PhantomMethodParams {
+                message: bs58::encode(&message_data).into_string(),
+            },
+        };
+        let result = PhantomWallet::request(request).await?;
+        match serde_wasm_bindgen::from_value::<PhantomResult>(result)? {
+            PhantomResult::Signature { signature, .. } => {
+                let signature =
+                    Signature::from_str(&signature).map_err(|_| Error::InvalidSignature)?;
+                PhantomWallet::add_signature(&mut transaction, &self.public_key, signature)?;
+                Ok(transaction)
+            }
+            _ => Err(Error::InvalidSignature),
+        }
+    }
+
+    /// Requests signatures for all `transactions` from Phantom in a single approval. Signatures
+    /// are matched back to transactions by position and each one is verified, returning
+    /// [`Error::PartialSignFailure`] with the indexes of any that fail.
+    pub async fn sign_all_transactions(
+        &self,
+        mut transactions: Vec<Transaction>,
+    ) -> Result<Vec<Transaction>, Error> {
+        if self.status != PhantomStatus::Connected {
+            return Err(Error::WalletNotConnected);
+        }
+        let request = PhantomRequest::SignAllTransactions {
+            params: PhantomBatchParams {
+                message: transactions
+                    .iter()
+                    .map(|t| bs58::encode(t.message_data()).into_string())
+                    .collect(),
+            },
+        };
+        let result = PhantomWallet::request(request).await?;
+        let signatures = match serde_wasm_bindgen::from_value::<PhantomResult>(result)? {
+            PhantomResult::Signatures { signatures, .. } => signatures,
+            _ => return Err(Error::InvalidSignature),
+        };
+        if signatures.len() != transactions.len() {
+            return Err(Error::InvalidSignature);
+        }
+
+        let failed: Vec<usize> = transactions
+            .iter_mut()
+            .zip(signatures)
+            .enumerate()
+            .filter_map(|(i, (transaction, signature))| {
+                Signature::from_str(&signature)
+                    .map_err(|_| Error::InvalidSignature)
+                    .and_then(|s| PhantomWallet::add_signature(transaction, &self.public_key, s))
+                    .err()
+                    .map(|_| i)
+            })
+            .collect();
+        if failed.is_empty() {
+            Ok(transactions)
         } else {
-            Err(Error::PhantomWalletNotFound)
+            Err(Error::PartialSignFailure(failed))
         }
     }
-    pub fn sign_transaction(ctx: ScopeRef<'_>, transaction: Transaction) -> Result<(), Error> {
-        let wallet_signal = ctx.use_context::<Signal<PhantomWallet>>();
-        let wallet = wallet_signal.get();
-        if wallet.status == PhantomStatus::Disconnected {
-            let params = PhantomRequest::SignTransaction {
+
+    /// Requests an ed25519 signature over arbitrary `message` bytes, displayed to the user
+    /// as utf8, and verifies it against the connected `public_key`.
+    pub async fn sign_message(&self, message: &[u8]) -> Result<Signature, Error> {
+        if self.status != PhantomStatus::Connected {
+            return Err(Error::WalletNotConnected);
+        }
+        let request = PhantomRequest::SignMessage {
+            params: PhantomMessageParams {
+                message: message.to_vec(),
+                display: "utf8".to_string(),
+            },
+        };
+        let result = PhantomWallet::request(request).await?;
+        match serde_wasm_bindgen::from_value::<PhantomResult>(result)? {
+            PhantomResult::Signature { signature, .. } => {
+                let signature =
+                    Signature::from_str(&signature).map_err(|_| Error::InvalidSignature)?;
+                if signature.verify(self.public_key.as_ref(), message) {
+                    Ok(signature)
+                } else {
+                    Err(Error::InvalidSignature)
+                }
+            }
+            _ => Err(Error::InvalidSignature),
+        }
+    }
+
+    /// Signs a [`SignInMessage`] for the current page's host, valid for `ttl` seconds.
+    pub async fn sign_in(&self, ttl: u64) -> Result<(SignInMessage, Signature), Error> {
+        let domain = window()?.location().host()?;
+        let now = (js_sys::Date::now() / 1_000.0) as u64;
+        let mut message = SignInMessage::new(&domain, self.public_key, now, Some(ttl))?;
+        message.statement = Some("Sign in to WASM Token App".to_string());
+        let signature = self.sign_message(message.to_string().as_bytes()).await?;
+        Ok((message, signature))
+    }
+
+    /// Has Phantom sign and submit `transaction`, returning the signature as soon as it is sent.
+    /// Confirmation is then tracked in the background and each stage is written to the store
+    /// until `commitment` is reached, the transaction fails or its blockhash expires. If the
+    /// blockhash expires while Phantom is open, the user is offered to sign it again with a
+    /// fresh one.
+    pub async fn sign_and_send<'a>(
+        &self,
+        ctx: ScopeRef<'a>,
+        rpc: RpcClient,
+        transaction: Transaction,
+        commitment: Commitment,
+    ) -> Result<Signature, Error> {
+        let (signature, blockhash) = self.sign_and_submit(ctx, &rpc, transaction).await?;
+        PhantomWallet::spawn_confirmation(ctx, rpc, signature, blockhash, commitment);
+        Ok(signature)
+    }
+
+    /// Like [`PhantomWallet::sign_and_send`], for callers that wait for confirmation
+    /// themselves with [`PhantomWallet::track_confirmation`]. Returns the blockhash the
+    /// transaction was sent with, `None` if it is durable.
+    pub async fn sign_and_submit(
+        &self,
+        ctx: ScopeRef<'_>,
+        rpc: &RpcClient,
+        transaction: Transaction,
+    ) -> Result<(Signature, Option<Hash>), Error> {
+        if self.status != PhantomStatus::Connected {
+            return Err(Error::WalletNotConnected);
+        }
+        let mut transaction = PhantomWallet::apply_priority_fee(ctx, rpc, transaction).await?;
+        let durable = nonce::is_durable(rpc, &transaction.message).await?;
+        let mut notice = None;
+        loop {
+            // Refreshed before the approval, so the preview estimates the fee of what is signed.
+            let last_valid_block_height =
+                PhantomWallet::refresh_blockhash(rpc, &mut transaction, durable).await?;
+            PhantomWallet::request_approval(ctx, rpc, &[&transaction.message], notice).await?;
+            PhantomWallet::set_stage(ctx, ConfirmationStage::Sending);
+            let request = PhantomRequest::SignAndSendTransaction {
                 params: PhantomMethodParams {
-                    message: "dingus".to_string(),
+                    message: bs58::encode(transaction.message_data()).into_string(),
                 },
             };
+            let sent = match PhantomWallet::request(request)
+                .await
+                .and_then(|result| Ok(serde_wasm_bindgen::from_value::<PhantomResult>(result)?))
+            {
+                Ok(PhantomResult::Signature { signature, .. }) => {
+                    Signature::from_str(&signature).map_err(|_| Error::InvalidSignature)
+                }
+                Ok(_) => Err(Error::InvalidSignature),
+                Err(error) => Err(error),
+            };
+            let expired = match &sent {
+                Err(error) if !rejected(error) => {
+                    PhantomWallet::blockhash_expired(rpc, last_valid_block_height).await
+                }
+                _ => false,
+            };
+            if expired {
+                PhantomWallet::set_stage(ctx, ConfirmationStage::Expired);
+                notice = Some(BLOCKHASH_EXPIRED_NOTICE);
+                continue;
+            }
+            let blockhash = (!durable).then(|| transaction.message.recent_blockhash);
+            return PhantomWallet::record_sent(ctx, sent).map(|signature| (signature, blockhash));
+        }
+    }
+
+    /// Like [`PhantomWallet::sign_and_send`], for transactions that also need the signatures
+    /// of locally generated `signers`, such as a new account being created. Phantom only
+    /// signs, and the fully signed transaction is submitted through `rpc`.
+    pub async fn sign_and_send_with_signers<'a>(
+        &self,
+        ctx: ScopeRef<'a>,
+        rpc: RpcClient,
+        transaction: Transaction,
+        signers: &[&Keypair],
+        commitment: Commitment,
+    ) -> Result<Signature, Error> {
+        let mut transaction = PhantomWallet::apply_priority_fee(ctx, &rpc, transaction).await?;
+        let durable = nonce::is_durable(&rpc, &transaction.message).await?;
+        let mut notice = None;
+        loop {
+            let last_valid_block_height =
+                PhantomWallet::refresh_blockhash(&rpc, &mut transaction, durable).await?;
+            PhantomWallet::request_approval(ctx, &rpc, &[&transaction.message], notice).await?;
+            PhantomWallet::set_stage(ctx, ConfirmationStage::Sending);
+            let signed = async {
+                let mut transaction = transaction.clone();
+                let message_data = transaction.message_data();
+                for signer in signers {
+                    let signature = signer.sign_message(&message_data);
+                    PhantomWallet::add_signature(&mut transaction, &signer.pubkey(), signature)?;
+                }
+                self.sign_transaction(transaction).await
+            }
+            .await;
+            let expired = match &signed {
+                Err(error) if rejected(error) => false,
+                _ => PhantomWallet::blockhash_expired(&rpc, last_valid_block_height).await,
+            };
+            if expired {
+                PhantomWallet::set_stage(ctx, ConfirmationStage::Expired);
+                notice = Some(BLOCKHASH_EXPIRED_NOTICE);
+                continue;
+            }
+            let sent = match signed {
+                Ok(signed) => rpc.send_transaction(&signed).await,
+                Err(error) => Err(error),
+            };
+            let signature = PhantomWallet::record_sent(ctx, sent)?;
+            let blockhash = (!durable).then(|| transaction.message.recent_blockhash);
+            PhantomWallet::spawn_confirmation(ctx, rpc, signature, blockhash, commitment);
+            return Ok(signature);
+        }
+    }
+
+    /// Has Phantom add its signature to a durable nonce `transaction` once the user approves its
+    /// preview, without sending it, so that other signers can add theirs or it can be submitted
+    /// later. Compute budget instructions are only added while no one has signed yet.
+    pub async fn sign_durable(
+        &self,
+        ctx: ScopeRef<'_>,
+        rpc: &RpcClient,
+        transaction: Transaction,
+    ) -> Result<Transaction, Error> {
+        if !nonce::is_durable(rpc, &transaction.message).await? {
+            return Err(Error::InvalidNonceAccount(
+                "transaction does not use the current nonce of a nonce account".to_string(),
+            ));
+        }
+        let unsigned = transaction
+            .signatures
+            .iter()
+            .all(|signature| *signature == Signature::default());
+        let transaction = if unsigned {
+            PhantomWallet::apply_priority_fee(ctx, rpc, transaction).await?
         } else {
+            transaction
+        };
+        PhantomWallet::request_approval(ctx, rpc, &[&transaction.message], None).await?;
+        self.sign_transaction(transaction).await
+    }
+
+    /// Prepends compute budget instructions for the priority fee level selected in the store.
+    async fn apply_priority_fee(
+        ctx: ScopeRef<'_>,
+        rpc: &RpcClient,
+        transaction: Transaction,
+    ) -> Result<Transaction, Error> {
+        let priority_fee = *ctx.use_context::<Signal<PriorityFee>>().get();
+        priority_fee.apply(rpc, transaction).await
+    }
+
+    /// Replaces the blockhash of the unsigned `transaction` with the latest one, returning the
+    /// last block height at which it can be processed. `durable` transactions, see
+    /// [`nonce::is_durable`], are left as they are and never expire.
+    async fn refresh_blockhash(
+        rpc: &RpcClient,
+        transaction: &mut Transaction,
+        durable: bool,
+    ) -> Result<Option<u64>, Error> {
+        if durable {
+            return Ok(None);
         }
+        let latest = rpc.get_latest_blockhash().await?.value;
+        transaction.message.recent_blockhash = latest.blockhash;
+        Ok(Some(latest.last_valid_block_height))
+    }
+
+    /// Whether the cluster has moved past `last_valid_block_height`, so a transaction signed
+    /// with the matching blockhash can no longer be processed.
+    async fn blockhash_expired(rpc: &RpcClient, last_valid_block_height: Option<u64>) -> bool {
+        let last_valid_block_height = match last_valid_block_height {
+            Some(height) => height,
+            None => return false,
+        };
+        match rpc.get_block_height().await {
+            Ok(block_height) => block_height > last_valid_block_height,
+            Err(error) => {
+                log::error!("{}", error);
+                false
+            }
+        }
+    }
+
+    fn set_stage(ctx: ScopeRef<'_>, stage: ConfirmationStage) {
+        reducer(
+            ctx,
+            Action::ConfirmationSet(Confirmation {
+                signature: None,
+                stage,
+            }),
+        );
+    }
+
+    /// Shows a preview of each message, with its estimated fee and rent, and waits for the user
+    /// to approve them before anything is sent to Phantom. `notice` explains why approval is
+    /// being asked for again.
+    async fn request_approval(
+        ctx: ScopeRef<'_>,
+        rpc: &RpcClient,
+        messages: &[&Message],
+        notice: Option<&str>,
+    ) -> Result<(), Error> {
+        let mut previews = Vec::with_capacity(messages.len());
+        for message in messages {
+            previews.push(TransactionPreview::estimate(rpc, message).await?);
+        }
+        let mut resolve = None;
+        let promise = js_sys::Promise::new(&mut |resolve_fn, _| resolve = Some(resolve_fn));
+        let resolve = resolve.ok_or(Error::JsFutureError)?;
+        reducer(
+            ctx,
+            Action::ApprovalSet(Some(PendingApproval {
+                previews,
+                notice: notice.map(str::to_string),
+                resolve: resolve.clone(),
+            })),
+        );
+        let approved = wasm_bindgen_futures::JsFuture::from(promise).await?;
+        reducer(ctx, Action::ApprovalClear(resolve));
+        if approved.as_bool() == Some(true) {
+            Ok(())
+        } else {
+            Err(Error::TransactionRejected)
+        }
+    }
+
+    /// Adds the signatures of each transaction's local `signers` and has Phantom sign all of
+    /// `transactions` in a single approval. All transactions but durable nonce ones are given
+    /// the latest blockhash, and are signed again with a fresh one if it expires before Phantom
+    /// returns. Returns the signed transactions, in order, and the latest blockhash they were
+    /// signed with. They are not submitted, so callers can record their signatures first.
+    pub async fn sign_all(
+        &self,
+        ctx: ScopeRef<'_>,
+        rpc: &RpcClient,
+        transactions: Vec<Transaction>,
+        signers: &[Vec<&Keypair>],
+    ) -> Result<(Vec<Transaction>, Hash), Error> {
+        let mut budgeted = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            budgeted.push(PhantomWallet::apply_priority_fee(ctx, rpc, transaction).await?);
+        }
+        let mut transactions = budgeted;
+        let mut durable = Vec::with_capacity(transactions.len());
+        for transaction in &transactions {
+            durable.push(nonce::is_durable(rpc, &transaction.message).await?);
+        }
+        let mut notice = None;
+        loop {
+            let messages: Vec<&Message> = transactions.iter().map(|t| &t.message).collect();
+            PhantomWallet::request_approval(ctx, rpc, &messages, notice).await?;
+            let latest = rpc.get_latest_blockhash().await?.value;
+            let mut last_valid_block_height = None;
+            let mut signed = transactions.clone();
+            for ((transaction, signers), durable) in signed.iter_mut().zip(signers).zip(&durable) {
+                if !durable {
+                    transaction.message.recent_blockhash = latest.blockhash;
+                    last_valid_block_height = Some(latest.last_valid_block_height);
+                }
+                let message_data = transaction.message_data();
+                for signer in signers {
+                    let signature = signer.sign_message(&message_data);
+                    PhantomWallet::add_signature(transaction, &signer.pubkey(), signature)?;
+                }
+            }
+            let signed = self.sign_all_transactions(signed).await?;
+            if PhantomWallet::blockhash_expired(rpc, last_valid_block_height).await {
+                // Previews of the new approval estimate fees with a blockhash that is valid.
+                let latest = rpc.get_latest_blockhash().await?.value.blockhash;
+                for (transaction, durable) in transactions.iter_mut().zip(&durable) {
+                    if !durable {
+                        transaction.message.recent_blockhash = latest;
+                    }
+                }
+                notice = Some(BLOCKHASH_EXPIRED_NOTICE);
+                continue;
+            }
+            return Ok((signed, latest.blockhash));
+        }
+    }
+
+    /// Polls until each of `signatures`, all built with `blockhash`, reaches `commitment`,
+    /// fails or expires, returning the outcome of each in order. Expiry is decided by
+    /// [`confirmation::poll`], and transactions still unresolved after
+    /// [`CONFIRMATION_MAX_POLLS`] polls fail with [`Error::ConfirmationTimeout`].
+    pub async fn confirm_all(
+        rpc: &RpcClient,
+        signatures: &[Signature],
+        blockhash: &Hash,
+        commitment: Commitment,
+    ) -> Vec<Result<(), Error>> {
+        let mut outcomes: Vec<Option<Result<(), Error>>> =
+            signatures.iter().map(|_| None).collect();
+        for _ in 0..CONFIRMATION_MAX_POLLS {
+            if outcomes.iter().all(Option::is_some) {
+                break;
+            }
+            sleep(CONFIRMATION_POLL_MS).await;
+            let states = match confirmation::poll(rpc, signatures, Some(blockhash)).await {
+                Ok(states) => states,
+                Err(error) => {
+                    log::error!("{}", error);
+                    continue;
+                }
+            };
+            for (outcome, state) in outcomes.iter_mut().zip(states) {
+                if outcome.is_none() {
+                    *outcome = state.outcome(commitment);
+                }
+            }
+        }
+        outcomes
+            .into_iter()
+            .map(|outcome| outcome.unwrap_or(Err(Error::ConfirmationTimeout)))
+            .collect()
+    }
+
+    /// Records the outcome of submitting a transaction in the store.
+    pub fn record_sent(
+        ctx: ScopeRef<'_>,
+        sent: Result<Signature, Error>,
+    ) -> Result<Signature, Error> {
+        let (signature, stage) = match &sent {
+            Ok(signature) => (Some(*signature), ConfirmationStage::Sent),
+            Err(error) => (None, ConfirmationStage::Failed(error.to_string())),
+        };
+        reducer(
+            ctx,
+            Action::ConfirmationSet(Confirmation { signature, stage }),
+        );
+        sent
+    }
+
+    /// Tracks the confirmation of `signature` in the background. The outcome is only recorded
+    /// in the store, see [`PhantomWallet::track_confirmation`].
+    pub fn spawn_confirmation<'a>(
+        ctx: ScopeRef<'a>,
+        rpc: RpcClient,
+        signature: Signature,
+        blockhash: Option<Hash>,
+        commitment: Commitment,
+    ) {
+        ctx.spawn_local(async move {
+            let _ = PhantomWallet::track_confirmation(ctx, &rpc, signature, blockhash, commitment)
+                .await;
+        });
+    }
+
+    /// Polls until `commitment` is reached, recording each new stage and returning the slot
+    /// the transaction landed in. `blockhash` is `None` for durable transactions, which do not
+    /// expire; others are reported expired once [`confirmation::poll`] finds their blockhash
+    /// invalid and no record of them in the ledger history. Gives up with
+    /// [`Error::ConfirmationTimeout`], or the last RPC error, after [`CONFIRMATION_MAX_POLLS`]
+    /// polls.
+    pub async fn track_confirmation(
+        ctx: ScopeRef<'_>,
+        rpc: &RpcClient,
+        signature: Signature,
+        blockhash: Option<Hash>,
+        commitment: Commitment,
+    ) -> Result<u64, Error> {
+        let set_stage = |stage| {
+            reducer(
+                ctx,
+                Action::ConfirmationSet(Confirmation {
+                    signature: Some(signature),
+                    stage,
+                }),
+            )
+        };
+        let fail = |error: Error| {
+            set_stage(ConfirmationStage::Failed(error.to_string()));
+            Err(error)
+        };
+        let mut reached: Option<Commitment> = None;
+        let mut last_error = None;
+        for _ in 0..CONFIRMATION_MAX_POLLS {
+            sleep(CONFIRMATION_POLL_MS).await;
+            let state = match confirmation::poll(rpc, &[signature], blockhash.as_ref()).await {
+                Ok(states) => {
+                    // Only an RPC error on the final poll is reported instead of a timeout.
+                    last_error = None;
+                    states
+                        .into_iter()
+                        .next()
+                        .unwrap_or(TransactionState::Unseen)
+                }
+                Err(error) => {
+                    log::error!("{}", error);
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+            match state {
+                TransactionState::Seen(status) => {
+                    if let Some(err) = &status.err {
+                        return fail(Error::TransactionFailed(err.to_string()));
+                    }
+                    if let Some(level) = status.confirmation_status {
+                        if reached != Some(level) {
+                            reached = Some(level);
+                            set_stage(level.into());
+                        }
+                        if level >= commitment {
+                            return Ok(status.slot);
+                        }
+                    }
+                }
+                TransactionState::Unseen => {}
+                TransactionState::Expired => {
+                    set_stage(ConfirmationStage::Expired);
+                    return Err(Error::BlockhashExpired);
+                }
+            }
+        }
+        fail(last_error.unwrap_or(Error::ConfirmationTimeout))
+    }
+
+    /// Verifies `signature` over the transaction message and places it in the
+    /// signer position of `public_key`.
+    pub fn add_signature(
+        transaction: &mut Transaction,
+        public_key: &Pubkey,
+        signature: Signature,
+    ) -> Result<(), Error> {
+        if !signature.verify(public_key.as_ref(), &transaction.message_data()) {
+            return Err(Error::InvalidSignature);
+        }
+        let num_signers = transaction.message.header.num_required_signatures as usize;
+        let position = transaction.message.account_keys[..num_signers]
+            .iter()
+            .position(|key| key == public_key)
+            .ok_or(Error::InvalidSignature)?;
+        if transaction.signatures.len() < num_signers {
+            transaction
+                .signatures
+                .resize(num_signers, Signature::default());
+        }
+        transaction.signatures[position] = signature;
         Ok(())
     }
 
+    /// Transfers `lamports` from the connected wallet to `to`. The transaction is built with a
+    /// fresh blockhash from the selected cluster, then signed, sent and tracked in the store.
     pub fn create_transfer_transaction(
         ctx: ScopeRef<'_>,
         to: &Pubkey,
@@ -281,81 +815,156 @@ impl PhantomWallet {
     ) -> Result<(), Error> {
         let wallet_signal = ctx.use_context::<Signal<PhantomWallet>>();
         let wallet = wallet_signal.get();
-        if wallet.status == PhantomStatus::Disconnected {
-            let params = PhantomRequest::SignTransaction {
-                params: PhantomMethodParams {
-                    message: "dingus".to_string(),
-                },
-            };
-        } else {
+        if wallet.status != PhantomStatus::Connected {
+            return Err(Error::WalletNotConnected);
         }
+        let instruction = system_instruction::transfer(&wallet.public_key, to, lamports);
+        let rpc = rpc_client(ctx);
+        ctx.spawn_local(async move {
+            let result = async {
+                let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+                let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                    &[instruction],
+                    Some(&wallet.public_key),
+                    &blockhash,
+                ));
+                wallet
+                    .sign_and_send(ctx, rpc, transaction, Commitment::Confirmed)
+                    .await
+            };
+            if let Err(error) = result.await {
+                report_error(ctx, error);
+            }
+        });
         Ok(())
     }
 }
 
+/// Phantom provider events that are mirrored into the store.
+const WALLET_EVENTS: [&str; 3] = ["connect", "disconnect", "accountChanged"];
+
+/// Closures registered with `window.solana.on`. Each re-dispatches its event on
+/// `#message-target` so the [`Wallet`] component can update the store within its scope.
+/// The listeners are removed from the provider when this is dropped.
+pub struct PhantomListeners {
+    solana: JsValue,
+    closures: Vec<(&'static str, Closure<dyn FnMut(JsValue)>)>,
+}
+
+impl PhantomListeners {
+    pub fn subscribe() -> Result<Self, Error> {
+        let solana = PhantomWallet::provider()?;
+        let on = PhantomWallet::method(&solana, "on")?;
+
+        let closures = WALLET_EVENTS
+            .iter()
+            .map(|&event| {
+                let closure = Closure::wrap(Box::new(move |_: JsValue| {
+                    dispatch_wallet_event(event);
+                }) as Box<dyn FnMut(JsValue)>);
+                on.call2(
+                    &solana,
+                    &JsValue::from_str(event),
+                    closure.as_ref().unchecked_ref(),
+                )?;
+                Ok((event, closure))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self { solana, closures })
+    }
+}
+
+impl Drop for PhantomListeners {
+    fn drop(&mut self) {
+        if let Ok(remove) = PhantomWallet::method(&self.solana, "removeListener") {
+            for (event, closure) in self.closures.iter() {
+                let _ = remove.call2(
+                    &self.solana,
+                    &JsValue::from_str(event),
+                    closure.as_ref().unchecked_ref(),
+                );
+            }
+        }
+    }
+}
+
+fn dispatch_wallet_event(event: &str) {
+    let target = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id("message-target"));
+    if let (Some(target), Ok(event)) = (target, web_sys::Event::new(event)) {
+        if let Err(error) = target.dispatch_event(&event) {
+            log::error!("{:?}", error);
+        }
+    }
+}
+
+/// Whether the user turned down a request, which is then never offered again even if its
+/// blockhash has expired in the meantime.
+fn rejected(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::PhantomUserRejected(_) | Error::TransactionRejected
+    )
+}
+
+fn window() -> Result<web_sys::Window, Error> {
+    web_sys::window().ok_or_else(|| Error::JsError("no window".to_string()))
+}
+
+/// Reads the current account from the provider and stores it.
+fn sync_wallet(ctx: ScopeRef<'_>) {
+    match PhantomWallet::pubkey() {
+        Ok(wallet) => reducer(ctx, Action::WalletSet(wallet)),
+        Err(error) => report_error(ctx, error),
+    }
+}
+
+/// Stores `error` so it can be shown to the user instead of panicking.
+pub fn report_error(ctx: ScopeRef<'_>, error: Error) {
+    log::error!("{}", error);
+    reducer(ctx, Action::ErrorSet(Some(error.to_string())));
+}
+
+/// Resolves after `millis` milliseconds using `setTimeout`.
+pub async fn sleep(millis: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
 #[component]
 pub fn Wallet<G: Html>(ctx: ScopeRef) -> View<G> {
-    // let request = PhantomRequest {
-    //     method: PhantomWalletMethod::Connect,
-    //     params: None,
-    // };
-    // PhantomWallet::request(request).unwrap();
-
-    // let window = web_sys::window().expect("should have a window in this context");
-    // let document = window.document().expect("window should have a document");
-    // let a = Closure::wrap(Box::new(move |message_event: web_sys::MessageEvent| {
-    //     // log::debug!("message event: {:?}", message_event.data());
-    //     let data = message_event.data();
-    //     if let Ok(value) = serde_wasm_bindgen::from_value::<PhantomResponse>(data) {
-    //         match value.result {
-    //             PhantomResult::Connect { public_key } => {
-    //                 log::debug!("public_key: {:?}", public_key);
-    //                 let new_event = web_sys::Event::new("connect").unwrap();
-    //                 document
-    //                     .get_element_by_id("message-target")
-    //                     .expect("#message-target should exist")
-    //                     .dyn_ref::<web_sys::HtmlElement>()
-    //                     .expect("message-target should be an html element")
-    //                     .dispatch_event(&new_event)
-    //                     .unwrap();
-    //             }
-    //             PhantomResult::Disconnect => {
-    //                 let new_event = web_sys::Event::new("disconnect").unwrap();
-    //                 log::debug!("disconnected");
-    //                 document
-    //                     .get_element_by_id("message-target")
-    //                     .expect("#message-target should exist")
-    //                     .dyn_ref::<web_sys::HtmlElement>()
-    //                     .expect("message-target should be an html element")
-    //                     .dispatch_event(&new_event)
-    //                     .unwrap();
-    //             }
-    //         }
-    //     }
-
-    //     // ding.set(true);
-    // }) as Box<dyn Fn(_)>);
-    // window
-    //     .add_event_listener_with_callback("message", a.as_ref().unchecked_ref())
-    //     .unwrap();
-    // a.forget();
+    match PhantomListeners::subscribe() {
+        Ok(listeners) => {
+            ctx.create_ref(listeners);
+        }
+        Err(error) => log::warn!("wallet events unavailable: {}", error),
+    }
 
     let wallet_signal = ctx.use_context::<Signal<PhantomWallet>>();
 
     view! {ctx, div(id="message-target",
-        // on:connect={|event: web_sys::Event| {
-        //     log::debug!("message-target: {:?}", event.type_());
-        //     let wallet = wallet_sig.get().pubkey().unwrap();
-        //     wallet_sig.set(wallet);
-        //  }}
+        on:connect=|_| sync_wallet(ctx),
+        on:disconnect=|_| reducer(ctx, Action::WalletSet(PhantomWallet::default())),
+        on:accountChanged=|_| sync_wallet(ctx)
         ) {
             button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
                 focus:outline-none text-sm text-slate-200 uppercase tracking-wider
                 font-semibold sm:text-base",
                 on:click=|_| {
-                    if wallet_signal.get().status == PhantomStatus::Connected {
-                        PhantomWallet::disconnect(ctx).unwrap();
-                    } else { PhantomWallet::connect(ctx).unwrap(); }
+                    let result = if wallet_signal.get().status == PhantomStatus::Connected {
+                        PhantomWallet::disconnect(ctx)
+                    } else {
+                        PhantomWallet::connect(ctx)
+                    };
+                    if let Err(error) = result {
+                        report_error(ctx, error);
+                    }
                 }
             ) {
                 (if wallet_signal.get().status == PhantomStatus::Connected {
@@ -368,17 +977,63 @@ pub fn Wallet<G: Html>(ctx: ScopeRef) -> View<G> {
                 focus:outline-none text-sm text-slate-200 uppercase tracking-wider
                 font-semibold sm:text-base",
                 on:click=|_| {
-                    // PhantomWallet::sign_transaction(ctx, ).unwrap();
+                    let wallet = wallet_signal.get();
+                    let rpc = rpc_client(ctx);
+                    ctx.spawn_local(async move {
+                        let result = async {
+                            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+                            let instruction = system_instruction::transfer(
+                                &wallet.public_key,
+                                &wallet.public_key,
+                                0,
+                            );
+                            let transaction = Transaction::new_unsigned(
+                                Message::new_with_blockhash(
+                                    &[instruction],
+                                    Some(&wallet.public_key),
+                                    &blockhash,
+                                ),
+                            );
+                            // Previewed like every other transaction before Phantom signs it.
+                            PhantomWallet::request_approval(ctx, &rpc, &[&transaction.message], None)
+                                .await?;
+                            wallet.sign_transaction(transaction).await
+                        };
+                        match result.await {
+                            Ok(signed) => log::debug!("signed: {:?}", signed.signatures),
+                            Err(error) => report_error(ctx, error),
+                        }
+                    })
                 }
             ) {
                 "Sign Transaction"
             }
+            button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                font-semibold sm:text-base",
+                on:click=|_| {
+                    let wallet = wallet_signal.get();
+                    ctx.spawn_local(async move {
+                        match wallet.sign_in(SIGN_IN_TTL_SECS).await {
+                            Ok((message, signature)) => {
+                                log::debug!("{}", message);
+                                log::debug!("signature: {}", signature);
+                            }
+                            Err(error) => report_error(ctx, error),
+                        }
+                    })
+                }
+            ) {
+                "Sign In"
+            }
             button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
                 focus:outline-none text-sm text-slate-200 uppercase tracking-wider
                 font-semibold sm:text-base",
                 on:click=|_| {
                     ctx.spawn_local(async move {
-                        PhantomWallet::request(PhantomRequest::Connect).await.unwrap();
+                        if let Err(error) = PhantomWallet::request(PhantomRequest::Connect).await {
+                            report_error(ctx, error);
+                        }
                     })
 
                 }
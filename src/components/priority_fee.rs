@@ -0,0 +1,49 @@
+use crate::priority_fee::PriorityFee;
+use crate::store::{reducer, Action};
+use std::str::FromStr;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlSelectElement};
+
+const LEVELS: [(&str, &str); 4] = [
+    ("off", "No priority fee"),
+    ("low", "Low priority"),
+    ("medium", "Medium priority"),
+    ("high", "High priority"),
+];
+
+/// Selects how much priority fee is added to every transaction the app sends.
+#[component]
+pub fn PriorityFeeSelector<G: Html>(ctx: ScopeRef) -> View<G> {
+    let priority_fee = ctx.use_context::<Signal<PriorityFee>>();
+
+    let options = View::new_fragment(
+        LEVELS
+            .iter()
+            .map(|&(value, label)| {
+                view! {ctx,
+                    option(value=value, selected=priority_fee.get().to_string() == value) {(label)}
+                }
+            })
+            .collect(),
+    );
+
+    view! {ctx,
+        div(class="flex space-x-4 items-center") {
+            select(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200",
+                on:change=|event: Event| {
+                    let target: HtmlSelectElement = event.target().unwrap().unchecked_into();
+                    match PriorityFee::from_str(&target.value()) {
+                        Ok(new_priority_fee) => reducer(ctx, Action::PriorityFeeSet(new_priority_fee)),
+                        Err(error) => reducer(ctx, Action::ErrorSet(Some(error.to_string()))),
+                    }
+                }
+            ) {
+                (options)
+            }
+            p(class="text-sm text-slate-400") {
+                "Percentile of recent fees paid to prioritize transactions"
+            }
+        }
+    }
+}
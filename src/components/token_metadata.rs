@@ -0,0 +1,231 @@
+use crate::{
+    components::{
+        files::FileSelect,
+        phantom_wallet::{report_error, PhantomStatus, PhantomWallet},
+    },
+    error::Error,
+    programs::metadata::{
+        self, Data, JsonFile, JsonMetadata, JsonProperties, Metadata, MAX_NAME_LENGTH,
+        MAX_SYMBOL_LENGTH,
+    },
+    solana_rpc::Commitment,
+    store::{arweave_client, arweave_provider, rpc_client, selected_file},
+};
+use solana_sdk::{message::Message, pubkey::Pubkey, transaction::Transaction};
+use std::str::FromStr;
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+/// Uploads `file_name` as the logo of `mint` along with fungible token JSON metadata, then
+/// creates the mint's metadata account, or with `update` replaces the name, symbol and uri of
+/// an existing one. Creating requires the connected wallet to be the mint authority, updating
+/// requires it to be the update authority of mutable metadata.
+pub fn save_token_metadata(
+    ctx: ScopeRef<'_>,
+    mint: Pubkey,
+    file_name: &str,
+    name: String,
+    symbol: String,
+    description: String,
+    update: bool,
+) -> Result<(), Error> {
+    let wallet = ctx.use_context::<Signal<PhantomWallet>>().get();
+    if wallet.status != PhantomStatus::Connected {
+        return Err(Error::WalletNotConnected);
+    }
+    let provider = arweave_provider(ctx)?;
+    let file = selected_file(ctx, file_name)?;
+    let mut data = Data {
+        name: name.clone(),
+        symbol: symbol.clone(),
+        ..Default::default()
+    };
+    data.validate()?;
+
+    let arweave = arweave_client(ctx);
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            // Check the update authority before paying for uploads.
+            if update {
+                let account = rpc
+                    .get_account_info(&metadata::find_metadata_account(&mint))
+                    .await?
+                    .value
+                    .ok_or_else(|| {
+                        Error::InvalidMetadata(format!("no metadata account for {}", mint))
+                    })?;
+                let current = Metadata::unpack(&account.data)?;
+                if current.update_authority != wallet.public_key {
+                    return Err(Error::NotUpdateAuthority(mint));
+                }
+                if !current.is_mutable {
+                    return Err(Error::InvalidMetadata("metadata is immutable".to_string()));
+                }
+                // Only the name, symbol and uri are replaced; creators, royalties and the
+                // collection are kept.
+                data = Data {
+                    name: data.name,
+                    symbol: data.symbol,
+                    uri: data.uri,
+                    ..current.data
+                };
+            }
+
+            let content_type = file.raw_mime_type();
+            let logo_id = arweave.upload_file(&provider, &file).await?;
+            let logo_uri = arweave.data_url(&logo_id);
+            let json = JsonMetadata {
+                name,
+                symbol,
+                description,
+                image: logo_uri.clone(),
+                properties: JsonProperties {
+                    files: vec![JsonFile {
+                        uri: logo_uri,
+                        content_type,
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let metadata_id = arweave
+                .upload(&provider, serde_json::to_vec(&json)?, "application/json")
+                .await?;
+            data.uri = arweave.data_url(&metadata_id);
+            data.validate()?;
+
+            let instruction = if update {
+                metadata::update_metadata_account(
+                    &mint,
+                    &wallet.public_key,
+                    Some(&data),
+                    None,
+                    None,
+                    None,
+                )
+            } else {
+                metadata::create_metadata_account(
+                    &mint,
+                    &wallet.public_key,
+                    &wallet.public_key,
+                    &wallet.public_key,
+                    &data,
+                    true,
+                )
+            };
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &[instruction],
+                Some(&wallet.public_key),
+                &blockhash,
+            ));
+            wallet
+                .sign_and_send(ctx, rpc, transaction, Commitment::Confirmed)
+                .await
+        };
+        if let Err(error) = result.await {
+            report_error(ctx, error);
+        }
+    });
+    Ok(())
+}
+
+#[component]
+pub fn TokenMetadataForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let mint = ctx.create_signal(String::new());
+    let file_name = ctx.create_signal(String::new());
+    let name = ctx.create_signal(String::new());
+    let symbol = ctx.create_signal(String::new());
+    let description = ctx.create_signal(String::new());
+
+    let parsed_mint = ctx.create_memo(|| Pubkey::from_str(mint.get().trim()).ok());
+    let mint_error = ctx.create_memo(|| {
+        if !mint.get().is_empty() && parsed_mint.get().is_none() {
+            "Invalid mint address".to_string()
+        } else {
+            String::new()
+        }
+    });
+    let name_error = ctx.create_memo(|| {
+        if name.get().trim().len() > MAX_NAME_LENGTH {
+            format!("Name must be at most {} bytes", MAX_NAME_LENGTH)
+        } else {
+            String::new()
+        }
+    });
+    let symbol_error = ctx.create_memo(|| {
+        if symbol.get().trim().len() > MAX_SYMBOL_LENGTH {
+            format!("Symbol must be at most {} bytes", MAX_SYMBOL_LENGTH)
+        } else {
+            String::new()
+        }
+    });
+
+    let save = move |update: bool| {
+        let result = match *parsed_mint.get() {
+            Some(mint) => save_token_metadata(
+                ctx,
+                mint,
+                &file_name.get(),
+                name.get().trim().to_string(),
+                symbol.get().trim().to_string(),
+                description.get().trim().to_string(),
+                update,
+            ),
+            None => Err(Error::InvalidPublicKey),
+        };
+        if let Err(error) = result {
+            report_error(ctx, error);
+        }
+    };
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Mint address",
+                    bind:value=mint
+                )
+                FileSelect {
+                    selected: file_name
+                }
+            }
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Token name",
+                    maxlength=MAX_NAME_LENGTH.to_string(),
+                    bind:value=name
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    placeholder="Symbol",
+                    maxlength=MAX_SYMBOL_LENGTH.to_string(),
+                    bind:value=symbol
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Description",
+                    bind:value=description
+                )
+            }
+            div(class="flex space-x-4 items-center") {
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| save(false)
+                ) {
+                    "Create Metadata"
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=move |_| save(true)
+                ) {
+                    "Update Metadata"
+                }
+            }
+            p(class="text-sm text-rose-400") {(mint_error.get())}
+            p(class="text-sm text-rose-400") {(name_error.get())}
+            p(class="text-sm text-rose-400") {(symbol_error.get())}
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    components::{
+        phantom_wallet::{report_error, PhantomStatus, PhantomWallet},
+        transfer::parse_units,
+    },
+    crypto::generate_keypair,
+    error::Error,
+    programs::token,
+    solana_rpc::Commitment,
+    store::{reducer, rpc_client, Action, Cluster, Mints},
+};
+use solana_sdk::{message::Message, signature::Signer, transaction::Transaction};
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+pub const MAX_DECIMALS: u8 = 9;
+
+/// Creates a new mint owned by the connected wallet and mints `supply` base units to the
+/// wallet's associated token account, all in one transaction. The new mint address is added
+/// to the store once the transaction is sent.
+pub fn create_token(
+    ctx: ScopeRef<'_>,
+    decimals: u8,
+    supply: u64,
+    freezable: bool,
+) -> Result<(), Error> {
+    let wallet = ctx.use_context::<Signal<PhantomWallet>>().get();
+    if wallet.status != PhantomStatus::Connected {
+        return Err(Error::WalletNotConnected);
+    }
+    let rpc = rpc_client(ctx);
+    ctx.spawn_local(async move {
+        let result = async {
+            let mint = generate_keypair()?;
+            let rent = rpc
+                .get_minimum_balance_for_rent_exemption(token::MINT_LEN)
+                .await?;
+            let blockhash = rpc.get_latest_blockhash().await?.value.blockhash;
+            let instructions = token::create_mint_with_supply(
+                &wallet.public_key,
+                &mint.pubkey(),
+                decimals,
+                supply,
+                freezable,
+                rent,
+            );
+            let transaction = Transaction::new_unsigned(Message::new_with_blockhash(
+                &instructions,
+                Some(&wallet.public_key),
+                &blockhash,
+            ));
+            wallet
+                .sign_and_send_with_signers(ctx, rpc, transaction, &[&mint], Commitment::Confirmed)
+                .await?;
+            Ok::<_, Error>(mint.pubkey())
+        };
+        match result.await {
+            Ok(mint) => reducer(ctx, Action::MintAdd(mint)),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+#[component]
+pub fn TokenMintForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let decimals = ctx.create_signal(MAX_DECIMALS.to_string());
+    let supply = ctx.create_signal(String::new());
+    let freezable = ctx.create_signal(false);
+    let mints = ctx.use_context::<Signal<Mints>>();
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+
+    let parsed_decimals = ctx.create_memo(|| {
+        decimals
+            .get()
+            .trim()
+            .parse::<u8>()
+            .ok()
+            .filter(|d| *d <= MAX_DECIMALS)
+    });
+    let parsed_supply = ctx.create_memo(|| {
+        parsed_decimals
+            .get()
+            .and_then(|d| parse_units(&supply.get(), d).ok())
+    });
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-32",
+                    type="number",
+                    min="0",
+                    max=MAX_DECIMALS.to_string(),
+                    placeholder="Decimals",
+                    bind:value=decimals
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Initial supply",
+                    bind:value=supply
+                )
+                label(class="text-slate-200 space-x-2") {
+                    input(type="checkbox", bind:checked=freezable)
+                    span {"Freeze authority"}
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=|_| {
+                        let result = match (*parsed_decimals.get(), *parsed_supply.get()) {
+                            (Some(decimals), Some(supply)) => {
+                                create_token(ctx, decimals, supply, *freezable.get())
+                            }
+                            (None, _) => Err(Error::InvalidAmount(decimals.get().to_string())),
+                            _ => Err(Error::InvalidAmount(supply.get().to_string())),
+                        };
+                        if let Err(error) = result {
+                            report_error(ctx, error);
+                        }
+                    }
+                ) {
+                    "Create Token"
+                }
+            }
+            ul(class="text-slate-200 font-mono text-sm") {
+                Keyed {
+                    iterable: mints,
+                    view: move |ctx, mint| {
+                        let url = cluster.get().explorer_address_url(&mint);
+                        view! {ctx,
+                            li { a(class="underline", href=url, target="_blank") {(mint.to_string())} }
+                        }
+                    },
+                    key: |mint| *mint
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    components::phantom_wallet::{report_error, PhantomWallet},
+    error::Error,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlSelectElement};
+
+const SOL_DECIMALS: u8 = 9;
+
+/// Parses a decimal SOL amount such as `1.5` into lamports without going through floats.
+pub fn parse_sol(amount: &str) -> Result<u64, Error> {
+    parse_units(amount, SOL_DECIMALS)
+}
+
+/// Parses a decimal token amount into base units for a mint with `decimals` places.
+pub fn parse_units(amount: &str, decimals: u8) -> Result<u64, Error> {
+    let invalid = || Error::InvalidAmount(amount.to_string());
+    let decimals = decimals as usize;
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if (whole.is_empty() && fraction.is_empty()) || fraction.len() > decimals {
+        return Err(invalid());
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<u64>().map_err(|_| invalid())?
+    };
+    let fraction = if decimals == 0 {
+        0
+    } else {
+        format!("{:0<width$}", fraction, width = decimals)
+            .parse::<u64>()
+            .map_err(|_| invalid())?
+    };
+    10u64
+        .checked_pow(decimals as u32)
+        .and_then(|unit| whole.checked_mul(unit))
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+/// Formats base units of a mint with `decimals` places as a decimal amount, the inverse of
+/// [`parse_units`]. Mints with more places than a `u64` can hold show the raw base units.
+pub fn format_units(amount: u64, decimals: u8) -> String {
+    let unit = match 10u64.checked_pow(decimals as u32) {
+        Some(unit) => unit,
+        None => return format!("{} base units", amount),
+    };
+    let fraction = format!("{:0width$}", amount % unit, width = decimals as usize);
+    match fraction.trim_end_matches('0') {
+        "" => (amount / unit).to_string(),
+        fraction => format!("{}.{}", amount / unit, fraction),
+    }
+}
+
+pub fn format_sol(lamports: u64) -> String {
+    format!("{} SOL", format_units(lamports, SOL_DECIMALS))
+}
+
+pub fn parse_lamports(amount: &str) -> Result<u64, Error> {
+    amount
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| Error::InvalidAmount(amount.to_string()))
+}
+
+#[component]
+pub fn TransferForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let recipient = ctx.create_signal(String::new());
+    let amount = ctx.create_signal(String::new());
+    let in_lamports = ctx.create_signal(false);
+
+    let parsed_recipient = ctx.create_memo(|| Pubkey::from_str(recipient.get().trim()).ok());
+    let parsed_amount = ctx.create_memo(|| {
+        if *in_lamports.get() {
+            parse_lamports(&amount.get()).ok()
+        } else {
+            parse_sol(&amount.get()).ok()
+        }
+    });
+    let recipient_error = ctx.create_memo(|| {
+        if !recipient.get().is_empty() && parsed_recipient.get().is_none() {
+            "Invalid recipient address"
+        } else {
+            ""
+        }
+    });
+    let amount_error = ctx.create_memo(|| match *parsed_amount.get() {
+        None if !amount.get().is_empty() => "Invalid amount",
+        Some(0) => "Amount must be greater than zero",
+        _ => "",
+    });
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4") {
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Recipient address",
+                    bind:value=recipient
+                )
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 w-48",
+                    placeholder="Amount",
+                    bind:value=amount
+                )
+                select(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200",
+                    on:change=|event: Event| {
+                        let target: HtmlSelectElement = event.target().unwrap().unchecked_into();
+                        in_lamports.set(target.value() == "lamports");
+                    }
+                ) {
+                    option(value="sol") {"SOL"}
+                    option(value="lamports") {"lamports"}
+                }
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=|_| {
+                        match (*parsed_recipient.get(), *parsed_amount.get()) {
+                            (Some(to), Some(lamports)) if lamports > 0 => {
+                                if let Err(error) =
+                                    PhantomWallet::create_transfer_transaction(ctx, &to, lamports)
+                                {
+                                    report_error(ctx, error);
+                                }
+                            }
+                            (None, _) => report_error(ctx, Error::InvalidPublicKey),
+                            _ => report_error(ctx, Error::InvalidAmount(amount.get().to_string())),
+                        }
+                    }
+                ) {
+                    "Send"
+                }
+            }
+            p(class="text-sm text-rose-400") {(recipient_error.get())}
+            p(class="text-sm text-rose-400") {(amount_error.get())}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_decimal_amounts() {
+        assert_eq!(parse_sol("1.5").unwrap(), 1_500_000_000);
+        assert_eq!(parse_units(" 12 ", 2).unwrap(), 1200);
+        assert_eq!(parse_units(".25", 2).unwrap(), 25);
+        assert_eq!(parse_units("7.", 3).unwrap(), 7000);
+        assert_eq!(parse_units("42", 0).unwrap(), 42);
+        assert_eq!(parse_units("18446744073709551615", 0).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        for (amount, decimals) in [
+            ("", 9),
+            (".", 9),
+            ("1.123", 2),
+            ("1.5", 0),
+            ("-1", 9),
+            ("1e3", 9),
+            ("1.2.3", 9),
+            ("18446744073709551616", 0),
+            ("18446744073709551615", 1),
+            ("1", 20),
+        ] {
+            assert!(
+                matches!(parse_units(amount, decimals), Err(Error::InvalidAmount(_))),
+                "{:?} with {} decimals",
+                amount,
+                decimals
+            );
+        }
+    }
+
+    #[test]
+    fn formats_base_units() {
+        assert_eq!(format_units(1_500_000_000, 9), "1.5");
+        assert_eq!(format_units(1_000_000_000, 9), "1");
+        assert_eq!(format_units(5, 9), "0.000000005");
+        assert_eq!(format_units(0, 2), "0");
+        assert_eq!(format_units(42, 0), "42");
+        assert_eq!(format_units(u64::MAX, 19), "1.8446744073709551615");
+        assert_eq!(format_sol(2_500_000_000), "2.5 SOL");
+        for (amount, decimals) in [(1, 9), (123_456, 3), (u64::MAX, 19)] {
+            assert_eq!(
+                parse_units(&format_units(amount, decimals), decimals).unwrap(),
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn formats_raw_units_past_the_u64_range() {
+        assert_eq!(format_units(123, 20), "123 base units");
+        assert_eq!(
+            format_units(u64::MAX, u8::MAX),
+            format!("{} base units", u64::MAX)
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    components::{files::FileSelect, phantom_wallet::report_error},
+    error::Error,
+    notarization::{self, NotarizationRecord, OnChainNotarization},
+    store::{rpc_client, selected_file, Cluster},
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::str::FromStr;
+use sycamore::futures::ScopeSpawnLocal;
+use sycamore::prelude::*;
+
+/// The selected file's record and the notarizations found for it.
+#[derive(Debug, Clone, PartialEq)]
+struct Verification {
+    file: String,
+    local: NotarizationRecord,
+    found: Vec<OnChainNotarization>,
+}
+
+fn format_block_time(block_time: Option<i64>) -> String {
+    match block_time {
+        Some(seconds) => {
+            let date =
+                js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(seconds as f64 * 1_000.0));
+            String::from(date.to_iso_string())
+        }
+        None => "unknown time".to_string(),
+    }
+}
+
+/// Merklizes the selected file locally and compares its `data_root` with the notarizations
+/// recorded by a transaction signature, or among the recent transactions of an address. The
+/// file never leaves the browser.
+fn verify<'a>(
+    ctx: ScopeRef<'a>,
+    file_name: &str,
+    query: &str,
+    verification: &'a Signal<Option<Verification>>,
+) -> Result<(), Error> {
+    let file = selected_file(ctx, file_name)?;
+    let query = query.trim();
+    let (signature, address) = match Signature::from_str(query) {
+        Ok(signature) => (Some(signature), None),
+        Err(_) => (
+            None,
+            Some(Pubkey::from_str(query).map_err(|_| Error::InvalidPublicKey)?),
+        ),
+    };
+    let rpc = rpc_client(ctx);
+    verification.set(None);
+    ctx.spawn_local(async move {
+        let result = async {
+            let data = gloo_file::futures::read_as_bytes(&file)
+                .await
+                .map_err(|error| Error::JsError(error.to_string()))?;
+            let local = NotarizationRecord::new(data, None)?;
+            let found = match (signature, address) {
+                (Some(signature), _) => notarization::fetch_by_signature(&rpc, &signature).await?,
+                (_, Some(address)) => notarization::fetch_by_address(&rpc, &address).await?,
+                _ => vec![],
+            };
+            Ok::<_, Error>(Verification {
+                file: file.name(),
+                local,
+                found,
+            })
+        };
+        match result.await {
+            Ok(result) => verification.set(Some(result)),
+            Err(error) => report_error(ctx, error),
+        }
+    });
+    Ok(())
+}
+
+fn notarization_view<G: Html>(
+    ctx: ScopeRef,
+    cluster: &Cluster,
+    notarization: &OnChainNotarization,
+) -> View<G> {
+    let url = cluster.explorer_tx_url(&notarization.signature);
+    let signers = notarization
+        .signers
+        .iter()
+        .map(|signer| signer.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    let detail = format!(
+        "signed by {} at {} (slot {}), data_root {}",
+        signers,
+        format_block_time(notarization.block_time),
+        notarization.slot,
+        notarization.record.data_root
+    );
+    view! {ctx,
+        li(class="space-x-2") {
+            a(class="underline", href=url, target="_blank") {"transaction"}
+            span {(detail)}
+        }
+    }
+}
+
+#[component]
+pub fn VerifyNotarizationForm<G: Html>(ctx: ScopeRef) -> View<G> {
+    let file_name = ctx.create_signal(String::new());
+    let query = ctx.create_signal(String::new());
+    let verification = ctx.create_signal(None::<Verification>);
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+
+    view! {ctx,
+        div(class="space-y-2") {
+            div(class="flex space-x-4 items-center") {
+                FileSelect {
+                    selected: file_name
+                }
+                input(class="px-5 py-3 rounded-lg bg-slate-700 text-slate-200 flex-1",
+                    placeholder="Transaction signature or signer address",
+                    bind:value=query
+                )
+                button(class="px-5 py-3 rounded-lg shadow-lg bg-indigo-700 hover:bg-indigo-600 active:bg-indigo-800
+                    focus:outline-none text-sm text-slate-200 uppercase tracking-wider
+                    font-semibold sm:text-base",
+                    on:click=|_| {
+                        if let Err(error) = verify(ctx, &file_name.get(), &query.get(), verification) {
+                            report_error(ctx, error);
+                        }
+                    }
+                ) {
+                    "Verify"
+                }
+            }
+            (match (*verification.get()).clone() {
+                Some(verification) => {
+                    let cluster = cluster.get();
+                    let (matching, other): (Vec<_>, Vec<_>) = verification
+                        .found
+                        .iter()
+                        .partition(|found| found.matches(&verification.local));
+                    let summary = if matching.is_empty() {
+                        format!("Mismatch: no notarization of {} found", verification.file)
+                    } else {
+                        format!("Match: {} was notarized", verification.file)
+                    };
+                    let summary_class = if matching.is_empty() {
+                        "font-semibold text-rose-400"
+                    } else {
+                        "font-semibold text-green-400"
+                    };
+                    let local = format!(
+                        "Local data_root {}, {} bytes",
+                        verification.local.data_root, verification.local.data_size
+                    );
+                    let matching = View::new_fragment(
+                        matching.into_iter().map(|found| notarization_view(ctx, &cluster, found)).collect(),
+                    );
+                    let other = View::new_fragment(
+                        other.into_iter().map(|found| notarization_view(ctx, &cluster, found)).collect(),
+                    );
+                    view! {ctx,
+                        div(class="space-y-2 px-5 py-3 rounded-lg bg-slate-700 text-slate-200") {
+                            p(class=summary_class) {(summary)}
+                            p(class="font-mono text-sm truncate") {(local)}
+                            ul(class="font-mono text-sm text-green-400") {(matching)}
+                            ul(class="font-mono text-sm text-slate-400") {(other)}
+                        }
+                    }
+                }
+                None => View::empty(),
+            })
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! [Concurrent merkle trees](https://spl.solana.com/account-compression/concepts) of the SPL
+//! Account Compression program, which store compressed NFTs. Unlike the Arweave trees in
+//! [`crate::merkle`], they have a fixed depth: missing leaves are zeroed and nodes are
+//! `keccak(left || right)` in position order.
+//!
+//! The tree account keeps a buffer of recent roots, so a proof stays valid while other leaves
+//! change, and a canopy of the top nodes, so proofs sent to the program can omit them.
+
+use crate::{error::Error, programs::account_compression, solana_rpc::RpcClient};
+use solana_sdk::{keccak, pubkey::Pubkey};
+
+pub type Node = [u8; 32];
+
+/// Deepest tree the program supports.
+pub const MAX_DEPTH: u32 = 30;
+/// Value of a leaf that has not been set.
+pub const EMPTY_NODE: Node = [0; 32];
+
+const CONCURRENT_MERKLE_TREE_ACCOUNT: u8 = 1;
+const HEADER_V1: u8 = 0;
+const HEADER_LEN: usize = 56;
+const NODE_LEN: usize = 32;
+
+pub fn hash_pair(left: &Node, right: &Node) -> Node {
+    keccak::hashv(&[left, right]).to_bytes()
+}
+
+/// Root of an empty subtree `level` levels above the leaves.
+pub fn empty_node(level: u32) -> Node {
+    (0..level).fold(EMPTY_NODE, |node, _| hash_pair(&node, &node))
+}
+
+/// Root obtained by hashing `leaf` at `index` with the siblings in `proof`.
+pub fn recompute(leaf: &Node, proof: &[Node], index: u32) -> Node {
+    proof
+        .iter()
+        .enumerate()
+        .fold(*leaf, |node, (level, sibling)| {
+            if (index >> level) & 1 == 0 {
+                hash_pair(&node, sibling)
+            } else {
+                hash_pair(sibling, &node)
+            }
+        })
+}
+
+/// Completes a proof for leaf `index` with nodes from `canopy`, the way the program does
+/// before verifying it. The canopy stores the top levels below the root in heap order, with
+/// unset nodes zeroed.
+pub fn fill_from_canopy(
+    canopy: &[Node],
+    depth: u32,
+    index: u32,
+    proof: &[Node],
+) -> Result<Vec<Node>, Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::InvalidMerkleTree(format!(
+            "depth {} is more than {}",
+            depth, MAX_DEPTH
+        )));
+    }
+    if index as u64 >= 1 << depth {
+        return Err(Error::InvalidMerkleTree(format!(
+            "leaf {} is outside a tree of depth {}",
+            index, depth
+        )));
+    }
+    // Heap index of the highest node the proof reaches, the root being 1.
+    let mut node_index = ((1u64 << depth) + index as u64) >> proof.len().min(depth as usize);
+    let mut inferred = Vec::new();
+    while node_index > 1 {
+        let cached = ((node_index ^ 1) - 2) as usize;
+        let level = depth - (63 - node_index.leading_zeros());
+        let node = canopy.get(cached).ok_or_else(|| {
+            Error::InvalidMerkleTree(format!(
+                "proof of {} nodes is too short for a canopy of {} nodes",
+                proof.len(),
+                canopy.len()
+            ))
+        })?;
+        inferred.push(if *node == EMPTY_NODE {
+            empty_node(level)
+        } else {
+            *node
+        });
+        node_index >>= 1;
+    }
+    let mut full = proof.to_vec();
+    full.extend(inferred);
+    full.truncate(depth as usize);
+    Ok(full)
+}
+
+/// Number of levels cached by a canopy of `nodes` nodes, `2^(depth + 1) - 2`.
+pub fn canopy_depth(nodes: usize) -> u32 {
+    (nodes as u64 + 2).trailing_zeros().saturating_sub(1)
+}
+
+/// A concurrent merkle tree account: its header, the roots in the change log buffer and the
+/// canopy. Change log paths are not read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcurrentMerkleTree {
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+    pub authority: Pubkey,
+    pub creation_slot: u64,
+    /// Number of changes made to the tree.
+    pub sequence_number: u64,
+    /// Position of the current root in `roots`.
+    pub active_index: u64,
+    /// Number of roots in `roots` that have been set.
+    pub buffer_size: u64,
+    pub roots: Vec<Node>,
+    /// Number of leaves appended, which is also the index of the next one.
+    pub leaf_count: u32,
+    pub canopy: Vec<Node>,
+}
+
+impl ConcurrentMerkleTree {
+    /// Reads the account data: a Borsh header, then the zero-copy tree with `max_buffer_size`
+    /// change logs and the rightmost path, then the canopy.
+    pub fn unpack(data: &[u8]) -> Result<Self, Error> {
+        let invalid = |message: &str| Error::InvalidMerkleTree(message.to_string());
+        if data.len() < HEADER_LEN || data[0] != CONCURRENT_MERKLE_TREE_ACCOUNT {
+            return Err(invalid("not a concurrent merkle tree account"));
+        }
+        if data[1] != HEADER_V1 {
+            return Err(invalid("unsupported header version"));
+        }
+        let u32_at = |offset: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&data[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        let u64_at = |offset: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let node_at = |offset: usize| {
+            let mut node = EMPTY_NODE;
+            node.copy_from_slice(&data[offset..offset + NODE_LEN]);
+            node
+        };
+
+        let max_buffer_size = u32_at(2);
+        let max_depth = u32_at(6);
+        if max_depth > MAX_DEPTH {
+            return Err(invalid("depth is too large"));
+        }
+        let change_log_len = NODE_LEN + NODE_LEN * max_depth as usize + 8;
+        let path_len = NODE_LEN * max_depth as usize + NODE_LEN + 8;
+        let tree_len = 24 + change_log_len * max_buffer_size as usize + path_len;
+        let canopy_offset = HEADER_LEN + tree_len;
+        if data.len() < canopy_offset || (data.len() - canopy_offset) % NODE_LEN != 0 {
+            return Err(invalid(
+                "account size does not match its depth and buffer size",
+            ));
+        }
+
+        let roots = (0..max_buffer_size as usize)
+            .map(|i| node_at(HEADER_LEN + 24 + change_log_len * i))
+            .collect();
+        let rightmost_offset = HEADER_LEN + 24 + change_log_len * max_buffer_size as usize;
+        let canopy = data[canopy_offset..]
+            .chunks(NODE_LEN)
+            .map(|chunk| {
+                let mut node = EMPTY_NODE;
+                node.copy_from_slice(chunk);
+                node
+            })
+            .collect();
+        Ok(Self {
+            max_depth,
+            max_buffer_size,
+            authority: Pubkey::new(&data[10..42]),
+            creation_slot: u64_at(42),
+            sequence_number: u64_at(HEADER_LEN),
+            active_index: u64_at(HEADER_LEN + 8),
+            buffer_size: u64_at(HEADER_LEN + 16),
+            roots,
+            leaf_count: u32_at(rightmost_offset + path_len - 8),
+            canopy,
+        })
+    }
+
+    /// Fetches and unpacks the tree account at `address`.
+    pub async fn fetch(rpc: &RpcClient, address: &Pubkey) -> Result<Self, Error> {
+        let account = rpc
+            .get_account_info(address)
+            .await?
+            .value
+            .ok_or_else(|| Error::InvalidMerkleTree(format!("{} not found", address)))?;
+        if account.owner != account_compression::id() {
+            return Err(Error::InvalidMerkleTree(format!(
+                "{} is not owned by the account compression program",
+                address
+            )));
+        }
+        Self::unpack(&account.data)
+    }
+
+    pub fn root(&self) -> Node {
+        self.roots
+            .get(self.active_index as usize)
+            .copied()
+            .unwrap_or(EMPTY_NODE)
+    }
+
+    pub fn canopy_depth(&self) -> u32 {
+        canopy_depth(self.canopy.len())
+    }
+
+    /// Recent roots a proof may be checked against, newest first.
+    pub fn recent_roots(&self) -> Vec<Node> {
+        let len = self.roots.len();
+        (0..(self.buffer_size as usize).min(len))
+            .map(|i| self.roots[(self.active_index as usize + len - i) % len])
+            .collect()
+    }
+
+    /// Checks `leaf` at `index` against the recent roots, completing `proof` from the canopy.
+    /// Proofs against older roots fail once the buffer has moved past them.
+    pub fn verify_leaf(&self, leaf: &Node, proof: &[Node], index: u32) -> Result<bool, Error> {
+        if proof.len() > self.max_depth as usize {
+            return Err(Error::InvalidMerkleTree(format!(
+                "proof of {} nodes is longer than the depth {}",
+                proof.len(),
+                self.max_depth
+            )));
+        }
+        let proof = fill_from_canopy(&self.canopy, self.max_depth, index, proof)?;
+        let root = recompute(leaf, &proof, index);
+        Ok(self.recent_roots().contains(&root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPTH: u32 = 3;
+
+    /// Every layer of a tree of [`DEPTH`] with five leaves set, starting with the leaves.
+    fn layers() -> Vec<Vec<Node>> {
+        let mut leaves: Vec<Node> = (1..=5).map(|i| [i; 32]).collect();
+        leaves.resize(1 << DEPTH, EMPTY_NODE);
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| hash_pair(&pair[0], &pair[1]))
+                .collect();
+            layers.push(next);
+        }
+        layers
+    }
+
+    /// The canopy of `depth` levels, zeroing nodes above unset leaves like the program.
+    fn canopy(layers: &[Vec<Node>], depth: u32) -> Vec<Node> {
+        (1..=depth)
+            .flat_map(|level| {
+                let level = (DEPTH - level) as usize;
+                layers[level]
+                    .iter()
+                    .map(move |node| {
+                        if *node == empty_node(level as u32) {
+                            EMPTY_NODE
+                        } else {
+                            *node
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn full_proof(layers: &[Vec<Node>], index: u32) -> Vec<Node> {
+        (0..DEPTH)
+            .map(|level| layers[level as usize][((index >> level) ^ 1) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn canopy_depth_from_nodes() {
+        assert_eq!(canopy_depth(0), 0);
+        assert_eq!(canopy_depth(2), 1);
+        assert_eq!(canopy_depth(6), 2);
+        assert_eq!(canopy_depth(14), 3);
+        assert_eq!(canopy_depth(canopy(&layers(), 2).len()), 2);
+    }
+
+    #[test]
+    fn empty_nodes_match_an_empty_tree() {
+        let layers = layers();
+        assert_eq!(layers[1][3], empty_node(1));
+        assert_eq!(layers[2][1], hash_pair(&layers[1][2], &empty_node(1)));
+    }
+
+    #[test]
+    fn fills_proofs_from_canopy() {
+        let layers = layers();
+        let root = layers[DEPTH as usize][0];
+        for canopy_levels in 0..=DEPTH {
+            let canopy = canopy(&layers, canopy_levels);
+            for index in 0..5 {
+                let full = full_proof(&layers, index);
+                let sent = &full[..(DEPTH - canopy_levels) as usize];
+                let filled = fill_from_canopy(&canopy, DEPTH, index, sent).unwrap();
+                assert_eq!(filled, full, "leaf {} canopy {}", index, canopy_levels);
+                let leaf = layers[0][index as usize];
+                assert_eq!(recompute(&leaf, &filled, index), root);
+                // Nodes the canopy already has may still be sent.
+                assert_eq!(
+                    fill_from_canopy(&canopy, DEPTH, index, &full).unwrap(),
+                    full
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_short_proofs_and_outside_leaves() {
+        let layers = layers();
+        let canopy = canopy(&layers, 1);
+        let proof = full_proof(&layers, 4);
+        assert!(matches!(
+            fill_from_canopy(&canopy, DEPTH, 4, &proof[..1]),
+            Err(Error::InvalidMerkleTree(_))
+        ));
+        assert!(matches!(
+            fill_from_canopy(&canopy, DEPTH, 8, &proof),
+            Err(Error::InvalidMerkleTree(_))
+        ));
+    }
+
+    #[test]
+    fn verifies_leaves_against_recent_roots() {
+        let layers = layers();
+        let root = layers[DEPTH as usize][0];
+        let tree = ConcurrentMerkleTree {
+            max_depth: DEPTH,
+            max_buffer_size: 2,
+            authority: Pubkey::default(),
+            creation_slot: 0,
+            sequence_number: 6,
+            active_index: 1,
+            buffer_size: 2,
+            roots: vec![empty_node(DEPTH), root],
+            leaf_count: 5,
+            canopy: canopy(&layers, 1),
+        };
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.canopy_depth(), 1);
+        let proof = &full_proof(&layers, 2)[..2];
+        assert!(tree.verify_leaf(&layers[0][2], proof, 2).unwrap());
+        assert!(!tree.verify_leaf(&[9; 32], proof, 2).unwrap());
+        assert!(!tree.verify_leaf(&layers[0][2], proof, 3).unwrap());
+    }
+}
@@ -0,0 +1,231 @@
+//! Deciding from signature statuses whether a submitted transaction landed. The status cache
+//! the cluster checks by default only covers recent slots, so a transaction that is missing
+//! there is only taken to have never landed once its blockhash has expired and the ledger
+//! history has no record of it either.
+
+use crate::{
+    error::Error,
+    solana_rpc::{Commitment, RpcClient, SignatureStatus},
+};
+use solana_sdk::{hash::Hash, signature::Signature};
+
+/// Where a transaction stands after one poll.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionState {
+    /// Seen by the cluster, possibly failed.
+    Seen(SignatureStatus),
+    /// Not seen yet, it may still land.
+    Unseen,
+    /// Its blockhash expired and the ledger history has no record of it.
+    Expired,
+}
+
+impl TransactionState {
+    /// The final outcome once the transaction reaches `commitment`, fails or expires.
+    pub fn outcome(self, commitment: Commitment) -> Option<Result<(), Error>> {
+        match self {
+            TransactionState::Seen(SignatureStatus { err: Some(err), .. }) => {
+                Some(Err(Error::TransactionFailed(err.to_string())))
+            }
+            TransactionState::Seen(status) => status
+                .confirmation_status
+                .filter(|level| *level >= commitment)
+                .map(|_| Ok(())),
+            TransactionState::Unseen => None,
+            TransactionState::Expired => Some(Err(Error::BlockhashExpired)),
+        }
+    }
+}
+
+/// Whether a transaction whose confirmation failed with `error` can no longer have an effect,
+/// so what it carried may be sent again. Timeouts and RPC errors leave that open: the
+/// transaction may still land, or may have landed unseen.
+pub fn never_landed(error: &Error) -> bool {
+    matches!(error, Error::BlockhashExpired | Error::TransactionFailed(_))
+}
+
+/// Polls the statuses of `signatures`, all built with `blockhash`. Those missing from the
+/// recent status cache are looked up in the ledger history once `blockhash` is no longer
+/// valid at `confirmed`, and only reported expired if they are not found there either.
+/// `blockhash` is `None` for durable transactions, which do not expire.
+pub async fn poll(
+    rpc: &RpcClient,
+    signatures: &[Signature],
+    blockhash: Option<&Hash>,
+) -> Result<Vec<TransactionState>, Error> {
+    let mut states: Vec<TransactionState> = rpc
+        .get_signature_statuses(signatures, false)
+        .await?
+        .value
+        .into_iter()
+        .map(|status| status.map_or(TransactionState::Unseen, TransactionState::Seen))
+        .collect();
+    let unseen: Vec<usize> = (0..states.len())
+        .filter(|i| states[*i] == TransactionState::Unseen)
+        .collect();
+    let blockhash = match blockhash {
+        Some(blockhash) if !unseen.is_empty() => blockhash,
+        _ => return Ok(states),
+    };
+    // Checked after the statuses, so a transaction that lands in between is in the history.
+    if rpc
+        .is_blockhash_valid(blockhash, Commitment::Confirmed)
+        .await?
+        .value
+    {
+        return Ok(states);
+    }
+    let searched: Vec<Signature> = unseen.iter().map(|i| signatures[*i]).collect();
+    let history = rpc.get_signature_statuses(&searched, true).await?.value;
+    for (i, status) in unseen.into_iter().zip(history) {
+        states[i] = status.map_or(TransactionState::Expired, TransactionState::Seen);
+    }
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{block_on, serve, Request};
+    use serde_json::{json, Value};
+    use std::sync::mpsc::Receiver;
+
+    fn result(result: Value) -> (u16, String) {
+        (
+            200,
+            json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string(),
+        )
+    }
+
+    fn statuses(statuses: Value) -> (u16, String) {
+        result(json!({ "context": { "slot": 100 }, "value": statuses }))
+    }
+
+    fn valid(valid: bool) -> (u16, String) {
+        result(json!({ "context": { "slot": 100 }, "value": valid }))
+    }
+
+    fn landed() -> Value {
+        json!({
+            "slot": 7,
+            "confirmations": null,
+            "err": null,
+            "confirmationStatus": "finalized"
+        })
+    }
+
+    fn params(requests: &Receiver<Request>) -> Value {
+        let request: Value = serde_json::from_str(&requests.recv().unwrap().body).unwrap();
+        assert_eq!(request["method"], "getSignatureStatuses");
+        request["params"].clone()
+    }
+
+    fn signature(byte: u8) -> Signature {
+        Signature::new(&[byte; 64])
+    }
+
+    fn poll_stub(
+        responses: Vec<(u16, String)>,
+        signatures: &[Signature],
+        blockhash: Option<&Hash>,
+    ) -> (Result<Vec<TransactionState>, Error>, Receiver<Request>) {
+        let (url, requests) = serve(responses);
+        let states = block_on(poll(&RpcClient::new(&url), signatures, blockhash));
+        (states, requests)
+    }
+
+    #[test]
+    fn finds_landed_transactions_in_history() {
+        let signature = signature(1);
+        let (states, requests) = poll_stub(
+            vec![
+                statuses(json!([null])),
+                valid(false),
+                statuses(json!([landed()])),
+            ],
+            &[signature],
+            Some(&Hash::new_unique()),
+        );
+        let state = states.unwrap().remove(0);
+        assert!(matches!(
+            state,
+            TransactionState::Seen(SignatureStatus { slot: 7, .. })
+        ));
+        assert!(matches!(state.outcome(Commitment::Confirmed), Some(Ok(()))));
+
+        assert_eq!(params(&requests)[1]["searchTransactionHistory"], false);
+        requests.recv().unwrap();
+        let history = params(&requests);
+        assert_eq!(history[0], json!([signature.to_string()]));
+        assert_eq!(history[1]["searchTransactionHistory"], true);
+    }
+
+    #[test]
+    fn expires_transactions_missing_from_history() {
+        let (states, _requests) = poll_stub(
+            vec![
+                statuses(json!([landed(), null])),
+                valid(false),
+                statuses(json!([null])),
+            ],
+            &[signature(2), signature(3)],
+            Some(&Hash::new_unique()),
+        );
+        let states = states.unwrap();
+        assert!(matches!(states[0], TransactionState::Seen(_)));
+        assert_eq!(states[1], TransactionState::Expired);
+        let outcome = states[1].clone().outcome(Commitment::Confirmed).unwrap();
+        assert!(never_landed(&outcome.unwrap_err()));
+    }
+
+    #[test]
+    fn waits_while_the_blockhash_is_valid() {
+        let (states, _requests) = poll_stub(
+            vec![statuses(json!([null])), valid(true)],
+            &[signature(4)],
+            Some(&Hash::new_unique()),
+        );
+        assert_eq!(states.unwrap(), vec![TransactionState::Unseen]);
+        // Durable transactions do not expire, so their blockhash is not checked.
+        let (states, _requests) = poll_stub(vec![statuses(json!([null]))], &[signature(5)], None);
+        assert_eq!(states.unwrap(), vec![TransactionState::Unseen]);
+    }
+
+    #[test]
+    fn reports_failures_and_levels() {
+        let failed = json!({
+            "slot": 7,
+            "confirmations": 0,
+            "err": { "InstructionError": [0, "Custom"] },
+            "confirmationStatus": "confirmed"
+        });
+        let processed = json!({
+            "slot": 8,
+            "confirmations": 0,
+            "err": null,
+            "confirmationStatus": "processed"
+        });
+        let (states, _requests) = poll_stub(
+            vec![statuses(json!([failed, processed]))],
+            &[signature(6), signature(7)],
+            Some(&Hash::new_unique()),
+        );
+        let mut states = states.unwrap().into_iter();
+        let failed = states.next().unwrap().outcome(Commitment::Confirmed);
+        assert!(matches!(failed, Some(Err(Error::TransactionFailed(_)))));
+        let processed = states.next().unwrap();
+        assert!(processed.clone().outcome(Commitment::Confirmed).is_none());
+        assert!(matches!(
+            processed.outcome(Commitment::Processed),
+            Some(Ok(()))
+        ));
+    }
+
+    #[test]
+    fn classifies_errors() {
+        assert!(never_landed(&Error::BlockhashExpired));
+        assert!(never_landed(&Error::TransactionFailed("x".to_string())));
+        assert!(!never_landed(&Error::ConfirmationTimeout));
+        assert!(!never_landed(&Error::StatusCodeNotOk));
+    }
+}
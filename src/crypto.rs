@@ -1,11 +1,13 @@
 use crate::error::Error;
 use crate::transaction::Base64;
+use serde::Deserialize;
 // use jsonwebkey::JsonWebKey;
 use ring::{
     digest::{Context, SHA256},
     rand::{self, SecureRandom},
     signature::{self, KeyPair, RsaKeyPair},
 };
+use solana_sdk::signature::{keypair_from_seed, Keypair};
 
 /// Struct for for crypto methods.
 pub struct Provider {
@@ -13,7 +15,66 @@ pub struct Provider {
     pub sr: rand::SystemRandom,
 }
 
+/// Arweave keyfile in [JWK](https://datatracker.ietf.org/doc/html/rfc7517) format.
+#[derive(Deserialize)]
+struct Jwk {
+    n: Base64,
+    e: Base64,
+    d: Base64,
+    p: Base64,
+    q: Base64,
+    dp: Base64,
+    dq: Base64,
+    qi: Base64,
+}
+
+/// Encodes a DER length.
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        vec![len as u8]
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        let mut encoded = vec![0x80 | bytes.len() as u8];
+        encoded.extend(bytes);
+        encoded
+    }
+}
+
+/// Encodes big-endian unsigned bytes as a DER INTEGER.
+fn der_integer(bytes: &[u8]) -> Vec<u8> {
+    let mut value: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    if value.first().map_or(true, |b| b & 0x80 != 0) {
+        value.insert(0, 0);
+    }
+    let mut encoded = vec![0x02];
+    encoded.extend(der_length(value.len()));
+    encoded.extend(value);
+    encoded
+}
+
 impl Provider {
+    /// Loads an Arweave keyfile, re-encoding the JWK as a PKCS#1 `RSAPrivateKey`.
+    pub fn from_jwk(data: &str) -> Result<Provider, Error> {
+        let jwk: Jwk = serde_json::from_str(data)?;
+        let mut body = der_integer(&[0]);
+        for component in [
+            &jwk.n, &jwk.e, &jwk.d, &jwk.p, &jwk.q, &jwk.dp, &jwk.dq, &jwk.qi,
+        ] {
+            body.extend(der_integer(&component.0));
+        }
+        let mut der = vec![0x30];
+        der.extend(der_length(body.len()));
+        der.extend(body);
+        Ok(Self {
+            keypair: RsaKeyPair::from_der(&der)?,
+            sr: rand::SystemRandom::new(),
+        })
+    }
+
     // pub fn from_keypair_string(data: String) -> Result<Provider, Error> {
     //     let jwk_parsed: JsonWebKey = data.parse().unwrap();
     //     Ok(Self {
@@ -67,3 +128,11 @@ impl Provider {
         Ok(rand_bytes)
     }
 }
+
+/// Generates a new ed25519 [`Keypair`] for accounts created by the app, such as token mints,
+/// seeded from the browser's crypto RNG.
+pub fn generate_keypair() -> Result<Keypair, Error> {
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).map_err(|_| Error::InvalidHash)?;
+    keypair_from_seed(&seed).map_err(|_| Error::InvalidHash)
+}
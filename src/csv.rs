@@ -0,0 +1,129 @@
+//! Minimal [RFC 4180](https://www.rfc-editor.org/rfc/rfc4180) CSV reader for the metadata and
+//! transfer sheets users upload.
+
+use crate::error::Error;
+
+/// Splits `text` into records of fields. Fields may be quoted with `"`, in which case they
+/// can contain commas, line breaks and doubled `""` quotes. Blank lines are skipped.
+pub fn parse(text: &str) -> Result<Vec<Vec<String>>, Error> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].trim().is_empty() {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+                line += 1;
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(Error::InvalidCsv(format!(
+            "unterminated quoted field on line {}",
+            line
+        )));
+    }
+    if !record.is_empty() || !field.trim().is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Parses `text` with a header row, returning the trimmed header names and records. Every
+/// record must have as many fields as the header.
+pub fn parse_with_header(text: &str) -> Result<(Vec<String>, Vec<Vec<String>>), Error> {
+    let mut records = parse(text)?.into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or_else(|| Error::InvalidCsv("missing header row".to_string()))?
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .collect();
+    let records: Vec<Vec<String>> = records.collect();
+    if let Some(row) = records
+        .iter()
+        .position(|record| record.len() != header.len())
+    {
+        return Err(Error::InvalidCsv(format!(
+            "row {} has {} fields, expected {}",
+            row + 1,
+            records[row].len(),
+            header.len()
+        )));
+    }
+    Ok((header, records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_fields() {
+        let text = "file,name\r\n\"a,b.png\",\"say \"\"hi\"\"\"\n\nc.png,\"two\nlines\"";
+        assert_eq!(
+            parse(text).unwrap(),
+            vec![
+                vec!["file", "name"],
+                vec!["a,b.png", "say \"hi\""],
+                vec!["c.png", "two\nlines"],
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_empty_fields() {
+        assert_eq!(
+            parse("a,,c\n,\n").unwrap(),
+            vec![vec!["a", "", "c"], vec!["", ""]]
+        );
+        assert!(parse("\n  \n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        match parse("file,name\n\"a.png,\nb") {
+            Err(Error::InvalidCsv(message)) => {
+                assert_eq!(message, "unterminated quoted field on line 3")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn checks_record_lengths_against_header() {
+        let (header, records) = parse_with_header(" file , name\na.png,A\n").unwrap();
+        assert_eq!(header, vec!["file", "name"]);
+        assert_eq!(records, vec![vec!["a.png", "A"]]);
+        assert!(matches!(
+            parse_with_header("file,name\na.png,A\nb.png\n"),
+            Err(Error::InvalidCsv(message)) if message == "row 2 has 1 fields, expected 2"
+        ));
+        assert!(matches!(parse_with_header(""), Err(Error::InvalidCsv(_))));
+    }
+}
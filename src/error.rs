@@ -1,25 +1,38 @@
 //! Errors propagated by library functions.
 use base64::DecodeError;
-// use ring::error::{KeyRejected, Unspecified};
-// use serde_json;
+use ring::error::KeyRejected;
+// use ring::error::Unspecified;
 use thiserror::Error;
 // use url::ParseError;
+use wasm_bindgen::JsValue;
 
 /// Errors propagated by library functions.
 #[derive(Error, Debug)]
 pub enum Error {
-    // #[error("error getting arweave price: {0}")]
-    // ArweaveGetPriceError(reqwest::Error),
-    // #[error("error posting arweave transaction: {0}")]
-    // ArweavePostError(reqwest::Error),
+    #[error("error getting arweave price: {0}")]
+    ArweaveGetPriceError(reqwest::Error),
+    #[error("error posting arweave transaction: {0}")]
+    ArweavePostError(reqwest::Error),
+    #[error("arweave transaction {0} is pending")]
+    ArweaveTransactionPending(String),
+    #[error("arweave keyfile not loaded")]
+    ArweaveWalletNotLoaded,
     #[error("avro deserialize: {0}")]
     AvroDeError(#[from] avro_rs::DeError),
     #[error("base64 decode: {0}")]
     Base64Decode(#[from] DecodeError),
-    // #[error("bincode: {0}")]
-    // Bincode(#[from] Box<bincode::ErrorKind>),
+    #[error("blockhash expired before the transaction was processed")]
+    BlockhashExpired,
+    #[error("bincode: {0}")]
+    Bincode(#[from] Box<bincode::ErrorKind>),
+    #[error("base58 decode: {0}")]
+    Bs58Decode(#[from] bs58::decode::Error),
     #[error("unhandled boxed dyn error {0}")]
     BoxedDynStd(#[from] Box<dyn std::error::Error>),
+    #[error("transaction was not confirmed in time")]
+    ConfirmationTimeout,
+    #[error("file not found: {0}")]
+    FileNotFound(String),
     #[error("formatting error")]
     FormatError(#[from] std::fmt::Error),
     // #[error("from utf8: {0}")]
@@ -30,8 +43,34 @@ pub enum Error {
     // InvalidDataItem,
     #[error("hashing failed")]
     InvalidHash,
+    #[error("invalid amount: {0}")]
+    InvalidAmount(String),
+    #[error("invalid arweave transaction id: {0}")]
+    InvalidArweaveTxId(String),
+    #[error("invalid batch transfer: {0}")]
+    InvalidBatchTransfer(String),
+    #[error("invalid cluster: {0}")]
+    InvalidCluster(String),
+    #[error("invalid collection: {0}")]
+    InvalidCollection(String),
+    #[error("invalid csv: {0}")]
+    InvalidCsv(String),
+    #[error("invalid distribution: {0}")]
+    InvalidDistribution(String),
+    #[error("invalid merkle tree: {0}")]
+    InvalidMerkleTree(String),
+    #[error("invalid metadata: {0}")]
+    InvalidMetadata(String),
+    #[error("invalid nonce account: {0}")]
+    InvalidNonceAccount(String),
+    #[error("invalid priority fee: {0}")]
+    InvalidPriorityFee(String),
     #[error("invalid proof")]
     InvalidProof,
+    #[error("invalid public key")]
+    InvalidPublicKey,
+    #[error("invalid signature")]
+    InvalidSignature,
     #[error("invalid tags")]
     InvalidTags,
     // #[error("insufficient sol funds")]
@@ -40,8 +79,8 @@ pub enum Error {
     // IOError(#[from] std::io::Error),
     // #[error("keypair not provided")]
     // KeyPairNotProvided,
-    // #[error("key rejected: {0}")]
-    // KeyRejected(#[from] KeyRejected),
+    #[error("key rejected: {0}")]
+    KeyRejected(#[from] KeyRejected),
     // #[error("manifest not found")]
     // ManifestNotFound,
     // #[error("file path not provided")]
@@ -52,31 +91,107 @@ pub enum Error {
     // NoBundleStatusesFound,
     // #[error("error getting oracle prices: {0}")]
     // OracleGetPriceError(reqwest::Error),
+    #[error("connected wallet is not the update authority of {0}")]
+    NotUpdateAuthority(solana_sdk::pubkey::Pubkey),
+    #[error("signatures failed verification for transactions {0:?}")]
+    PartialSignFailure(Vec<usize>),
+    #[error("phantom wallet disconnected: {0}")]
+    PhantomDisconnected(String),
+    #[error("phantom internal error: {0}")]
+    PhantomInternal(String),
+    #[error("phantom request failed ({code}): {message}")]
+    PhantomRequest { code: i64, message: String },
+    #[error("phantom unauthorized: {0}")]
+    PhantomUnauthorized(String),
+    #[error("phantom method not supported: {0}")]
+    PhantomUnsupportedMethod(String),
+    #[error("request rejected in phantom: {0}")]
+    PhantomUserRejected(String),
     #[error("phantom wallet not found")]
     PhantomWalletNotFound,
     #[error("reqwest: {0}")]
     Reqwest(#[from] reqwest::Error),
     #[error("future error")]
     JsFutureError,
+    #[error("javascript error: {0}")]
+    JsError(String),
     // #[error("ring unspecified: {0}")]
     // RingUnspecified(#[from] Unspecified),
-    // #[error("serde json: {0}")]
-    // SerdeJson(#[from] serde_json::Error),
-    // #[error("status code not ok")]
-    // StatusCodeNotOk,
+    #[error("serde wasm bindgen: {0}")]
+    SerdeWasmBindgen(#[from] serde_wasm_bindgen::Error),
+    #[error("serde json: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("status code not ok")]
+    StatusCodeNotOk,
     // #[error("status not found")]
     // StatusNotFound,
+    #[error("sign-in message issued for {0}")]
+    SignInDomainMismatch(String),
+    #[error("sign-in message expired")]
+    SignInExpired,
+    #[error("invalid sign-in message: {0}")]
+    SignInParse(String),
+    #[error("solana rpc error {code}: {message}")]
+    SolanaRpc { code: i64, message: String },
     // #[error("solana hash parse {0}")]
     // SolanaHashParse(#[from] solana_sdk::hash::ParseHashError),
     // #[error("solana network error")]
     // SolanaNetworkError,
     // #[error("solana hash parse {0}")]
     // TokioJoinError(#[from] tokio::task::JoinError),
+    #[error("transaction failed: {0}")]
+    TransactionFailed(String),
+    #[error("transaction not found: {0}")]
+    TransactionNotFound(String),
+    #[error("transaction rejected after preview")]
+    TransactionRejected,
+    #[error("unsupported transaction version: {0}")]
+    UnsupportedTransactionVersion(String),
     // #[error("transaction is not signed")]
     // UnsignedTransaction,
+    #[error("wallet not connected")]
+    WalletNotConnected,
     // #[error("url parse error: {0}")]
     // UrlParse(#[from] ParseError),
 }
 
+impl Error {
+    /// Decodes a rejected Phantom provider request. Provider errors carry an
+    /// [EIP-1193](https://eips.ethereum.org/EIPS/eip-1193#provider-errors) `code` and `message`.
+    pub fn from_provider(value: JsValue) -> Self {
+        let message = js_message(&value);
+        match js_sys::Reflect::get(&value, &JsValue::from_str("code"))
+            .ok()
+            .and_then(|code| code.as_f64())
+            .map(|code| code as i64)
+        {
+            Some(4001) => Error::PhantomUserRejected(message),
+            Some(4100) => Error::PhantomUnauthorized(message),
+            Some(4200) | Some(-32601) => Error::PhantomUnsupportedMethod(message),
+            Some(4900) => Error::PhantomDisconnected(message),
+            Some(-32603) => Error::PhantomInternal(message),
+            Some(code) => Error::PhantomRequest { code, message },
+            None => Error::JsError(message),
+        }
+    }
+}
+
+/// The `message` of a thrown JS error, or the value itself if it has none.
+fn js_message(value: &JsValue) -> String {
+    js_sys::Reflect::get(value, &JsValue::from_str("message"))
+        .ok()
+        .and_then(|message| message.as_string())
+        .or_else(|| value.as_string())
+        .unwrap_or_else(|| format!("{:?}", value))
+}
+
+/// Any other thrown JS value. Codes are only decoded for provider requests, see
+/// [`Error::from_provider`].
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Self {
+        Error::JsError(js_message(&value))
+    }
+}
+
 unsafe impl Send for Error {}
 unsafe impl Sync for Error {}
@@ -0,0 +1,331 @@
+//! Integrity checks for NFTs stored on Arweave: the metadata JSON and the assets it references
+//! are downloaded and re-merklized, and their data roots compared with the ones committed to
+//! by the Arweave transactions.
+
+use crate::{
+    arweave::{tx_id_from_url, ArweaveClient},
+    error::Error,
+    programs::metadata::{self, Metadata},
+    solana_rpc::RpcClient,
+    transaction::{merklize, Base64},
+};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+
+/// Result of checking one file referenced by an NFT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileCheck {
+    pub label: String,
+    pub uri: String,
+    /// Data root of the downloaded bytes.
+    pub data_root: Option<Base64>,
+    /// Data root committed to by the Arweave transaction.
+    pub arweave_data_root: Option<Base64>,
+    /// Confirmations of the Arweave transaction, `None` if it is not mined.
+    pub confirmations: Option<u64>,
+    /// Why the data could be downloaded but not compared with an Arweave transaction.
+    pub unverifiable: Option<String>,
+    pub error: Option<String>,
+}
+
+impl FileCheck {
+    fn new(label: &str, uri: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            uri: uri.to_string(),
+            data_root: None,
+            arweave_data_root: None,
+            confirmations: None,
+            unverifiable: None,
+            error: None,
+        }
+    }
+
+    /// Whether the downloaded bytes are exactly the ones stored on Arweave.
+    pub fn is_intact(&self) -> bool {
+        self.error.is_none() && self.data_root.is_some() && self.data_root == self.arweave_data_root
+    }
+
+    /// Whether the downloaded bytes differ from the ones stored on Arweave.
+    pub fn is_mismatch(&self) -> bool {
+        self.error.is_none()
+            && self.arweave_data_root.is_some()
+            && self.data_root != self.arweave_data_root
+    }
+
+    /// Whether the Arweave transaction has been mined.
+    pub fn is_permanent(&self) -> bool {
+        self.confirmations
+            .map_or(false, |confirmations| confirmations > 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityReport {
+    pub mint: Pubkey,
+    pub name: String,
+    pub checks: Vec<FileCheck>,
+}
+
+impl IntegrityReport {
+    pub fn is_intact(&self) -> bool {
+        self.checks.iter().all(FileCheck::is_intact)
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        self.checks.iter().all(FileCheck::is_permanent)
+    }
+
+    pub fn has_mismatch(&self) -> bool {
+        self.checks.iter().any(FileCheck::is_mismatch)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.checks.iter().any(|check| check.error.is_some())
+    }
+
+    /// Whether every file could be compared with its Arweave transaction.
+    pub fn is_verifiable(&self) -> bool {
+        self.checks.iter().all(|check| check.unverifiable.is_none())
+    }
+}
+
+/// Downloads the file at `uri`, recording its data root and that of its Arweave transaction
+/// in `check`. Returns the downloaded bytes. Files whose transaction is pending, or which are
+/// data items bundled into another transaction, are marked unverifiable rather than failed.
+async fn check_file(arweave: &ArweaveClient, check: &mut FileCheck) -> Result<Vec<u8>, Error> {
+    let id = tx_id_from_url(&check.uri)
+        .ok_or_else(|| Error::InvalidMetadata(format!("{} is not an Arweave URI", check.uri)))?;
+    let url = if check.uri.starts_with("ar://") {
+        arweave.data_url(&id)
+    } else {
+        check.uri.clone()
+    };
+    let data = arweave.get_data(&url).await?;
+    check.data_root = Some(merklize(data.clone())?.data_root);
+    match arweave.get_transaction(&id).await {
+        Ok(transaction) => check.arweave_data_root = Some(transaction.data_root),
+        Err(Error::TransactionNotFound(_)) => {
+            check.unverifiable =
+                Some("no Arweave transaction, likely a bundled data item".to_string());
+            return Ok(data);
+        }
+        Err(Error::ArweaveTransactionPending(_)) => {
+            check.unverifiable = Some("Arweave transaction is pending".to_string());
+            return Ok(data);
+        }
+        Err(error) => return Err(error),
+    }
+    check.confirmations = arweave
+        .get_status(&id)
+        .await?
+        .map(|status| status.number_of_confirmations);
+    Ok(data)
+}
+
+/// URIs of the image and every file listed in the metadata JSON, without duplicates.
+fn asset_uris(json: &Value) -> Vec<(String, String)> {
+    let mut uris: Vec<(String, String)> = Vec::new();
+    let mut add = |label: &str, uri: Option<&str>| {
+        if let Some(uri) = uri.filter(|uri| !uri.is_empty()) {
+            if !uris.iter().any(|(_, known)| known == uri) {
+                uris.push((label.to_string(), uri.to_string()));
+            }
+        }
+    };
+    add("Image", json["image"].as_str());
+    add("Animation", json["animation_url"].as_str());
+    if let Some(files) = json["properties"]["files"].as_array() {
+        for file in files {
+            add("File", file["uri"].as_str());
+        }
+    }
+    uris
+}
+
+/// Follows `mint` to its metadata account, JSON metadata and assets, checking that each file
+/// still matches its Arweave transaction.
+pub async fn check_nft(
+    rpc: &RpcClient,
+    arweave: &ArweaveClient,
+    mint: &Pubkey,
+) -> Result<IntegrityReport, Error> {
+    let address = metadata::find_metadata_account(mint);
+    let account = rpc
+        .get_account_info(&address)
+        .await?
+        .value
+        .ok_or_else(|| Error::InvalidMetadata(format!("no metadata account for {}", mint)))?;
+    let metadata = Metadata::unpack(&account.data)?;
+
+    let mut checks = Vec::new();
+    let mut json_check = FileCheck::new("Metadata", &metadata.data.uri);
+    let json = check_file(arweave, &mut json_check)
+        .await
+        .and_then(|json| Ok(serde_json::from_slice::<Value>(&json)?));
+    if let Err(error) = &json {
+        json_check.error = Some(error.to_string());
+    }
+    checks.push(json_check);
+
+    if let Ok(json) = json {
+        for (label, uri) in asset_uris(&json) {
+            let mut check = FileCheck::new(&label, &uri);
+            if let Err(error) = check_file(arweave, &mut check).await {
+                check.error = Some(error.to_string());
+            }
+            checks.push(check);
+        }
+    }
+
+    Ok(IntegrityReport {
+        mint: *mint,
+        name: metadata.data.name,
+        checks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{block_on, serve};
+    use serde_json::json;
+
+    fn id(byte: u8) -> String {
+        Base64(vec![byte; 32]).to_string()
+    }
+
+    fn header(data: &[u8]) -> String {
+        serde_json::to_string(&merklize(data.to_vec()).unwrap()).unwrap()
+    }
+
+    /// Metadata account data up to `is_mutable`, as written by older versions of the program.
+    fn metadata_account(mint: &Pubkey, name: &str, uri: &str) -> Vec<u8> {
+        let mut data = vec![4];
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        for str in [name, "TST", uri] {
+            data.extend_from_slice(&(str.len() as u32).to_le_bytes());
+            data.extend_from_slice(str.as_bytes());
+        }
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 1]);
+        data
+    }
+
+    fn rpc(account: Option<Vec<u8>>) -> RpcClient {
+        let value = account.map(|data| {
+            json!({
+                "lamports": 1,
+                "owner": metadata::ID.to_string(),
+                "data": [base64::encode(data), "base64"],
+                "executable": false,
+                "rentEpoch": 0
+            })
+        });
+        let (url, _requests) = serve(vec![(
+            200,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": { "context": { "slot": 1 }, "value": value }
+            })
+            .to_string(),
+        )]);
+        RpcClient::new(&url)
+    }
+
+    #[test]
+    fn lists_asset_uris_once() {
+        let json = json!({
+            "image": "ar://image",
+            "animation_url": "",
+            "properties": {
+                "files": [{ "uri": "ar://image" }, { "uri": "ar://video" }, { "type": "x" }]
+            }
+        });
+        assert_eq!(
+            asset_uris(&json),
+            vec![
+                ("Image".to_string(), "ar://image".to_string()),
+                ("File".to_string(), "ar://video".to_string()),
+            ]
+        );
+        assert!(asset_uris(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn separates_unverifiable_files_from_mismatches() {
+        let mint = Pubkey::new_unique();
+        let json = json!({
+            "name": "Test",
+            "image": format!("ar://{}", id(2)),
+            "animation_url": format!("ar://{}", id(3)),
+            "properties": { "files": [
+                { "uri": format!("ar://{}", id(2)) },
+                { "uri": format!("ar://{}", id(4)) }
+            ] }
+        })
+        .to_string();
+        let status = json!({ "block_height": 10, "number_of_confirmations": 3 }).to_string();
+        let (url, requests) = serve(vec![
+            (200, json.clone()),
+            (200, header(json.as_bytes())),
+            (200, status.clone()),
+            (200, "image".to_string()),
+            (404, "Not Found.".to_string()),
+            (200, "animation".to_string()),
+            (200, header(b"tampered")),
+            (200, status),
+            (200, "file".to_string()),
+            (202, "Pending".to_string()),
+        ]);
+        let rpc = rpc(Some(metadata_account(
+            &mint,
+            "Test",
+            &format!("ar://{}", id(1)),
+        )));
+        let report = block_on(check_nft(&rpc, &ArweaveClient::new(&url), &mint)).unwrap();
+        assert_eq!(report.name, "Test");
+
+        let paths: Vec<String> = requests.try_iter().map(|request| request.path).collect();
+        assert_eq!(
+            paths[..3],
+            [
+                format!("/{}", id(1)),
+                format!("/tx/{}", id(1)),
+                format!("/tx/{}/status", id(1)),
+            ]
+        );
+
+        let labels: Vec<&str> = report
+            .checks
+            .iter()
+            .map(|check| check.label.as_str())
+            .collect();
+        assert_eq!(labels, ["Metadata", "Image", "Animation", "File"]);
+        let [metadata, image, animation, file] = [0, 1, 2, 3].map(|i| &report.checks[i]);
+        assert!(metadata.is_intact() && metadata.is_permanent());
+        assert!(image.unverifiable.is_some() && !image.is_intact() && !image.is_mismatch());
+        assert!(animation.is_mismatch() && animation.unverifiable.is_none());
+        assert_eq!(
+            file.unverifiable.as_deref(),
+            Some("Arweave transaction is pending")
+        );
+        assert!(!file.is_mismatch());
+
+        assert!(report.has_mismatch());
+        assert!(!report.has_errors());
+        assert!(!report.is_verifiable());
+    }
+
+    #[test]
+    fn requires_metadata_account() {
+        let rpc = rpc(None);
+        let arweave = ArweaveClient::new("http://127.0.0.1:1");
+        assert!(matches!(
+            block_on(check_nft(&rpc, &arweave, &Pubkey::new_unique())),
+            Err(Error::InvalidMetadata(_))
+        ));
+    }
+}
@@ -0,0 +1,6 @@
+//! Modules without browser dependencies, shared with native builds. A server authenticating
+//! users of the app depends on this crate and calls [`siws::verify_sign_in`] with the message
+//! and signature posted by the browser.
+
+pub mod error;
+pub mod siws;
@@ -1,16 +1,50 @@
+mod arweave;
+mod batch_transfer;
+mod collection;
 mod components;
+mod concurrent_merkle;
+mod confirmation;
 mod crypto;
-mod error;
+mod csv;
+mod integrity;
 mod merkle;
+mod merkle_distributor;
+mod nonce;
+mod notarization;
+mod preview;
+mod priority_fee;
+mod programs;
+mod solana_rpc;
 mod store;
+#[cfg(test)]
+mod stub_server;
 mod transaction;
 use components::{
+    alert::ErrorAlert,
+    approval::TransactionApproval,
+    arweave_wallet::ArweaveKeyfile,
+    batch_transfer::BatchTransferForm,
+    cluster::ClusterSelector,
+    collection_mint::CollectionMintForm,
+    compressed_proof::CompressedProofForm,
+    confirmation::ConfirmationProgress,
     files::FilesSelector,
+    merkle_distributor::MerkleDistributorForm,
+    nft_integrity::NftIntegrityForm,
+    nft_mint::NftMintForm,
+    nonce::NonceForm,
+    notarize::NotarizeForm,
     phantom_wallet::{PhantomWallet, Wallet},
+    priority_fee::PriorityFeeSelector,
+    token_metadata::TokenMetadataForm,
+    token_mint::TokenMintForm,
+    transfer::TransferForm,
+    verify_notarization::VerifyNotarizationForm,
 };
 use store::*;
 use sycamore::prelude::*;
 use wasm_bindgen::{prelude::*, JsCast};
+use wasm_token_app::{error, siws};
 
 #[derive(Prop)]
 struct CounterProps<'a> {
@@ -35,6 +69,7 @@ fn Counter<'a, G: Html>(ctx: ScopeRef<'a>, props: CounterProps<'a>) -> View<G> {
 #[component]
 fn App<G: Html>(ctx: ScopeRef) -> View<G> {
     initialize_store(ctx);
+    PhantomWallet::reconnect_if_trusted(ctx);
     let label = ctx.create_signal("count".to_string());
     let wallet = ctx.use_context::<Signal<PhantomWallet>>();
     ctx.create_effect(|| log::debug!("Connection status changed to {:?}", wallet.get()));
@@ -44,11 +79,29 @@ fn App<G: Html>(ctx: ScopeRef) -> View<G> {
             h1(class="text-2xl text-slate-200 font-semibold pt-8") {
                 "WASM Token App"
             }
+            ErrorAlert {}
+            TransactionApproval {}
+            ClusterSelector {}
+            PriorityFeeSelector {}
             Counter {
                 label: label
             }
             FilesSelector {}
             Wallet {}
+            TransferForm {}
+            BatchTransferForm {}
+            TokenMintForm {}
+            TokenMetadataForm {}
+            ArweaveKeyfile {}
+            NftMintForm {}
+            CollectionMintForm {}
+            NonceForm {}
+            NotarizeForm {}
+            VerifyNotarizationForm {}
+            NftIntegrityForm {}
+            MerkleDistributorForm {}
+            CompressedProofForm {}
+            ConfirmationProgress {}
         }
     }
 }
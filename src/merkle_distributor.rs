@@ -0,0 +1,355 @@
+//! Merkle trees for token distributions. Each leaf commits to a `(index, claimant, amount)`
+//! claim, and a claimant proves their allocation to a merkle-distributor program with the
+//! sibling hashes on the path to the root instead of being sent a transfer.
+//!
+//! Leaves are `hash(index as u64 LE || claimant || amount as u64 LE)` and each pair of nodes is
+//! hashed in sorted order, so a proof is just the list of siblings.
+
+use crate::{csv, error::Error, transaction::stringify};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{hash, keccak, pubkey::Pubkey};
+use std::{collections::HashSet, fmt, str::FromStr};
+
+const CLAIMANT_COLUMN: &str = "claimant";
+const AMOUNT_COLUMN: &str = "amount";
+
+/// Hash function used for leaves and nodes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LeafHash {
+    Keccak,
+    Sha256,
+}
+
+impl Default for LeafHash {
+    fn default() -> LeafHash {
+        LeafHash::Keccak
+    }
+}
+
+impl fmt::Display for LeafHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LeafHash::Keccak => write!(f, "keccak"),
+            LeafHash::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+impl FromStr for LeafHash {
+    type Err = Error;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "keccak" => Ok(LeafHash::Keccak),
+            "sha256" => Ok(LeafHash::Sha256),
+            _ => Err(Error::InvalidDistribution(format!("unknown hash {}", str))),
+        }
+    }
+}
+
+impl LeafHash {
+    fn hashv(&self, values: &[&[u8]]) -> [u8; 32] {
+        match self {
+            LeafHash::Keccak => keccak::hashv(values).to_bytes(),
+            LeafHash::Sha256 => hash::hashv(values).to_bytes(),
+        }
+    }
+
+    pub fn leaf(&self, index: u64, claimant: &Pubkey, amount: u64) -> [u8; 32] {
+        self.hashv(&[
+            &index.to_le_bytes(),
+            claimant.as_ref(),
+            &amount.to_le_bytes(),
+        ])
+    }
+
+    fn node(&self, a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        if a <= b {
+            self.hashv(&[a, b])
+        } else {
+            self.hashv(&[b, a])
+        }
+    }
+}
+
+/// Lowercase hex, as used for roots and proofs in exported distributions.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Result<[u8; 32], Error> {
+    let invalid = || Error::InvalidDistribution(format!("invalid hash {}", hex));
+    let hex = hex.trim_start_matches("0x");
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+/// One claimant's allocation and the proof of its leaf.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Claim {
+    pub index: u64,
+    #[serde(with = "stringify")]
+    pub claimant: Pubkey,
+    #[serde(with = "stringify")]
+    pub amount: u64,
+    pub proof: Vec<String>,
+}
+
+/// A distribution as exported to JSON, with every claimant's proof.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Distribution {
+    pub hash: LeafHash,
+    pub merkle_root: String,
+    #[serde(with = "stringify")]
+    pub token_total: u64,
+    pub num_nodes: u64,
+    pub claims: Vec<Claim>,
+}
+
+impl Distribution {
+    /// Builds the tree over `allocations`, indexed in order. Each claimant may appear once.
+    pub fn new(hash: LeafHash, allocations: &[(Pubkey, u64)]) -> Result<Self, Error> {
+        if allocations.is_empty() {
+            return Err(Error::InvalidDistribution("no claimants".to_string()));
+        }
+        let mut claimants = HashSet::new();
+        let mut token_total: u64 = 0;
+        for (claimant, amount) in allocations {
+            if !claimants.insert(claimant) {
+                return Err(Error::InvalidDistribution(format!(
+                    "{} is listed more than once",
+                    claimant
+                )));
+            }
+            token_total = token_total
+                .checked_add(*amount)
+                .ok_or_else(|| Error::InvalidDistribution("total overflows u64".to_string()))?;
+        }
+
+        let leaves: Vec<[u8; 32]> = allocations
+            .iter()
+            .enumerate()
+            .map(|(index, (claimant, amount))| hash.leaf(index as u64, claimant, *amount))
+            .collect();
+        let layers = build_layers(hash, leaves);
+        let root = layers.last().and_then(|layer| layer.first()).copied();
+        let claims = allocations
+            .iter()
+            .enumerate()
+            .map(|(index, (claimant, amount))| Claim {
+                index: index as u64,
+                claimant: *claimant,
+                amount: *amount,
+                proof: proof(&layers, index)
+                    .iter()
+                    .map(|node| to_hex(node))
+                    .collect(),
+            })
+            .collect();
+        Ok(Self {
+            hash,
+            merkle_root: to_hex(&root.unwrap_or_default()),
+            token_total,
+            num_nodes: allocations.len() as u64,
+            claims,
+        })
+    }
+
+    pub fn claim(&self, claimant: &Pubkey) -> Option<&Claim> {
+        self.claims.iter().find(|claim| claim.claimant == *claimant)
+    }
+
+    /// Checks that `claim` is a leaf of `root`. The root must come from a trusted source,
+    /// such as the distributor account or where it was published, not from the export itself.
+    pub fn verify(&self, claim: &Claim, root: &[u8; 32]) -> Result<bool, Error> {
+        let proof = claim
+            .proof
+            .iter()
+            .map(|node| from_hex(node))
+            .collect::<Result<Vec<_>, _>>()?;
+        let leaf = self.hash.leaf(claim.index, &claim.claimant, claim.amount);
+        Ok(verify(self.hash, root, leaf, &proof))
+    }
+}
+
+/// Hashes `leaves` pairwise up to the root, returning every layer starting with the leaves.
+/// The last node of an odd layer is carried up unchanged.
+fn build_layers(hash: LeafHash, leaves: Vec<[u8; 32]>) -> Vec<Vec<[u8; 32]>> {
+    let mut layers = vec![leaves];
+    while layers.last().map_or(false, |layer| layer.len() > 1) {
+        let next = layers
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => hash.node(a, b),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+        layers.push(next);
+    }
+    layers
+}
+
+/// Siblings on the path from leaf `index` to the root.
+fn proof(layers: &[Vec<[u8; 32]>], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    for layer in &layers[..layers.len() - 1] {
+        if let Some(sibling) = layer.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+    }
+    proof
+}
+
+pub fn verify(hash: LeafHash, root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, sibling| hash.node(&node, sibling));
+    computed == *root
+}
+
+/// Reads `claimant` and `amount` columns, with amounts in the mint's base units.
+pub fn parse_allocations(text: &str) -> Result<Vec<(Pubkey, u64)>, Error> {
+    let (header, records) = csv::parse_with_header(text)?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::InvalidCsv(format!("missing {} column", name)))
+    };
+    let claimant = column(CLAIMANT_COLUMN)?;
+    let amount = column(AMOUNT_COLUMN)?;
+    records
+        .iter()
+        .enumerate()
+        .map(|(row, record)| {
+            let invalid =
+                |what: &str| Error::InvalidCsv(format!("row {}: invalid {}", row + 1, what));
+            Ok((
+                Pubkey::from_str(record[claimant].trim()).map_err(|_| invalid(CLAIMANT_COLUMN))?,
+                record[amount]
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| invalid(AMOUNT_COLUMN))?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocations(count: u64) -> Vec<(Pubkey, u64)> {
+        (0..count)
+            .map(|i| (Pubkey::new_unique(), 1_000 * (i + 1)))
+            .collect()
+    }
+
+    #[test]
+    fn encodes_leaves() {
+        let claimant = Pubkey::new_unique();
+        let mut bytes = 7u64.to_le_bytes().to_vec();
+        bytes.extend_from_slice(claimant.as_ref());
+        bytes.extend_from_slice(&500u64.to_le_bytes());
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(
+            LeafHash::Keccak.leaf(7, &claimant, 500),
+            keccak::hash(&bytes).to_bytes()
+        );
+        assert_eq!(
+            LeafHash::Sha256.leaf(7, &claimant, 500),
+            hash::hash(&bytes).to_bytes()
+        );
+    }
+
+    #[test]
+    fn hashes_pairs_in_sorted_order() {
+        let (a, b) = ([1; 32], [2; 32]);
+        let expected = keccak::hashv(&[&a, &b]).to_bytes();
+        assert_eq!(LeafHash::Keccak.node(&a, &b), expected);
+        assert_eq!(LeafHash::Keccak.node(&b, &a), expected);
+    }
+
+    #[test]
+    fn carries_the_last_node_of_odd_layers() {
+        let allocations = allocations(3);
+        let distribution = Distribution::new(LeafHash::Sha256, &allocations).unwrap();
+        let leaves: Vec<[u8; 32]> = allocations
+            .iter()
+            .enumerate()
+            .map(|(i, (claimant, amount))| LeafHash::Sha256.leaf(i as u64, claimant, *amount))
+            .collect();
+        let root =
+            LeafHash::Sha256.node(&LeafHash::Sha256.node(&leaves[0], &leaves[1]), &leaves[2]);
+        assert_eq!(distribution.merkle_root, to_hex(&root));
+        assert_eq!(
+            distribution.claims[2].proof,
+            vec![to_hex(&LeafHash::Sha256.node(&leaves[0], &leaves[1]))]
+        );
+        assert_eq!(distribution.token_total, 6_000);
+    }
+
+    #[test]
+    fn proofs_round_trip() {
+        for count in 1..=9 {
+            let distribution = Distribution::new(LeafHash::Keccak, &allocations(count)).unwrap();
+            let root = from_hex(&distribution.merkle_root).unwrap();
+            let json = serde_json::to_string(&distribution).unwrap();
+            let distribution: Distribution = serde_json::from_str(&json).unwrap();
+            for claim in &distribution.claims {
+                assert!(
+                    distribution.verify(claim, &root).unwrap(),
+                    "{} of {}",
+                    claim.index,
+                    count
+                );
+                let inflated = Claim {
+                    amount: claim.amount + 1,
+                    ..claim.clone()
+                };
+                assert!(!distribution.verify(&inflated, &root).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_exports() {
+        let published = Distribution::new(LeafHash::Keccak, &allocations(4)).unwrap();
+        let root = from_hex(&published.merkle_root).unwrap();
+        let mut allocations: Vec<(Pubkey, u64)> = published
+            .claims
+            .iter()
+            .map(|claim| (claim.claimant, claim.amount))
+            .collect();
+        allocations[1].1 *= 100;
+        let tampered = Distribution::new(LeafHash::Keccak, &allocations).unwrap();
+        assert!(tampered
+            .verify(
+                &tampered.claims[1],
+                &from_hex(&tampered.merkle_root).unwrap()
+            )
+            .unwrap());
+        assert!(!tampered.verify(&tampered.claims[1], &root).unwrap());
+    }
+
+    #[test]
+    fn parses_hex() {
+        let bytes = [0xab; 32];
+        assert_eq!(from_hex(&to_hex(&bytes)).unwrap(), bytes);
+        assert_eq!(from_hex(&format!("0x{}", to_hex(&bytes))).unwrap(), bytes);
+        assert!(from_hex("abcd").is_err());
+        assert!(from_hex(&"zz".repeat(32)).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Durable nonce accounts. A transaction whose first instruction advances a nonce account and
+//! whose blockhash is the stored nonce stays valid until the nonce is advanced, so it can be
+//! signed hours later or passed between several signers.
+
+use crate::{error::Error, solana_rpc::RpcClient};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+    transaction::Transaction,
+};
+
+/// Size of the `nonce::state::Versions` account data.
+pub const NONCE_ACCOUNT_LEN: usize = 80;
+
+const INITIALIZED: u32 = 1;
+
+/// Initialized nonce account state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonceAccount {
+    pub authority: Pubkey,
+    /// Nonce to use as the blockhash of durable transactions.
+    pub blockhash: Hash,
+    pub lamports_per_signature: u64,
+}
+
+impl NonceAccount {
+    /// Reads nonce account data: a `u32` version, a `u32` state, then the authority, nonce and
+    /// fee calculator of an initialized account.
+    pub fn unpack(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != NONCE_ACCOUNT_LEN {
+            return Err(Error::InvalidNonceAccount(format!(
+                "expected {} bytes, found {}",
+                NONCE_ACCOUNT_LEN,
+                data.len()
+            )));
+        }
+        let u32_at = |offset: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&data[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        if u32_at(4) != INITIALIZED {
+            return Err(Error::InvalidNonceAccount("not initialized".to_string()));
+        }
+        let mut lamports_per_signature = [0; 8];
+        lamports_per_signature.copy_from_slice(&data[72..80]);
+        Ok(Self {
+            authority: Pubkey::new(&data[8..40]),
+            blockhash: Hash::new(&data[40..72]),
+            lamports_per_signature: u64::from_le_bytes(lamports_per_signature),
+        })
+    }
+
+    /// Fetches and unpacks the nonce account at `address`.
+    pub async fn fetch(rpc: &RpcClient, address: &Pubkey) -> Result<Self, Error> {
+        let account = rpc
+            .get_account_info(address)
+            .await?
+            .value
+            .ok_or_else(|| Error::InvalidNonceAccount(format!("{} not found", address)))?;
+        if account.owner != system_program::id() {
+            return Err(Error::InvalidNonceAccount(format!(
+                "{} is not owned by the system program",
+                address
+            )));
+        }
+        Self::unpack(&account.data)
+    }
+}
+
+/// Builds an unsigned transaction that advances `nonce_account` before running
+/// `instructions`, using its current nonce as the blockhash.
+pub fn durable_transaction(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    nonce_account: &Pubkey,
+    nonce: &NonceAccount,
+) -> Transaction {
+    let mut all = vec![system_instruction::advance_nonce_account(
+        nonce_account,
+        &nonce.authority,
+    )];
+    all.extend_from_slice(instructions);
+    Transaction::new_unsigned(Message::new_with_blockhash(
+        &all,
+        Some(payer),
+        &nonce.blockhash,
+    ))
+}
+
+/// Nonce account advanced by the first instruction of `message`, which must stay first.
+pub fn advanced_nonce_account(message: &Message) -> Option<Pubkey> {
+    let instruction = message.instructions.first()?;
+    let advances = message.account_keys[instruction.program_id_index as usize]
+        == system_program::id()
+        && matches!(
+            bincode::deserialize::<SystemInstruction>(&instruction.data),
+            Ok(SystemInstruction::AdvanceNonceAccount)
+        );
+    if !advances {
+        return None;
+    }
+    let account = *instruction.accounts.first()?;
+    message.account_keys.get(account as usize).copied()
+}
+
+/// Whether `message` is a durable transaction: it advances a nonce account and its blockhash is
+/// the nonce stored there, so the blockhash must not be replaced and does not expire. Advancing
+/// a nonce with a recent blockhash is an ordinary transaction. Only nonce accounts are fetched.
+pub async fn is_durable(rpc: &RpcClient, message: &Message) -> Result<bool, Error> {
+    match advanced_nonce_account(message) {
+        Some(address) => {
+            Ok(NonceAccount::fetch(rpc, &address).await?.blockhash == message.recent_blockhash)
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Account data of an initialized nonce account, laid out field by field.
+    fn data(authority: &Pubkey, blockhash: &Hash, lamports_per_signature: u64) -> Vec<u8> {
+        let mut data = Vec::with_capacity(NONCE_ACCOUNT_LEN);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&INITIALIZED.to_le_bytes());
+        data.extend_from_slice(authority.as_ref());
+        data.extend_from_slice(blockhash.as_ref());
+        data.extend_from_slice(&lamports_per_signature.to_le_bytes());
+        data
+    }
+
+    fn invalid(data: &[u8]) -> String {
+        match NonceAccount::unpack(data) {
+            Err(Error::InvalidNonceAccount(reason)) => reason,
+            other => panic!("expected an invalid nonce account, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unpacks_initialized_accounts() {
+        let authority = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let data = data(&authority, &blockhash, 5_000);
+        assert_eq!(data.len(), NONCE_ACCOUNT_LEN);
+        assert_eq!(
+            NonceAccount::unpack(&data).unwrap(),
+            NonceAccount {
+                authority,
+                blockhash,
+                lamports_per_signature: 5_000,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_uninitialized_accounts() {
+        let mut data = data(&Pubkey::new_unique(), &Hash::new_unique(), 5_000);
+        data[4..8].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(invalid(&data), "not initialized");
+        assert_eq!(invalid(&[0; NONCE_ACCOUNT_LEN]), "not initialized");
+    }
+
+    #[test]
+    fn rejects_data_of_the_wrong_size() {
+        let data = data(&Pubkey::new_unique(), &Hash::new_unique(), 5_000);
+        assert_eq!(invalid(&data[..79]), "expected 80 bytes, found 79");
+        assert_eq!(
+            invalid(&[data, vec![0]].concat()),
+            "expected 80 bytes, found 81"
+        );
+        assert_eq!(invalid(&[]), "expected 80 bytes, found 0");
+    }
+
+    #[test]
+    fn finds_the_advanced_nonce_account() {
+        let payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let nonce = NonceAccount {
+            authority: payer,
+            blockhash: Hash::new_unique(),
+            lamports_per_signature: 5_000,
+        };
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        let durable = durable_transaction(&[transfer.clone()], &payer, &nonce_account, &nonce);
+        assert_eq!(durable.message.recent_blockhash, nonce.blockhash);
+        assert_eq!(
+            advanced_nonce_account(&durable.message),
+            Some(nonce_account)
+        );
+
+        // Advancing a nonce anywhere but first does not make a transaction durable.
+        let advance = system_instruction::advance_nonce_account(&nonce_account, &payer);
+        let later = Message::new(&[transfer.clone(), advance], Some(&payer));
+        assert_eq!(advanced_nonce_account(&later), None);
+        // Neither do other system instructions.
+        let plain = Message::new(&[transfer], Some(&payer));
+        assert_eq!(advanced_nonce_account(&plain), None);
+        let empty = Message::new(&[], Some(&payer));
+        assert_eq!(advanced_nonce_account(&empty), None);
+    }
+}
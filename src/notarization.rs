@@ -0,0 +1,327 @@
+//! Proof of existence: a file's Arweave `data_root` written to a Solana memo signed by the
+//! connected wallet, which timestamps the file and attributes it to the wallet.
+
+use crate::{
+    error::Error,
+    programs::memo,
+    solana_rpc::{ConfirmedTransaction, RpcClient},
+    transaction::{merklize, Base64},
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+/// Most recent transactions of an address searched for notarizations.
+const ADDRESS_SEARCH_LIMIT: usize = 1_000;
+/// Marker identifying notarization memos, as serialized by [`NotarizationRecord::memo`].
+const RECORD_TYPE: &str = "arweave-data-root";
+
+/// Memo recording a file's `data_root`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "arweave-data-root", rename_all = "camelCase")]
+pub struct NotarizationRecord {
+    pub data_root: Base64,
+    pub data_size: u64,
+    /// Arweave transaction storing the file, if it has been uploaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arweave_tx: Option<Base64>,
+}
+
+impl NotarizationRecord {
+    /// Merklizes `data` the same way as an Arweave upload.
+    pub fn new(data: Vec<u8>, arweave_tx: Option<Base64>) -> Result<Self, Error> {
+        let transaction = merklize(data)?;
+        Ok(Self {
+            data_root: transaction.data_root,
+            data_size: transaction.data_size,
+            arweave_tx,
+        })
+    }
+
+    pub fn memo(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parses a memo, returning `None` for memos that are not notarization records.
+    pub fn parse(memo: &str) -> Option<Self> {
+        serde_json::from_str(memo).ok()
+    }
+}
+
+/// A notarization record found on-chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnChainNotarization {
+    pub signature: Signature,
+    pub slot: u64,
+    /// Unix timestamp of the block, if the cluster still has it.
+    pub block_time: Option<i64>,
+    /// Accounts that signed the memo, or the fee payer of a memo without signers.
+    pub signers: Vec<Pubkey>,
+    pub record: NotarizationRecord,
+}
+
+impl OnChainNotarization {
+    /// Whether this records the same `data_root` and size as `record`.
+    pub fn matches(&self, record: &NotarizationRecord) -> bool {
+        self.record.data_root == record.data_root && self.record.data_size == record.data_size
+    }
+}
+
+/// Reads the notarization records in the memos of a successful `confirmed` transaction.
+fn notarizations(
+    signature: Signature,
+    confirmed: &ConfirmedTransaction,
+) -> Vec<OnChainNotarization> {
+    if confirmed.err.is_some() {
+        return vec![];
+    }
+    let message = &confirmed.transaction.message;
+    message
+        .instructions
+        .iter()
+        .filter(|instruction| {
+            let program_id = message.account_keys[instruction.program_id_index as usize];
+            program_id == memo::id() || program_id == memo::v1::id()
+        })
+        .filter_map(|instruction| {
+            let record = NotarizationRecord::parse(std::str::from_utf8(&instruction.data).ok()?)?;
+            let mut signers: Vec<Pubkey> = instruction
+                .accounts
+                .iter()
+                .map(|&index| index as usize)
+                .filter(|&index| message.is_signer(index))
+                .map(|index| message.account_keys[index])
+                .collect();
+            if signers.is_empty() {
+                signers.push(message.account_keys[0]);
+            }
+            Some(OnChainNotarization {
+                signature,
+                slot: confirmed.slot,
+                block_time: confirmed.block_time,
+                signers,
+                record,
+            })
+        })
+        .collect()
+}
+
+/// Fetches the notarizations recorded by the transaction `signature`.
+pub async fn fetch_by_signature(
+    rpc: &RpcClient,
+    signature: &Signature,
+) -> Result<Vec<OnChainNotarization>, Error> {
+    let confirmed = rpc
+        .get_transaction(signature)
+        .await?
+        .ok_or_else(|| Error::TransactionNotFound(signature.to_string()))?;
+    Ok(notarizations(*signature, &confirmed))
+}
+
+/// Fetches the notarizations among the recent transactions of `address`, newest first.
+/// Transactions that cannot be fetched or decoded are logged and skipped, so one of them does
+/// not hide the records of the others.
+pub async fn fetch_by_address(
+    rpc: &RpcClient,
+    address: &Pubkey,
+) -> Result<Vec<OnChainNotarization>, Error> {
+    let mut found = Vec::new();
+    for info in rpc
+        .get_signatures_for_address(address, ADDRESS_SEARCH_LIMIT)
+        .await?
+    {
+        let has_record = info
+            .memo
+            .as_ref()
+            .map_or(false, |memo| memo.contains(RECORD_TYPE));
+        if info.err.is_none() && has_record {
+            match fetch_by_signature(rpc, &info.signature).await {
+                Ok(notarizations) => found.extend(notarizations),
+                Err(error) => log::error!("{}: {}", info.signature, error),
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{block_on, serve};
+    use serde_json::{json, Value};
+    use solana_sdk::{
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        transaction::Transaction,
+    };
+
+    fn record() -> NotarizationRecord {
+        NotarizationRecord {
+            data_root: Base64(vec![7; 32]),
+            data_size: 1_024,
+            arweave_tx: Some(Base64(vec![9; 32])),
+        }
+    }
+
+    fn v1_memo(memo: &[u8]) -> Instruction {
+        Instruction {
+            program_id: memo::v1::id(),
+            accounts: vec![],
+            data: memo.to_vec(),
+        }
+    }
+
+    fn transaction(instructions: &[Instruction], payer: &Pubkey) -> Transaction {
+        Transaction::new_unsigned(Message::new_with_blockhash(
+            instructions,
+            Some(payer),
+            &Hash::default(),
+        ))
+    }
+
+    fn confirmed(transaction: Transaction, err: Option<Value>) -> ConfirmedTransaction {
+        ConfirmedTransaction {
+            slot: 11,
+            block_time: Some(1_700_000_000),
+            transaction,
+            err,
+        }
+    }
+
+    #[test]
+    fn parses_records() {
+        let record = record();
+        let memo = String::from_utf8(record.memo().unwrap()).unwrap();
+        assert!(memo.contains(RECORD_TYPE));
+        assert_eq!(NotarizationRecord::parse(&memo), Some(record.clone()));
+
+        let without_upload = NotarizationRecord {
+            arweave_tx: None,
+            ..record
+        };
+        let memo = String::from_utf8(without_upload.memo().unwrap()).unwrap();
+        assert!(!memo.contains("arweaveTx"));
+        assert_eq!(NotarizationRecord::parse(&memo), Some(without_upload));
+    }
+
+    #[test]
+    fn ignores_other_memos() {
+        let record = serde_json::to_value(record()).unwrap();
+        let mut other_type = record.clone();
+        other_type["type"] = json!("something-else");
+        let mut untyped = record;
+        untyped.as_object_mut().unwrap().remove("type");
+        for memo in [
+            "hello".to_string(),
+            String::new(),
+            other_type.to_string(),
+            untyped.to_string(),
+            json!({ "type": RECORD_TYPE }).to_string(),
+        ] {
+            assert_eq!(NotarizationRecord::parse(&memo), None, "{}", memo);
+        }
+    }
+
+    #[test]
+    fn reads_records_from_both_memo_versions() {
+        let payer = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let memo = record().memo().unwrap();
+        let transaction = transaction(
+            &[
+                memo::build_memo(&memo, &[&signer]),
+                v1_memo(&memo),
+                memo::build_memo(b"not a record", &[&payer]),
+                v1_memo(&[0xff, 0xfe]),
+                Instruction::new_with_bytes(
+                    Pubkey::new_unique(),
+                    &memo,
+                    vec![AccountMeta::new_readonly(payer, true)],
+                ),
+            ],
+            &payer,
+        );
+        let signature = Signature::new(&[1; 64]);
+        let found = notarizations(signature, &confirmed(transaction, None));
+        assert_eq!(found.len(), 2);
+        for notarization in &found {
+            assert_eq!(notarization.signature, signature);
+            assert_eq!(notarization.slot, 11);
+            assert_eq!(notarization.block_time, Some(1_700_000_000));
+            assert_eq!(notarization.record, record());
+            assert!(notarization.matches(&NotarizationRecord {
+                arweave_tx: None,
+                ..record()
+            }));
+        }
+        assert_eq!(found[0].signers, vec![signer]);
+        // A v1 memo has no signers, so it is attributed to the fee payer.
+        assert_eq!(found[1].signers, vec![payer]);
+    }
+
+    #[test]
+    fn skips_failed_transactions() {
+        let payer = Pubkey::new_unique();
+        let memo = record().memo().unwrap();
+        let transaction = transaction(&[memo::build_memo(&memo, &[&payer])], &payer);
+        let err = json!({ "InstructionError": [0, { "Custom": 1 }] });
+        assert!(
+            notarizations(Signature::new(&[2; 64]), &confirmed(transaction, Some(err))).is_empty()
+        );
+    }
+
+    #[test]
+    fn skips_transactions_that_cannot_be_fetched() {
+        let payer = Pubkey::new_unique();
+        let memo = record().memo().unwrap();
+        let transaction = transaction(&[memo::build_memo(&memo, &[&payer])], &payer);
+        let encoded = base64::encode(bincode::serialize(&transaction).unwrap());
+        let memo = String::from_utf8(memo).unwrap();
+        let info = |byte: u8| {
+            json!({
+                "signature": Signature::new(&[byte; 64]).to_string(),
+                "slot": 11,
+                "err": null,
+                "memo": format!("[{}] {}", memo.len(), memo),
+                "blockTime": null
+            })
+        };
+        let result = |result: Value| {
+            (
+                200,
+                json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string(),
+            )
+        };
+        let fetched = |version: Value| {
+            result(json!({
+                "slot": 11,
+                "blockTime": null,
+                "transaction": [encoded, "base64"],
+                "meta": { "err": null },
+                "version": version
+            }))
+        };
+        let (url, requests) = serve(vec![
+            result(json!([info(3), info(4), info(5)])),
+            (
+                200,
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": { "code": -32011, "message": "Transaction history is not available" }
+                })
+                .to_string(),
+            ),
+            fetched(json!(0)),
+            fetched(json!("legacy")),
+        ]);
+        let found = block_on(fetch_by_address(&RpcClient::new(&url), &payer)).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].signature, Signature::new(&[5; 64]));
+
+        requests.recv().unwrap();
+        let request: Value = serde_json::from_str(&requests.recv().unwrap().body).unwrap();
+        assert_eq!(request["method"], "getTransaction");
+        assert_eq!(request["params"][1]["maxSupportedTransactionVersion"], 0);
+    }
+}
@@ -0,0 +1,658 @@
+//! Human-readable summaries of transactions, shown before the user is asked to approve them
+//! in their wallet.
+
+use crate::{
+    components::transfer::{format_sol, format_units},
+    error::Error,
+    programs::{associated_token, compute_budget, memo, metadata, token},
+    solana_rpc::RpcClient,
+};
+use serde_json::Value;
+use solana_sdk::{
+    instruction::{CompiledInstruction, InstructionError},
+    message::Message,
+    pubkey::Pubkey,
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+/// One decoded instruction. `fields` are label and value pairs in display order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructionPreview {
+    pub program: String,
+    pub action: String,
+    pub fields: Vec<(String, String)>,
+    /// Rent deposited into a new account funded through `create_account`.
+    pub rent_lamports: u64,
+    /// Size of an account the program creates itself, paid for by the fee payer.
+    pub created_account_len: Option<usize>,
+}
+
+impl InstructionPreview {
+    fn new(program: &str, action: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            action: action.to_string(),
+            fields: Vec::new(),
+            rent_lamports: 0,
+            created_account_len: None,
+        }
+    }
+
+    fn field(mut self, label: &str, value: impl ToString) -> Self {
+        self.fields.push((label.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds the instruction account at `position`, marked as a signer or writable.
+    fn account(
+        self,
+        message: &Message,
+        instruction: &CompiledInstruction,
+        position: usize,
+        label: &str,
+    ) -> Self {
+        match instruction.accounts.get(position) {
+            Some(index) => {
+                let index = *index as usize;
+                let mut value = message.account_keys[index].to_string();
+                if message.is_signer(index) {
+                    value.push_str(" (signer)");
+                } else if message.is_writable(index) {
+                    value.push_str(" (writable)");
+                }
+                self.field(label, value)
+            }
+            None => self.field(label, "missing"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionPreview {
+    pub fee_payer: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub instructions: Vec<InstructionPreview>,
+    /// Network fee, `None` until estimated or if the blockhash has expired.
+    pub fee_lamports: Option<u64>,
+    /// Rent for every account the transaction creates.
+    pub rent_lamports: u64,
+    pub simulation: Option<Simulation>,
+}
+
+/// Outcome of `simulateTransaction`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub units_consumed: Option<u64>,
+    pub logs: Vec<String>,
+    /// Decoded reason the transaction would fail.
+    pub error: Option<String>,
+}
+
+impl TransactionPreview {
+    /// Decodes every instruction of `message`. Fees and rent for accounts created by programs
+    /// other than the System program need the cluster, see [`TransactionPreview::estimate`].
+    pub fn new(message: &Message) -> Self {
+        let num_signers = message.header.num_required_signatures as usize;
+        let instructions: Vec<InstructionPreview> = message
+            .instructions
+            .iter()
+            .map(|instruction| decode_instruction(message, instruction))
+            .collect();
+        Self {
+            fee_payer: message.account_keys[0],
+            signers: message.account_keys[..num_signers].to_vec(),
+            rent_lamports: instructions.iter().map(|i| i.rent_lamports).sum(),
+            instructions,
+            fee_lamports: None,
+            simulation: None,
+        }
+    }
+
+    /// Decodes `message`, estimates its fee and the rent of every account it creates, and
+    /// simulates it against the cluster.
+    pub async fn estimate(rpc: &RpcClient, message: &Message) -> Result<Self, Error> {
+        let mut preview = Self::new(message);
+        preview.fee_lamports = rpc.get_fee_for_message(message).await?.value;
+        let simulated = rpc
+            .simulate_transaction(&Transaction::new_unsigned(message.clone()))
+            .await?
+            .value;
+        preview.simulation = Some(Simulation {
+            units_consumed: simulated.units_consumed,
+            logs: simulated.logs.unwrap_or_default(),
+            error: simulated
+                .err
+                .map(|err| describe_error(err, message, &preview.instructions)),
+        });
+        for instruction in &preview.instructions {
+            if let Some(len) = instruction.created_account_len {
+                preview.rent_lamports += rpc.get_minimum_balance_for_rent_exemption(len).await?;
+            }
+        }
+        Ok(preview)
+    }
+
+    /// Whether the transaction is expected to fail on-chain.
+    pub fn simulation_failed(&self) -> bool {
+        self.simulation
+            .as_ref()
+            .map_or(false, |simulation| simulation.error.is_some())
+    }
+
+    /// Fee and rent, the total the fee payer is charged on top of any transfers.
+    pub fn cost_lamports(&self) -> u64 {
+        self.fee_lamports.unwrap_or_default() + self.rent_lamports
+    }
+}
+
+/// Turns a `TransactionError` from the RPC into a message naming the failed instruction and,
+/// for programs the app knows, the custom error.
+fn describe_error(err: Value, message: &Message, instructions: &[InstructionPreview]) -> String {
+    let (index, error) = match serde_json::from_value::<TransactionError>(err.clone()) {
+        Ok(TransactionError::InstructionError(index, error)) => (index as usize, error),
+        Ok(error) => return error.to_string(),
+        Err(_) => return err.to_string(),
+    };
+    let label = instructions
+        .get(index)
+        .map(|instruction| format!("{}: {}", instruction.program, instruction.action))
+        .unwrap_or_default();
+    let program_id = message
+        .instructions
+        .get(index)
+        .map(|instruction| message.account_keys[instruction.program_id_index as usize]);
+    let reason = match (&error, program_id) {
+        (InstructionError::Custom(code), Some(program_id)) => program_error(&program_id, *code)
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string()),
+        _ => error.to_string(),
+    };
+    format!("instruction {} ({}) failed: {}", index + 1, label, reason)
+}
+
+fn program_error(program_id: &Pubkey, code: u32) -> Option<&'static str> {
+    if *program_id == system_program::id() {
+        match code {
+            0 => Some("account already in use"),
+            1 => Some("insufficient lamports"),
+            3 => Some("invalid account data length"),
+            _ => None,
+        }
+    } else if *program_id == token::id() {
+        token::error_message(code)
+    } else {
+        None
+    }
+}
+
+pub fn decode_instruction(
+    message: &Message,
+    instruction: &CompiledInstruction,
+) -> InstructionPreview {
+    let program_id = message.account_keys[instruction.program_id_index as usize];
+    let data = &instruction.data;
+    let preview = if program_id == system_program::id() {
+        decode_system(message, instruction)
+    } else if program_id == token::id() {
+        decode_token(message, instruction)
+    } else if program_id == associated_token::id() {
+        decode_associated_token(message, instruction)
+    } else if program_id == metadata::id() {
+        decode_metadata(message, instruction)
+    } else if program_id == compute_budget::id() {
+        decode_compute_budget(instruction)
+    } else if program_id == memo::id() || program_id == memo::v1::id() {
+        Some(InstructionPreview::new("Memo", "Memo").field("Memo", String::from_utf8_lossy(data)))
+    } else {
+        None
+    };
+    preview.unwrap_or_else(|| {
+        InstructionPreview::new(&program_id.to_string(), "Unknown instruction")
+            .field("Data", bs58::encode(data).into_string())
+    })
+}
+
+fn decode_compute_budget(instruction: &CompiledInstruction) -> Option<InstructionPreview> {
+    let (tag, rest) = instruction.data.split_first()?;
+    match *tag {
+        compute_budget::SET_COMPUTE_UNIT_LIMIT => {
+            let units = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?);
+            Some(
+                InstructionPreview::new("Compute Budget", "Set compute unit limit")
+                    .field("Units", units),
+            )
+        }
+        compute_budget::SET_COMPUTE_UNIT_PRICE => {
+            let price = u64::from_le_bytes(rest.get(..8)?.try_into().ok()?);
+            Some(
+                InstructionPreview::new("Compute Budget", "Set compute unit price")
+                    .field("Price", format!("{} micro-lamports per unit", price)),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn decode_system(
+    message: &Message,
+    instruction: &CompiledInstruction,
+) -> Option<InstructionPreview> {
+    let preview = InstructionPreview::new("System", "");
+    match bincode::deserialize::<SystemInstruction>(&instruction.data).ok()? {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            let preview = InstructionPreview {
+                action: "Create account".to_string(),
+                rent_lamports: lamports,
+                ..preview
+            }
+            .account(message, instruction, 0, "Funded by")
+            .account(message, instruction, 1, "New account")
+            .field("Deposit", format_sol(lamports))
+            .field("Size", format!("{} bytes", space))
+            .field("Owner", owner);
+            Some(preview)
+        }
+        SystemInstruction::Transfer { lamports } => Some(
+            InstructionPreview {
+                action: "Transfer SOL".to_string(),
+                ..preview
+            }
+            .account(message, instruction, 0, "From")
+            .account(message, instruction, 1, "To")
+            .field("Amount", format_sol(lamports)),
+        ),
+        other => Some(InstructionPreview {
+            action: format!("{:?}", other),
+            ..preview
+        }),
+    }
+}
+
+fn decode_token(
+    message: &Message,
+    instruction: &CompiledInstruction,
+) -> Option<InstructionPreview> {
+    let (tag, rest) = instruction.data.split_first()?;
+    let amount = || {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(rest.get(..8)?);
+        Some(u64::from_le_bytes(bytes))
+    };
+    let preview = |action: &str| InstructionPreview::new("SPL Token", action);
+    match *tag {
+        token::INITIALIZE_MINT => {
+            let decimals = *rest.first()?;
+            let mint_authority = Pubkey::new(rest.get(1..33)?);
+            let freeze_authority = match rest.get(33)? {
+                1 => Pubkey::new(rest.get(34..66)?).to_string(),
+                _ => "None".to_string(),
+            };
+            Some(
+                preview("Initialize mint")
+                    .account(message, instruction, 0, "Mint")
+                    .field("Decimals", decimals)
+                    .field("Mint authority", mint_authority)
+                    .field("Freeze authority", freeze_authority),
+            )
+        }
+        token::TRANSFER => Some(
+            preview("Transfer")
+                .account(message, instruction, 0, "From")
+                .account(message, instruction, 1, "To")
+                .account(message, instruction, 2, "Owner")
+                .field("Amount", format!("{} base units", amount()?)),
+        ),
+        token::APPROVE => Some(
+            preview("Approve delegate")
+                .account(message, instruction, 0, "Token account")
+                .account(message, instruction, 1, "Delegate")
+                .account(message, instruction, 2, "Owner")
+                .field("Amount", format!("{} base units", amount()?)),
+        ),
+        token::MINT_TO => Some(
+            preview("Mint")
+                .account(message, instruction, 0, "Mint")
+                .account(message, instruction, 1, "To")
+                .account(message, instruction, 2, "Mint authority")
+                .field("Amount", format!("{} base units", amount()?)),
+        ),
+        token::TRANSFER_CHECKED => Some(
+            preview("Transfer")
+                .account(message, instruction, 0, "From")
+                .account(message, instruction, 1, "Mint")
+                .account(message, instruction, 2, "To")
+                .account(message, instruction, 3, "Owner")
+                .field("Amount", format_units(amount()?, *rest.get(8)?)),
+        ),
+        _ => None,
+    }
+}
+
+fn decode_associated_token(
+    message: &Message,
+    instruction: &CompiledInstruction,
+) -> Option<InstructionPreview> {
+    let action = match instruction.data.first() {
+        None | Some(0) => "Create token account",
+        Some(1) => "Create token account if missing",
+        _ => return None,
+    };
+    let mut preview = InstructionPreview::new("Associated Token", action)
+        .account(message, instruction, 0, "Funded by")
+        .account(message, instruction, 1, "Token account")
+        .account(message, instruction, 2, "Wallet")
+        .account(message, instruction, 3, "Mint");
+    preview.created_account_len = Some(token::ACCOUNT_LEN);
+    Some(preview)
+}
+
+fn decode_metadata(
+    message: &Message,
+    instruction: &CompiledInstruction,
+) -> Option<InstructionPreview> {
+    let (tag, rest) = instruction.data.split_first()?;
+    let with_data = |preview: InstructionPreview, data: &metadata::Data| {
+        preview
+            .field("Name", &data.name)
+            .field("Symbol", &data.symbol)
+            .field("URI", &data.uri)
+            .field(
+                "Royalty",
+                format!("{}%", format_units(data.seller_fee_basis_points as u64, 2)),
+            )
+    };
+    match *tag {
+        metadata::CREATE_METADATA_ACCOUNT_V2 => {
+            let data = metadata::Data::unpack(rest).ok()?;
+            let mut preview = InstructionPreview::new("Token Metadata", "Create metadata")
+                .account(message, instruction, 0, "Metadata")
+                .account(message, instruction, 1, "Mint")
+                .account(message, instruction, 4, "Update authority");
+            preview.created_account_len = Some(metadata::MAX_METADATA_LEN);
+            Some(with_data(preview, &data))
+        }
+        metadata::UPDATE_METADATA_ACCOUNT_V2 => {
+            let preview = InstructionPreview::new("Token Metadata", "Update metadata")
+                .account(message, instruction, 0, "Metadata")
+                .account(message, instruction, 1, "Update authority");
+            match rest.split_first()? {
+                (1, data) => Some(with_data(preview, &metadata::Data::unpack(data).ok()?)),
+                _ => Some(preview),
+            }
+        }
+        metadata::CREATE_MASTER_EDITION_V3 => {
+            let max_supply = match rest.split_first()? {
+                (1, supply) => {
+                    let mut bytes = [0; 8];
+                    bytes.copy_from_slice(supply.get(..8)?);
+                    u64::from_le_bytes(bytes).to_string()
+                }
+                _ => "Unlimited".to_string(),
+            };
+            let mut preview = InstructionPreview::new("Token Metadata", "Create master edition")
+                .account(message, instruction, 0, "Edition")
+                .account(message, instruction, 1, "Mint")
+                .field("Max supply", max_supply);
+            preview.created_account_len = Some(metadata::MAX_MASTER_EDITION_LEN);
+            Some(preview)
+        }
+        metadata::VERIFY_COLLECTION => Some(
+            InstructionPreview::new("Token Metadata", "Verify collection")
+                .account(message, instruction, 0, "Metadata")
+                .account(message, instruction, 1, "Collection authority")
+                .account(message, instruction, 3, "Collection mint"),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_sdk::{hash::Hash, instruction::Instruction, system_instruction};
+
+    fn message() -> (Message, Vec<InstructionPreview>) {
+        let payer = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let message = Message::new_with_blockhash(
+            &[
+                system_instruction::create_account(&payer, &account, 1_000_000, 0, &token::id()),
+                token::transfer_checked(&account, &mint, &account, &payer, 1, 0),
+            ],
+            Some(&payer),
+            &Hash::default(),
+        );
+        let instructions = message
+            .instructions
+            .iter()
+            .map(|instruction| decode_instruction(&message, instruction))
+            .collect();
+        (message, instructions)
+    }
+
+    #[test]
+    fn names_custom_program_errors() {
+        let (message, instructions) = message();
+        let err = serde_json::to_value(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(3),
+        ))
+        .unwrap();
+        assert_eq!(err, json!({ "InstructionError": [0, { "Custom": 3 }] }));
+        assert_eq!(
+            describe_error(err, &message, &instructions),
+            "instruction 1 (System: Create account) failed: invalid account data length"
+        );
+        let err = json!({ "InstructionError": [1, { "Custom": 1 }] });
+        assert_eq!(
+            describe_error(err, &message, &instructions),
+            format!(
+                "instruction 2 ({}: {}) failed: insufficient funds",
+                instructions[1].program, instructions[1].action
+            )
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_runtime_error() {
+        let (message, instructions) = message();
+        let err = json!({ "InstructionError": [0, { "Custom": 99 }] });
+        assert_eq!(
+            describe_error(err, &message, &instructions),
+            format!(
+                "instruction 1 (System: Create account) failed: {}",
+                InstructionError::Custom(99)
+            )
+        );
+        let err = json!("AccountNotFound");
+        assert_eq!(
+            describe_error(err, &message, &instructions),
+            TransactionError::AccountNotFound.to_string()
+        );
+        let err = json!({ "Unknown": true });
+        assert_eq!(
+            describe_error(err.clone(), &message, &instructions),
+            err.to_string()
+        );
+    }
+
+    fn decode(instructions: &[Instruction], payer: &Pubkey) -> Vec<InstructionPreview> {
+        let message = Message::new_with_blockhash(instructions, Some(payer), &Hash::default());
+        TransactionPreview::new(&message).instructions
+    }
+
+    fn field<'a>(preview: &'a InstructionPreview, label: &str) -> &'a str {
+        preview
+            .fields
+            .iter()
+            .find(|(name, _)| name == label)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_else(|| panic!("no {} field in {:?}", label, preview))
+    }
+
+    #[test]
+    fn decodes_system_instructions() {
+        let payer = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let previews = decode(
+            &[
+                system_instruction::transfer(&payer, &to, 1_500_000_000),
+                system_instruction::create_account(&payer, &to, 2_000_000, 82, &token::id()),
+                system_instruction::assign(&to, &token::id()),
+            ],
+            &payer,
+        );
+        assert_eq!(previews[0].program, "System");
+        assert_eq!(previews[0].action, "Transfer SOL");
+        assert_eq!(field(&previews[0], "From"), format!("{} (signer)", payer));
+        assert_eq!(field(&previews[0], "To"), format!("{} (signer)", to));
+        assert_eq!(field(&previews[0], "Amount"), "1.5 SOL");
+        assert_eq!(previews[0].rent_lamports, 0);
+
+        assert_eq!(previews[1].action, "Create account");
+        assert_eq!(field(&previews[1], "Deposit"), "0.002 SOL");
+        assert_eq!(field(&previews[1], "Size"), "82 bytes");
+        assert_eq!(field(&previews[1], "Owner"), token::id().to_string());
+        assert_eq!(previews[1].rent_lamports, 2_000_000);
+
+        assert!(previews[2].action.starts_with("Assign"));
+    }
+
+    #[test]
+    fn decodes_token_instructions() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let previews = decode(
+            &[
+                token::initialize_mint(&mint, &payer, None, 6),
+                token::mint_to(&mint, &account, &payer, 2_500_000),
+                token::transfer_checked(&account, &mint, &account, &payer, 2_500_000, 6),
+                token::transfer_checked(&account, &mint, &account, &payer, 42, 20),
+            ],
+            &payer,
+        );
+        assert!(previews
+            .iter()
+            .all(|preview| preview.program == "SPL Token"));
+        assert_eq!(previews[0].action, "Initialize mint");
+        assert_eq!(field(&previews[0], "Mint"), format!("{} (writable)", mint));
+        assert_eq!(field(&previews[0], "Decimals"), "6");
+        assert_eq!(field(&previews[0], "Mint authority"), payer.to_string());
+        assert_eq!(field(&previews[0], "Freeze authority"), "None");
+
+        assert_eq!(previews[1].action, "Mint");
+        assert_eq!(field(&previews[1], "Amount"), "2500000 base units");
+
+        assert_eq!(previews[2].action, "Transfer");
+        assert_eq!(field(&previews[2], "Owner"), format!("{} (signer)", payer));
+        assert_eq!(field(&previews[2], "Amount"), "2.5");
+        // More decimals than a u64 can hold must not panic.
+        assert_eq!(field(&previews[3], "Amount"), "42 base units");
+    }
+
+    #[test]
+    fn decodes_associated_token_instructions() {
+        let payer = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let previews = decode(
+            &[
+                associated_token::create_associated_token_account(&payer, &wallet, &mint),
+                associated_token::create_associated_token_account_idempotent(
+                    &payer, &wallet, &mint,
+                ),
+            ],
+            &payer,
+        );
+        assert_eq!(previews[0].action, "Create token account");
+        assert_eq!(previews[1].action, "Create token account if missing");
+        for preview in &previews {
+            assert_eq!(preview.program, "Associated Token");
+            assert_eq!(
+                field(preview, "Token account"),
+                format!(
+                    "{} (writable)",
+                    associated_token::get_associated_token_address(&wallet, &mint)
+                )
+            );
+            assert_eq!(field(preview, "Wallet"), wallet.to_string());
+            assert_eq!(field(preview, "Mint"), mint.to_string());
+            assert_eq!(preview.created_account_len, Some(token::ACCOUNT_LEN));
+        }
+    }
+
+    #[test]
+    fn decodes_metadata_instructions() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let data = metadata::Data {
+            name: "Name".to_string(),
+            symbol: "SYM".to_string(),
+            uri: "https://example.com/token.json".to_string(),
+            seller_fee_basis_points: 250,
+            creators: Some(vec![metadata::Creator {
+                address: payer,
+                verified: true,
+                share: 100,
+            }]),
+            collection: None,
+        };
+        let previews = decode(
+            &[
+                metadata::create_metadata_account(&mint, &payer, &payer, &payer, &data, true),
+                metadata::update_metadata_account(&mint, &payer, Some(&data), None, None, None),
+                metadata::update_metadata_account(&mint, &payer, None, None, None, Some(false)),
+            ],
+            &payer,
+        );
+        assert_eq!(previews[0].action, "Create metadata");
+        assert_eq!(
+            previews[0].created_account_len,
+            Some(metadata::MAX_METADATA_LEN)
+        );
+        assert_eq!(previews[1].action, "Update metadata");
+        for preview in &previews[..2] {
+            assert_eq!(preview.program, "Token Metadata");
+            assert_eq!(
+                field(preview, "Metadata"),
+                format!("{} (writable)", metadata::find_metadata_account(&mint))
+            );
+            assert_eq!(field(preview, "Name"), "Name");
+            assert_eq!(field(preview, "Symbol"), "SYM");
+            assert_eq!(field(preview, "URI"), data.uri);
+            assert_eq!(field(preview, "Royalty"), "2.5%");
+        }
+        assert_eq!(previews[2].action, "Update metadata");
+        assert!(previews[2].fields.iter().all(|(label, _)| label != "Name"));
+    }
+
+    #[test]
+    fn decodes_memos_and_unknown_programs() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let previews = decode(
+            &[
+                memo::build_memo(b"hello", &[&payer]),
+                Instruction::new_with_bytes(program_id, &[1, 2, 3], vec![]),
+            ],
+            &payer,
+        );
+        assert_eq!(previews[0].program, "Memo");
+        assert_eq!(field(&previews[0], "Memo"), "hello");
+        assert_eq!(previews[1].program, program_id.to_string());
+        assert_eq!(previews[1].action, "Unknown instruction");
+        assert_eq!(
+            field(&previews[1], "Data"),
+            bs58::encode([1, 2, 3]).into_string()
+        );
+    }
+}
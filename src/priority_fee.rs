@@ -0,0 +1,374 @@
+//! Compute budget and priority fees, prepended to transactions so that they keep landing when
+//! the cluster is congested.
+
+use crate::{
+    error::Error,
+    nonce,
+    programs::compute_budget::{self, MAX_COMPUTE_UNIT_LIMIT},
+    solana_rpc::RpcClient,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    transaction::Transaction,
+};
+use std::{fmt, str::FromStr};
+
+/// Compute units added to the simulated usage, as a percentage.
+const UNIT_MARGIN_PERCENT: u64 = 10;
+/// Compute units used by the two compute budget instructions themselves.
+const COMPUTE_BUDGET_UNITS: u64 = 300;
+
+/// How much to pay for priority, as a percentile of the fees paid in recent slots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriorityFee {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl Default for PriorityFee {
+    fn default() -> PriorityFee {
+        PriorityFee::Off
+    }
+}
+
+impl fmt::Display for PriorityFee {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PriorityFee::Off => write!(f, "off"),
+            PriorityFee::Low => write!(f, "low"),
+            PriorityFee::Medium => write!(f, "medium"),
+            PriorityFee::High => write!(f, "high"),
+        }
+    }
+}
+
+impl FromStr for PriorityFee {
+    type Err = Error;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "off" => Ok(PriorityFee::Off),
+            "low" => Ok(PriorityFee::Low),
+            "medium" => Ok(PriorityFee::Medium),
+            "high" => Ok(PriorityFee::High),
+            _ => Err(Error::InvalidPriorityFee(str.to_string())),
+        }
+    }
+}
+
+impl PriorityFee {
+    fn percentile(&self) -> Option<usize> {
+        match self {
+            PriorityFee::Off => None,
+            PriorityFee::Low => Some(25),
+            PriorityFee::Medium => Some(50),
+            PriorityFee::High => Some(75),
+        }
+    }
+
+    /// Price in micro-lamports per compute unit paid by transactions writing to the same
+    /// accounts as `message` in recent slots, at this level's percentile.
+    pub async fn unit_price(&self, rpc: &RpcClient, message: &Message) -> Result<u64, Error> {
+        let percentile = match self.percentile() {
+            Some(percentile) => percentile,
+            None => return Ok(0),
+        };
+        let writable: Vec<_> = (0..message.account_keys.len())
+            .filter(|&i| is_writable(message, i))
+            .map(|i| message.account_keys[i])
+            .collect();
+        let mut fees: Vec<u64> = rpc
+            .get_recent_prioritization_fees(&writable)
+            .await?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+        if fees.is_empty() {
+            return Ok(0);
+        }
+        fees.sort_unstable();
+        let rank = (fees.len() * percentile + 99) / 100;
+        Ok(fees[rank.saturating_sub(1)])
+    }
+
+    /// Rebuilds `transaction` with compute budget instructions prepended: a unit limit of the
+    /// simulated usage plus a margin, and this level's unit price. The transaction must not be
+    /// signed yet. Nothing is changed when priority fees are off.
+    pub async fn apply(
+        &self,
+        rpc: &RpcClient,
+        transaction: Transaction,
+    ) -> Result<Transaction, Error> {
+        let message = &transaction.message;
+        let already_budgeted = message
+            .instructions
+            .iter()
+            .any(|i| message.account_keys[i.program_id_index as usize] == compute_budget::id());
+        if *self == PriorityFee::Off || already_budgeted {
+            return Ok(transaction);
+        }
+
+        let mut budget = Vec::with_capacity(2);
+        if let Some(units) = rpc
+            .simulate_transaction(&transaction)
+            .await?
+            .value
+            .units_consumed
+        {
+            let limit = units * (100 + UNIT_MARGIN_PERCENT) / 100 + COMPUTE_BUDGET_UNITS;
+            budget.push(compute_budget::set_compute_unit_limit(
+                limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32,
+            ));
+        }
+        let price = self.unit_price(rpc, message).await?;
+        if price > 0 {
+            budget.push(compute_budget::set_compute_unit_price(price));
+        }
+        if budget.is_empty() {
+            return Ok(transaction);
+        }
+
+        // Advancing a durable nonce has to stay the first instruction.
+        let mut instructions = decompile(message);
+        let at = if nonce::advanced_nonce_account(message).is_some() {
+            1
+        } else {
+            0
+        };
+        instructions.splice(at..at, budget);
+        Ok(Transaction::new_unsigned(Message::new_with_blockhash(
+            &instructions,
+            Some(&message.account_keys[0]),
+            &message.recent_blockhash,
+        )))
+    }
+}
+
+/// Whether the message header marks account `i` as writable.
+fn is_writable(message: &Message, i: usize) -> bool {
+    let header = &message.header;
+    let signers = header.num_required_signatures as usize;
+    if i < signers {
+        i < signers - header.num_readonly_signed_accounts as usize
+    } else {
+        i < message.account_keys.len() - header.num_readonly_unsigned_accounts as usize
+    }
+}
+
+/// Expands each compiled instruction of `message` back into an [`Instruction`].
+fn decompile(message: &Message) -> Vec<Instruction> {
+    message
+        .instructions
+        .iter()
+        .map(|instruction| Instruction {
+            program_id: message.account_keys[instruction.program_id_index as usize],
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = index as usize;
+                    AccountMeta {
+                        pubkey: message.account_keys[index],
+                        is_signer: message.is_signer(index),
+                        is_writable: is_writable(message, index),
+                    }
+                })
+                .collect(),
+            data: instruction.data.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        nonce::{self, NonceAccount},
+        programs::{memo, token},
+        stub_server::{block_on, serve, Request},
+    };
+    use serde_json::{json, Value};
+    use solana_sdk::{hash::Hash, pubkey::Pubkey, system_instruction};
+    use std::sync::mpsc::Receiver;
+
+    fn result(result: Value) -> (u16, String) {
+        (
+            200,
+            json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string(),
+        )
+    }
+
+    fn simulated(units: u64) -> (u16, String) {
+        result(json!({
+            "context": { "slot": 100 },
+            "value": { "err": null, "logs": [], "unitsConsumed": units }
+        }))
+    }
+
+    fn fees(fees: &[u64]) -> (u16, String) {
+        let fees: Vec<Value> = fees
+            .iter()
+            .map(|fee| json!({ "slot": 100, "prioritizationFee": fee }))
+            .collect();
+        result(json!(fees))
+    }
+
+    fn unit_price(level: PriorityFee, fees: &[u64]) -> (u64, Receiver<Request>) {
+        let payer = Pubkey::new_unique();
+        let message = Message::new(
+            &[system_instruction::transfer(
+                &payer,
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&payer),
+        );
+        let (url, requests) = serve(vec![self::fees(fees)]);
+        let price = block_on(level.unit_price(&RpcClient::new(&url), &message)).unwrap();
+        (price, requests)
+    }
+
+    fn apply(
+        level: PriorityFee,
+        transaction: Transaction,
+        responses: Vec<(u16, String)>,
+    ) -> Vec<Instruction> {
+        let (url, _requests) = serve(responses);
+        let applied = block_on(level.apply(&RpcClient::new(&url), transaction)).unwrap();
+        decompile(&applied.message)
+    }
+
+    #[test]
+    fn decompiles_instructions_that_compile_to_the_same_message() {
+        let payer = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let instructions = vec![
+            system_instruction::transfer(&payer, &account, 1),
+            token::transfer_checked(&account, &mint, &account, &signer, 10, 2),
+            memo::build_memo(b"memo", &[&signer]),
+            compute_budget::set_compute_unit_limit(1_000),
+        ];
+        let message = Message::new_with_blockhash(&instructions, Some(&payer), &Hash::new_unique());
+        let decompiled = decompile(&message);
+        assert_eq!(decompiled, instructions);
+        assert_eq!(
+            Message::new_with_blockhash(&decompiled, Some(&payer), &message.recent_blockhash),
+            message
+        );
+    }
+
+    #[test]
+    fn reads_writable_accounts_from_the_header() {
+        let payer = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let message = Message::new(
+            &[
+                system_instruction::transfer(&payer, &to, 1),
+                memo::build_memo(b"memo", &[&signer]),
+            ],
+            Some(&payer),
+        );
+        let writable: Vec<Pubkey> = (0..message.account_keys.len())
+            .filter(|&i| is_writable(&message, i))
+            .map(|i| message.account_keys[i])
+            .collect();
+        assert_eq!(writable, vec![payer, to]);
+    }
+
+    #[test]
+    fn picks_the_fee_at_the_percentile() {
+        let recent = [40, 10, 30, 20];
+        assert_eq!(unit_price(PriorityFee::Low, &recent).0, 10);
+        assert_eq!(unit_price(PriorityFee::Medium, &recent).0, 20);
+        assert_eq!(unit_price(PriorityFee::High, &recent).0, 30);
+        let recent = [5, 1, 4, 2, 3];
+        assert_eq!(unit_price(PriorityFee::Low, &recent).0, 2);
+        assert_eq!(unit_price(PriorityFee::Medium, &recent).0, 3);
+        assert_eq!(unit_price(PriorityFee::High, &recent).0, 4);
+        for level in [PriorityFee::Low, PriorityFee::High] {
+            assert_eq!(unit_price(level, &[7]).0, 7);
+            assert_eq!(unit_price(level, &[]).0, 0);
+        }
+
+        let (_, requests) = unit_price(PriorityFee::Medium, &[1]);
+        let request: Value = serde_json::from_str(&requests.recv().unwrap().body).unwrap();
+        assert_eq!(request["method"], "getRecentPrioritizationFees");
+        assert_eq!(request["params"][0].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn prepends_the_compute_budget() {
+        let payer = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        let transaction =
+            Transaction::new_unsigned(Message::new(&[transfer.clone()], Some(&payer)));
+        let instructions = apply(
+            PriorityFee::Medium,
+            transaction.clone(),
+            vec![simulated(10_000), fees(&[5])],
+        );
+        assert_eq!(
+            instructions,
+            vec![
+                compute_budget::set_compute_unit_limit(11_300),
+                compute_budget::set_compute_unit_price(5),
+                transfer,
+            ]
+        );
+
+        // Without recent fees only the unit limit is set.
+        let instructions = apply(
+            PriorityFee::High,
+            transaction.clone(),
+            vec![simulated(2_000_000), fees(&[])],
+        );
+        assert_eq!(
+            instructions[0],
+            compute_budget::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT)
+        );
+        assert_eq!(instructions.len(), 2);
+
+        // Nothing is requested when priority fees are off.
+        let off = block_on(
+            PriorityFee::Off.apply(&RpcClient::new("http://127.0.0.1:9"), transaction.clone()),
+        );
+        assert_eq!(off.unwrap(), transaction);
+    }
+
+    #[test]
+    fn keeps_advancing_the_nonce_first() {
+        let payer = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let nonce = NonceAccount {
+            authority,
+            blockhash: Hash::new_unique(),
+            lamports_per_signature: 5_000,
+        };
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        let transaction =
+            nonce::durable_transaction(&[transfer.clone()], &payer, &nonce_account, &nonce);
+        let (url, _requests) = serve(vec![simulated(10_000), fees(&[5])]);
+        let applied = block_on(PriorityFee::Low.apply(&RpcClient::new(&url), transaction)).unwrap();
+        assert_eq!(applied.message.recent_blockhash, nonce.blockhash);
+        assert_eq!(applied.message.account_keys[0], payer);
+        assert_eq!(
+            nonce::advanced_nonce_account(&applied.message),
+            Some(nonce_account)
+        );
+        assert_eq!(
+            decompile(&applied.message),
+            vec![
+                system_instruction::advance_nonce_account(&nonce_account, &authority),
+                compute_budget::set_compute_unit_limit(11_300),
+                compute_budget::set_compute_unit_price(5),
+                transfer,
+            ]
+        );
+    }
+}
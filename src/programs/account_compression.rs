@@ -0,0 +1,4 @@
+//! [SPL Account Compression](https://spl.solana.com/account-compression), which owns the
+//! concurrent merkle trees compressed NFTs are stored in.
+
+solana_sdk::declare_id!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
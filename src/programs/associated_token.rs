@@ -0,0 +1,54 @@
+//! [Associated Token Account](https://spl.solana.com/associated-token-account) instructions.
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+solana_sdk::declare_id!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+pub const CREATE_IDEMPOTENT: u8 = 1;
+
+pub fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), super::token::id().as_ref(), mint.as_ref()],
+        &id(),
+    )
+    .0
+}
+
+/// Creates the associated token account of `wallet` for `mint`, funded by `payer`.
+pub fn create_associated_token_account(
+    payer: &Pubkey,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    create(payer, wallet, mint, vec![])
+}
+
+/// Like [`create_associated_token_account`], but succeeds without changes if the account
+/// already exists.
+pub fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    create(payer, wallet, mint, vec![CREATE_IDEMPOTENT])
+}
+
+fn create(payer: &Pubkey, wallet: &Pubkey, mint: &Pubkey, data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(get_associated_token_address(wallet, mint), false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(super::token::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data,
+    }
+}
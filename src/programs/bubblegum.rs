@@ -0,0 +1,168 @@
+//! [Metaplex Bubblegum](https://developers.metaplex.com/bubblegum) compressed NFTs. Their
+//! metadata is not stored in accounts: each NFT is a leaf of a concurrent merkle tree that
+//! commits to its owner, delegate and the hashes of its metadata and creators.
+
+use crate::programs::metadata::{Collection, Creator};
+use solana_sdk::{keccak, pubkey::Pubkey};
+
+solana_sdk::declare_id!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+
+/// Version byte of `LeafSchema::V1`.
+const LEAF_SCHEMA_V1: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uses {
+    /// `UseMethod` discriminant: `0` burn, `1` multiple, `2` single.
+    pub use_method: u8,
+    pub remaining: u64,
+    pub total: u64,
+}
+
+/// Metadata a compressed NFT is minted with, `MetadataArgs` in the program.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataArgs {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    /// `TokenStandard` discriminant, `0` for non-fungible.
+    pub token_standard: Option<u8>,
+    pub collection: Option<Collection>,
+    pub uses: Option<Uses>,
+    /// `TokenProgramVersion` discriminant, `0` for the original token program.
+    pub token_program_version: u8,
+    pub creators: Vec<Creator>,
+}
+
+impl MetadataArgs {
+    /// Packs the metadata with Borsh, as hashed by the program.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for str in [&self.name, &self.symbol, &self.uri] {
+            data.extend_from_slice(&(str.len() as u32).to_le_bytes());
+            data.extend_from_slice(str.as_bytes());
+        }
+        data.extend_from_slice(&self.seller_fee_basis_points.to_le_bytes());
+        data.push(self.primary_sale_happened as u8);
+        data.push(self.is_mutable as u8);
+        for byte in [self.edition_nonce, self.token_standard] {
+            match byte {
+                Some(byte) => data.extend_from_slice(&[1, byte]),
+                None => data.push(0),
+            }
+        }
+        match &self.collection {
+            Some(collection) => {
+                data.push(1);
+                data.push(collection.verified as u8);
+                data.extend_from_slice(collection.key.as_ref());
+            }
+            None => data.push(0),
+        }
+        match &self.uses {
+            Some(uses) => {
+                data.push(1);
+                data.push(uses.use_method);
+                data.extend_from_slice(&uses.remaining.to_le_bytes());
+                data.extend_from_slice(&uses.total.to_le_bytes());
+            }
+            None => data.push(0),
+        }
+        data.push(self.token_program_version);
+        data.extend_from_slice(&(self.creators.len() as u32).to_le_bytes());
+        for creator in &self.creators {
+            data.extend_from_slice(creator.address.as_ref());
+            data.push(creator.verified as u8);
+            data.push(creator.share);
+        }
+        data
+    }
+
+    /// `keccak(keccak(metadata) || seller_fee_basis_points)`. Royalties are hashed separately
+    /// so marketplaces can check them without the rest of the metadata.
+    pub fn data_hash(&self) -> [u8; 32] {
+        let metadata_hash = keccak::hashv(&[&self.pack()]).to_bytes();
+        keccak::hashv(&[&metadata_hash, &self.seller_fee_basis_points.to_le_bytes()]).to_bytes()
+    }
+
+    /// Keccak of every creator's address, verified flag and share.
+    pub fn creator_hash(&self) -> [u8; 32] {
+        creator_hash(&self.creators)
+    }
+}
+
+pub fn creator_hash(creators: &[Creator]) -> [u8; 32] {
+    let creators: Vec<Vec<u8>> = creators
+        .iter()
+        .map(|creator| {
+            [
+                creator.address.as_ref(),
+                &[creator.verified as u8],
+                &[creator.share],
+            ]
+            .concat()
+        })
+        .collect();
+    keccak::hashv(
+        &creators
+            .iter()
+            .map(|creator| creator.as_slice())
+            .collect::<Vec<&[u8]>>(),
+    )
+    .to_bytes()
+}
+
+/// A compressed NFT as committed to by its leaf, `LeafSchema::V1` in the program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeafSchema {
+    /// Asset id, see [`find_asset_id`].
+    pub id: Pubkey,
+    pub owner: Pubkey,
+    /// The owner when there is no delegate.
+    pub delegate: Pubkey,
+    /// Number of NFTs minted to the tree before this one, also its leaf index.
+    pub nonce: u64,
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+}
+
+impl LeafSchema {
+    pub fn new(
+        tree: &Pubkey,
+        owner: &Pubkey,
+        delegate: &Pubkey,
+        nonce: u64,
+        metadata: &MetadataArgs,
+    ) -> Self {
+        Self {
+            id: find_asset_id(tree, nonce),
+            owner: *owner,
+            delegate: *delegate,
+            nonce,
+            data_hash: metadata.data_hash(),
+            creator_hash: metadata.creator_hash(),
+        }
+    }
+
+    /// The leaf stored in the tree.
+    pub fn hash(&self) -> [u8; 32] {
+        keccak::hashv(&[
+            &[LEAF_SCHEMA_V1],
+            self.id.as_ref(),
+            self.owner.as_ref(),
+            self.delegate.as_ref(),
+            &self.nonce.to_le_bytes(),
+            &self.data_hash,
+            &self.creator_hash,
+        ])
+        .to_bytes()
+    }
+}
+
+/// Asset id of the NFT minted with `nonce` to `tree`.
+pub fn find_asset_id(tree: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"asset", tree.as_ref(), &nonce.to_le_bytes()], &id()).0
+}
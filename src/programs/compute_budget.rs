@@ -0,0 +1,33 @@
+//! Compute Budget instructions, which set a transaction's compute unit limit and the price it
+//! pays per unit to be prioritized by the leader.
+
+use solana_sdk::instruction::Instruction;
+
+solana_sdk::declare_id!("ComputeBudget111111111111111111111111111111");
+
+pub const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+pub const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Most compute units a transaction may request.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data,
+    }
+}
+
+/// Sets the priority fee, in micro-lamports per requested compute unit.
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data,
+    }
+}
@@ -0,0 +1,26 @@
+//! [SPL Memo](https://spl.solana.com/memo) instructions.
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+solana_sdk::declare_id!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// The first version of the program, which does not check signers.
+pub mod v1 {
+    solana_sdk::declare_id!("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo");
+}
+
+/// Records `memo`, which must be valid utf8, in the transaction log. Each of `signers` must
+/// sign the transaction.
+pub fn build_memo(memo: &[u8], signers: &[&Pubkey]) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: signers
+            .iter()
+            .map(|signer| AccountMeta::new_readonly(**signer, true))
+            .collect(),
+        data: memo.to_vec(),
+    }
+}
@@ -0,0 +1,469 @@
+//! [Metaplex Token Metadata](https://docs.metaplex.com/programs/token-metadata/) instructions
+//! and the off-chain JSON metadata schema their `uri` points to.
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+solana_sdk::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+pub const MAX_NAME_LENGTH: usize = 32;
+pub const MAX_SYMBOL_LENGTH: usize = 10;
+pub const MAX_URI_LENGTH: usize = 200;
+pub const MAX_CREATOR_LIMIT: usize = 5;
+/// Size of a metadata account.
+pub const MAX_METADATA_LEN: usize = 679;
+/// Size of a master edition account.
+pub const MAX_MASTER_EDITION_LEN: usize = 282;
+
+pub const UPDATE_METADATA_ACCOUNT_V2: u8 = 15;
+pub const CREATE_METADATA_ACCOUNT_V2: u8 = 16;
+pub const CREATE_MASTER_EDITION_V3: u8 = 17;
+pub const VERIFY_COLLECTION: u8 = 18;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    /// Percentage of royalties, shares of all creators must add up to 100.
+    pub share: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collection {
+    pub verified: bool,
+    pub key: Pubkey,
+}
+
+/// On-chain metadata, `DataV2` in the program. `uses` is always packed as `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Data {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+    pub collection: Option<Collection>,
+}
+
+impl Data {
+    /// Checks the limits the program enforces before the transaction is built.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.name.len() > MAX_NAME_LENGTH {
+            return Err(Error::InvalidMetadata(format!(
+                "name is longer than {} bytes",
+                MAX_NAME_LENGTH
+            )));
+        }
+        if self.symbol.len() > MAX_SYMBOL_LENGTH {
+            return Err(Error::InvalidMetadata(format!(
+                "symbol is longer than {} bytes",
+                MAX_SYMBOL_LENGTH
+            )));
+        }
+        if self.uri.len() > MAX_URI_LENGTH {
+            return Err(Error::InvalidMetadata(format!(
+                "uri is longer than {} bytes",
+                MAX_URI_LENGTH
+            )));
+        }
+        if self.seller_fee_basis_points > 10_000 {
+            return Err(Error::InvalidMetadata(
+                "seller fee is more than 10000 basis points".to_string(),
+            ));
+        }
+        if let Some(creators) = &self.creators {
+            if creators.len() > MAX_CREATOR_LIMIT {
+                return Err(Error::InvalidMetadata(format!(
+                    "more than {} creators",
+                    MAX_CREATOR_LIMIT
+                )));
+            }
+            if creators.iter().map(|c| c.share as u32).sum::<u32>() != 100 {
+                return Err(Error::InvalidMetadata(
+                    "creator shares must add up to 100".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads data packed by [`Data::pack`], as found in instruction data.
+    pub fn unpack(data: &[u8]) -> Result<Data, Error> {
+        let mut reader = Reader(data);
+        Ok(Data {
+            name: reader.string()?,
+            symbol: reader.string()?,
+            uri: reader.string()?,
+            seller_fee_basis_points: reader.u16()?,
+            creators: reader.creators()?,
+            collection: reader.collection()?,
+        })
+    }
+
+    /// Packs the data with Borsh: strings are length prefixed with a `u32` and options
+    /// with a single tag byte.
+    fn pack(&self, data: &mut Vec<u8>) {
+        for str in [&self.name, &self.symbol, &self.uri] {
+            data.extend_from_slice(&(str.len() as u32).to_le_bytes());
+            data.extend_from_slice(str.as_bytes());
+        }
+        data.extend_from_slice(&self.seller_fee_basis_points.to_le_bytes());
+        match &self.creators {
+            Some(creators) => {
+                data.push(1);
+                data.extend_from_slice(&(creators.len() as u32).to_le_bytes());
+                for creator in creators {
+                    data.extend_from_slice(creator.address.as_ref());
+                    data.push(creator.verified as u8);
+                    data.push(creator.share);
+                }
+            }
+            None => data.push(0),
+        }
+        match &self.collection {
+            Some(collection) => {
+                data.push(1);
+                data.push(collection.verified as u8);
+                data.extend_from_slice(collection.key.as_ref());
+            }
+            None => data.push(0),
+        }
+        data.push(0);
+    }
+}
+
+/// Deserialized metadata account. Fields after `collection` are not read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub update_authority: Pubkey,
+    pub mint: Pubkey,
+    pub data: Data,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+}
+
+/// Reads Borsh encoded fields from the front of account data.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::InvalidMetadata("data too short".to_string()));
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, Error> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, Error> {
+        Ok(Pubkey::new(self.take(32)?))
+    }
+
+    /// Strings are stored padded with null bytes to their maximum length.
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?)
+            .trim_end_matches('\0')
+            .to_string())
+    }
+
+    fn creators(&mut self) -> Result<Option<Vec<Creator>>, Error> {
+        if !self.bool()? {
+            return Ok(None);
+        }
+        let len = self.u32()?;
+        let mut creators = Vec::new();
+        for _ in 0..len {
+            creators.push(Creator {
+                address: self.pubkey()?,
+                verified: self.bool()?,
+                share: self.u8()?,
+            });
+        }
+        Ok(Some(creators))
+    }
+
+    fn collection(&mut self) -> Result<Option<Collection>, Error> {
+        if !self.bool()? {
+            return Ok(None);
+        }
+        Ok(Some(Collection {
+            verified: self.bool()?,
+            key: self.pubkey()?,
+        }))
+    }
+}
+
+impl Metadata {
+    pub fn unpack(data: &[u8]) -> Result<Metadata, Error> {
+        let mut reader = Reader(data);
+        reader.u8()?;
+        let update_authority = reader.pubkey()?;
+        let mint = reader.pubkey()?;
+        let name = reader.string()?;
+        let symbol = reader.string()?;
+        let uri = reader.string()?;
+        let seller_fee_basis_points = reader.u16()?;
+        let creators = reader.creators()?;
+        let primary_sale_happened = reader.bool()?;
+        let is_mutable = reader.bool()?;
+        // Older accounts end here; `edition_nonce` and `token_standard` precede `collection`.
+        let mut collection = None;
+        if reader.bool().unwrap_or(false) {
+            reader.u8()?;
+        }
+        if reader.bool().unwrap_or(false) {
+            reader.u8()?;
+        }
+        if !reader.0.is_empty() {
+            collection = reader.collection()?;
+        }
+        Ok(Metadata {
+            update_authority,
+            mint,
+            data: Data {
+                name,
+                symbol,
+                uri,
+                seller_fee_basis_points,
+                creators,
+                collection,
+            },
+            primary_sale_happened,
+            is_mutable,
+        })
+    }
+}
+
+/// Address of the metadata account of `mint`.
+pub fn find_metadata_account(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"metadata", id().as_ref(), mint.as_ref()], &id()).0
+}
+
+/// Address of the master edition account of `mint`.
+pub fn find_master_edition_account(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", id().as_ref(), mint.as_ref(), b"edition"],
+        &id(),
+    )
+    .0
+}
+
+pub fn create_metadata_account(
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    payer: &Pubkey,
+    update_authority: &Pubkey,
+    metadata: &Data,
+    is_mutable: bool,
+) -> Instruction {
+    let mut data = vec![CREATE_METADATA_ACCOUNT_V2];
+    metadata.pack(&mut data);
+    data.push(is_mutable as u8);
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(find_metadata_account(mint), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*mint_authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*update_authority, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Replaces the metadata of `mint`, signed by its current `update_authority`. `None` fields
+/// are left unchanged.
+pub fn update_metadata_account(
+    mint: &Pubkey,
+    update_authority: &Pubkey,
+    metadata: Option<&Data>,
+    new_update_authority: Option<&Pubkey>,
+    primary_sale_happened: Option<bool>,
+    is_mutable: Option<bool>,
+) -> Instruction {
+    let mut data = vec![UPDATE_METADATA_ACCOUNT_V2];
+    match metadata {
+        Some(metadata) => {
+            data.push(1);
+            metadata.pack(&mut data);
+        }
+        None => data.push(0),
+    }
+    match new_update_authority {
+        Some(new_update_authority) => {
+            data.push(1);
+            data.extend_from_slice(new_update_authority.as_ref());
+        }
+        None => data.push(0),
+    }
+    for flag in [primary_sale_happened, is_mutable] {
+        match flag {
+            Some(flag) => data.extend_from_slice(&[1, flag as u8]),
+            None => data.push(0),
+        }
+    }
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(find_metadata_account(mint), false),
+            AccountMeta::new_readonly(*update_authority, true),
+        ],
+        data,
+    }
+}
+
+/// Turns a supply one mint into a master edition, taking over its mint and freeze
+/// authorities. `max_supply` of `Some(0)` prevents printing editions.
+pub fn create_master_edition(
+    mint: &Pubkey,
+    update_authority: &Pubkey,
+    mint_authority: &Pubkey,
+    payer: &Pubkey,
+    max_supply: Option<u64>,
+) -> Instruction {
+    let mut data = vec![CREATE_MASTER_EDITION_V3];
+    match max_supply {
+        Some(max_supply) => {
+            data.push(1);
+            data.extend_from_slice(&max_supply.to_le_bytes());
+        }
+        None => data.push(0),
+    }
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(find_master_edition_account(mint), false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(*update_authority, true),
+            AccountMeta::new_readonly(*mint_authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(find_metadata_account(mint), false),
+            AccountMeta::new_readonly(super::token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Marks the collection of `mint`'s metadata as verified, signed by the update authority of
+/// `collection_mint`, which must be a master edition.
+pub fn verify_collection(
+    mint: &Pubkey,
+    collection_authority: &Pubkey,
+    payer: &Pubkey,
+    collection_mint: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(find_metadata_account(mint), false),
+            AccountMeta::new(*collection_authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*collection_mint, false),
+            AccountMeta::new_readonly(find_metadata_account(collection_mint), false),
+            AccountMeta::new_readonly(find_master_edition_account(collection_mint), false),
+        ],
+        data: vec![VERIFY_COLLECTION],
+    }
+}
+
+/// Instructions for a one-of-one NFT owned by `payer`: a zero decimal mint with a supply of
+/// one, its metadata account and a master edition that locks the supply.
+pub fn create_nft(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    metadata: &Data,
+    rent_lamports: u64,
+) -> Vec<Instruction> {
+    let mut instructions =
+        super::token::create_mint_with_supply(payer, mint, 0, 1, true, rent_lamports);
+    instructions.push(create_metadata_account(
+        mint, payer, payer, payer, metadata, true,
+    ));
+    instructions.push(create_master_edition(mint, payer, payer, payer, Some(0)));
+    instructions
+}
+
+/// Off-chain metadata per the
+/// [Metaplex token standard](https://docs.metaplex.com/programs/token-metadata/token-standard),
+/// uploaded to Arweave and referenced by [`Data::uri`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JsonMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub description: String,
+    pub seller_fee_basis_points: u16,
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<JsonAttribute>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<JsonCollection>,
+    #[serde(default)]
+    pub properties: JsonProperties,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JsonCollection {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JsonAttribute {
+    pub trait_type: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JsonProperties {
+    #[serde(default)]
+    pub files: Vec<JsonFile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub creators: Vec<JsonCreator>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JsonFile {
+    pub uri: String,
+    #[serde(rename = "type")]
+    pub content_type: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JsonCreator {
+    pub address: String,
+    pub share: u8,
+}
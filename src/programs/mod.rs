@@ -0,0 +1,10 @@
+//! Instruction builders for on-chain programs the app interacts with. Instruction data is
+//! packed by hand to match each program's layout, since the program crates pin their own
+//! versions of the Solana SDK.
+pub mod account_compression;
+pub mod associated_token;
+pub mod bubblegum;
+pub mod compute_budget;
+pub mod memo;
+pub mod metadata;
+pub mod token;
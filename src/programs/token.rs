@@ -0,0 +1,141 @@
+//! [SPL Token](https://spl.solana.com/token) instructions.
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_instruction, sysvar,
+};
+
+solana_sdk::declare_id!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// Size of a packed `Mint` account.
+pub const MINT_LEN: usize = 82;
+/// Size of a packed token `Account`.
+pub const ACCOUNT_LEN: usize = 165;
+
+pub const INITIALIZE_MINT: u8 = 0;
+pub const TRANSFER: u8 = 3;
+pub const APPROVE: u8 = 4;
+pub const MINT_TO: u8 = 7;
+pub const TRANSFER_CHECKED: u8 = 12;
+
+/// Decimals of a packed `Mint` account, which follow its `COption` mint authority and supply.
+pub fn mint_decimals(data: &[u8]) -> Option<u8> {
+    if data.len() != MINT_LEN {
+        return None;
+    }
+    data.get(44).copied()
+}
+
+/// Describes the program's custom `TokenError` codes.
+pub fn error_message(code: u32) -> Option<&'static str> {
+    match code {
+        0 => Some("account is not rent exempt"),
+        1 => Some("insufficient funds"),
+        2 => Some("invalid mint"),
+        3 => Some("account not associated with this mint"),
+        4 => Some("owner does not match"),
+        5 => Some("fixed supply, the mint has no mint authority"),
+        6 => Some("account already in use"),
+        12 => Some("invalid instruction"),
+        14 => Some("operation overflowed"),
+        17 => Some("account is frozen"),
+        18 => Some("mint decimals mismatch"),
+        _ => None,
+    }
+}
+
+/// Packs an optional authority as a `COption<Pubkey>`.
+fn pack_pubkey_option(data: &mut Vec<u8>, pubkey: Option<&Pubkey>) {
+    match pubkey {
+        Some(pubkey) => {
+            data.push(1);
+            data.extend_from_slice(pubkey.as_ref());
+        }
+        None => data.push(0),
+    }
+}
+
+pub fn initialize_mint(
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![INITIALIZE_MINT, decimals];
+    data.extend_from_slice(mint_authority.as_ref());
+    pack_pubkey_option(&mut data, freeze_authority);
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data,
+    }
+}
+
+pub fn mint_to(
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![MINT_TO];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data,
+    }
+}
+
+pub fn transfer_checked(
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![TRANSFER_CHECKED];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction {
+        program_id: id(),
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+/// Instructions for a turnkey token: creates a rent-exempt mint account funded by `payer`,
+/// initializes it, creates `payer`'s associated token account and mints `supply` into it.
+/// `payer` is the mint authority, and optionally the freeze authority.
+pub fn create_mint_with_supply(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    decimals: u8,
+    supply: u64,
+    freezable: bool,
+    rent_lamports: u64,
+) -> Vec<Instruction> {
+    let token_account = super::associated_token::get_associated_token_address(payer, mint);
+    let mut instructions = vec![
+        system_instruction::create_account(payer, mint, rent_lamports, MINT_LEN as u64, &id()),
+        initialize_mint(mint, payer, freezable.then(|| payer), decimals),
+        super::associated_token::create_associated_token_account(payer, payer, mint),
+    ];
+    if supply > 0 {
+        instructions.push(mint_to(mint, &token_account, payer, supply));
+    }
+    instructions
+}
@@ -0,0 +1,342 @@
+//! Sign-In With Solana messages, modelled on [EIP-4361](https://eips.ethereum.org/EIPS/eip-4361).
+//!
+//! Has no browser dependencies so that the same parser and verifier can be used by a
+//! native server authenticating users of the app.
+
+use crate::error::Error;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{fmt, str::FromStr};
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+const NONCE_PREFIX: &str = "Nonce: ";
+const ISSUED_AT_PREFIX: &str = "Issued At: ";
+const EXPIRATION_TIME_PREFIX: &str = "Expiration Time: ";
+pub const NONCE_SIZE: usize = 16;
+
+/// Message the user signs with their wallet to prove ownership of `address` to `domain`.
+/// Times are unix timestamps in seconds, formatted as RFC 3339 UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignInMessage {
+    pub domain: String,
+    pub address: Pubkey,
+    pub statement: Option<String>,
+    pub nonce: String,
+    pub issued_at: u64,
+    pub expiration_time: Option<u64>,
+}
+
+impl SignInMessage {
+    /// Creates a message with a random nonce, issued at `now` and valid for `ttl` seconds.
+    pub fn new(domain: &str, address: Pubkey, now: u64, ttl: Option<u64>) -> Result<Self, Error> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        getrandom::getrandom(&mut nonce).map_err(|_| Error::InvalidHash)?;
+        Ok(Self {
+            domain: domain.to_string(),
+            address,
+            statement: None,
+            nonce: bs58::encode(nonce).into_string(),
+            issued_at: now,
+            expiration_time: ttl.map(|ttl| now + ttl),
+        })
+    }
+
+    /// Checks that the message was issued for `domain`, is valid at `now` and that
+    /// `signature` was made over it by `address`.
+    pub fn verify(&self, signature: &Signature, domain: &str, now: u64) -> Result<(), Error> {
+        if self.domain != domain {
+            return Err(Error::SignInDomainMismatch(self.domain.clone()));
+        }
+        if now < self.issued_at || self.expiration_time.map_or(false, |exp| now >= exp) {
+            return Err(Error::SignInExpired);
+        }
+        if !signature.verify(self.address.as_ref(), self.to_string().as_bytes()) {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+/// Parses `message` and verifies `signature` over it, returning the authenticated message.
+pub fn verify_sign_in(
+    message: &str,
+    signature: &Signature,
+    domain: &str,
+    now: u64,
+) -> Result<SignInMessage, Error> {
+    let sign_in = SignInMessage::from_str(message)?;
+    if sign_in.to_string() != message {
+        return Err(Error::SignInParse(
+            "message is not in canonical form".to_string(),
+        ));
+    }
+    sign_in.verify(signature, domain, now)?;
+    Ok(sign_in)
+}
+
+impl fmt::Display for SignInMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}{}", self.domain, HEADER_SUFFIX)?;
+        writeln!(f, "{}", self.address)?;
+        writeln!(f)?;
+        if let Some(statement) = &self.statement {
+            writeln!(f, "{}", statement)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "{}{}", NONCE_PREFIX, self.nonce)?;
+        write!(
+            f,
+            "{}{}",
+            ISSUED_AT_PREFIX,
+            format_timestamp(self.issued_at)
+        )?;
+        if let Some(expiration_time) = self.expiration_time {
+            write!(
+                f,
+                "\n{}{}",
+                EXPIRATION_TIME_PREFIX,
+                format_timestamp(expiration_time)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SignInMessage {
+    type Err = Error;
+
+    fn from_str(message: &str) -> Result<Self, Self::Err> {
+        let parse_error = |reason: &str| Error::SignInParse(reason.to_string());
+        let mut lines = message.lines().peekable();
+
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(HEADER_SUFFIX))
+            .ok_or_else(|| parse_error("missing header"))?
+            .to_string();
+        let address = lines
+            .next()
+            .and_then(|line| Pubkey::from_str(line).ok())
+            .ok_or_else(|| parse_error("invalid address"))?;
+        if lines.next() != Some("") {
+            return Err(parse_error("missing blank line after address"));
+        }
+
+        let statement = match lines.peek() {
+            Some(line) if !line.starts_with(NONCE_PREFIX) => {
+                let statement = line.to_string();
+                lines.next();
+                if lines.next() != Some("") {
+                    return Err(parse_error("missing blank line after statement"));
+                }
+                Some(statement)
+            }
+            _ => None,
+        };
+
+        let nonce = lines
+            .next()
+            .and_then(|line| line.strip_prefix(NONCE_PREFIX))
+            .ok_or_else(|| parse_error("missing nonce"))?
+            .to_string();
+        let issued_at = lines
+            .next()
+            .and_then(|line| line.strip_prefix(ISSUED_AT_PREFIX))
+            .and_then(parse_timestamp)
+            .ok_or_else(|| parse_error("invalid issued at"))?;
+        let expiration_time = match lines.next() {
+            Some(line) => Some(
+                line.strip_prefix(EXPIRATION_TIME_PREFIX)
+                    .and_then(parse_timestamp)
+                    .ok_or_else(|| parse_error("invalid expiration time"))?,
+            ),
+            None => None,
+        };
+        if lines.next().is_some() {
+            return Err(parse_error("unexpected trailing lines"));
+        }
+
+        Ok(Self {
+            domain,
+            address,
+            statement,
+            nonce,
+            issued_at,
+            expiration_time,
+        })
+    }
+}
+
+/// Formats unix seconds as `YYYY-MM-DDTHH:MM:SSZ`.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+/// Parses a `YYYY-MM-DDTHH:MM:SSZ` timestamp into unix seconds.
+pub fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.splitn(3, ':').map(|p| p.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+/// Days since the unix epoch for a proleptic Gregorian date, per
+/// [Hinnant's algorithm](http://howardhinnant.github.io/date_algorithms.html#days_from_civil).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = (if days >= 0 { days } else { days - 146_096 }) / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    const DOMAIN: &str = "tokens.example.com";
+    /// 2022-03-01T12:00:00Z
+    const NOW: u64 = 1_646_136_000;
+
+    fn signed_message(keypair: &Keypair, ttl: Option<u64>) -> (String, Signature) {
+        let mut message = SignInMessage::new(DOMAIN, keypair.pubkey(), NOW, ttl).unwrap();
+        message.statement = Some("Sign in to WASM Token App".to_string());
+        let message = message.to_string();
+        let signature = keypair.sign_message(message.as_bytes());
+        (message, signature)
+    }
+
+    #[test]
+    fn round_trips_through_display_and_from_str() {
+        let address = Keypair::new().pubkey();
+        let mut message = SignInMessage::new(DOMAIN, address, NOW, Some(600)).unwrap();
+        assert_eq!(
+            SignInMessage::from_str(&message.to_string()).unwrap(),
+            message
+        );
+
+        message.statement = Some("Sign in to WASM Token App".to_string());
+        message.expiration_time = None;
+        let text = message.to_string();
+        assert_eq!(
+            text,
+            format!(
+                "{}{}\n{}\n\nSign in to WASM Token App\n\n{}{}\n{}2022-03-01T12:00:00Z",
+                DOMAIN, HEADER_SUFFIX, address, NONCE_PREFIX, message.nonce, ISSUED_AT_PREFIX
+            )
+        );
+        assert_eq!(SignInMessage::from_str(&text).unwrap(), message);
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let message = SignInMessage::new(DOMAIN, Keypair::new().pubkey(), NOW, None).unwrap();
+        let text = message.to_string();
+        for malformed in [
+            text.replacen(HEADER_SUFFIX, "", 1),
+            text.replacen(NONCE_PREFIX, "Nonce:", 1),
+            text.replace("2022-03-01", "2022-13-01"),
+            format!("{}\nResources: none", text),
+        ] {
+            assert!(matches!(
+                SignInMessage::from_str(&malformed),
+                Err(Error::SignInParse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn verifies_signed_message() {
+        let keypair = Keypair::new();
+        let (message, signature) = signed_message(&keypair, Some(600));
+        let sign_in = verify_sign_in(&message, &signature, DOMAIN, NOW + 599).unwrap();
+        assert_eq!(sign_in.address, keypair.pubkey());
+
+        let other = Keypair::new().sign_message(message.as_bytes());
+        assert!(matches!(
+            verify_sign_in(&message, &other, DOMAIN, NOW),
+            Err(Error::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_expired_messages() {
+        let keypair = Keypair::new();
+        let (message, signature) = signed_message(&keypair, Some(600));
+        for now in [NOW - 1, NOW + 600] {
+            assert!(matches!(
+                verify_sign_in(&message, &signature, DOMAIN, now),
+                Err(Error::SignInExpired)
+            ));
+        }
+        let (message, signature) = signed_message(&keypair, None);
+        assert!(verify_sign_in(&message, &signature, DOMAIN, NOW + 86_400 * 365).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_domains() {
+        let keypair = Keypair::new();
+        let (message, signature) = signed_message(&keypair, Some(600));
+        match verify_sign_in(&message, &signature, "evil.example.com", NOW) {
+            Err(Error::SignInDomainMismatch(domain)) => assert_eq!(domain, DOMAIN),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn converts_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(NOW + 3_661), "2022-03-01T13:01:01Z");
+        assert_eq!(parse_timestamp("2000-02-29T00:00:00Z"), Some(951_782_400));
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in (0..200_000).step_by(97) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        for invalid in [
+            "1969-12-31T23:59:59Z",
+            "2022-03-01T24:00:00Z",
+            "2022-03-01 12:00:00Z",
+            "2022-03-01T12:00:00",
+        ] {
+            assert_eq!(parse_timestamp(invalid), None);
+        }
+    }
+}
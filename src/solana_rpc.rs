@@ -0,0 +1,509 @@
+//! Solana JSON-RPC client over [`reqwest`], usable from both wasm and native builds.
+//!
+//! Only the methods the app needs are implemented, each with a typed response. The endpoint
+//! is passed in so that the client can be pointed at a local JSON-RPC stub.
+
+use crate::{error::Error, transaction::stringify};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::{
+    account::Account, hash::Hash, message::Message, pubkey::Pubkey, signature::Signature,
+    transaction::Transaction,
+};
+use std::str::FromStr;
+
+/// Most keys `getMultipleAccounts` accepts in one request.
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Commitment levels reported by `getSignatureStatuses`, ordered from least to most final.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcContext {
+    pub slot: u64,
+}
+
+/// Wrapper for responses that include the slot they were evaluated at.
+#[derive(Debug, Deserialize)]
+pub struct RpcContextual<T> {
+    pub context: RpcContext,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureStatus {
+    pub slot: u64,
+    pub confirmations: Option<u64>,
+    pub err: Option<Value>,
+    pub confirmation_status: Option<Commitment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestBlockhash {
+    #[serde(with = "stringify")]
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+}
+
+/// Account as returned with `base64` encoding, converted into an [`Account`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcAccount {
+    lamports: u64,
+    owner: String,
+    data: (String, String),
+    executable: bool,
+    rent_epoch: u64,
+}
+
+impl TryFrom<RpcAccount> for Account {
+    type Error = Error;
+
+    fn try_from(account: RpcAccount) -> Result<Self, Self::Error> {
+        Ok(Account {
+            lamports: account.lamports,
+            owner: Pubkey::from_str(&account.owner).map_err(|_| Error::InvalidPublicKey)?,
+            data: base64::decode(&account.data.0)?,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateTransactionResult {
+    pub err: Option<Value>,
+    pub logs: Option<Vec<String>>,
+    pub units_consumed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureInfo {
+    #[serde(with = "stringify")]
+    pub signature: Signature,
+    pub slot: u64,
+    pub err: Option<Value>,
+    pub memo: Option<String>,
+    pub block_time: Option<i64>,
+}
+
+/// Transaction as returned by `getTransaction` with `base64` encoding. `version` is `"legacy"`
+/// or a number, and missing from nodes that predate versioned transactions.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcConfirmedTransaction {
+    slot: u64,
+    block_time: Option<i64>,
+    transaction: (String, String),
+    meta: Option<RpcTransactionMeta>,
+    #[serde(default)]
+    version: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcTransactionMeta {
+    err: Option<Value>,
+}
+
+/// A transaction included in a block, with the outcome of processing it.
+#[derive(Debug, Clone)]
+pub struct ConfirmedTransaction {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub transaction: Transaction,
+    pub err: Option<Value>,
+}
+
+impl TryFrom<RpcConfirmedTransaction> for ConfirmedTransaction {
+    type Error = Error;
+
+    fn try_from(confirmed: RpcConfirmedTransaction) -> Result<Self, Self::Error> {
+        match confirmed.version {
+            None => {}
+            Some(Value::String(version)) if version == "legacy" => {}
+            Some(version) => return Err(Error::UnsupportedTransactionVersion(version.to_string())),
+        }
+        Ok(ConfirmedTransaction {
+            slot: confirmed.slot,
+            block_time: confirmed.block_time,
+            transaction: bincode::deserialize(&base64::decode(&confirmed.transaction.0)?)?,
+            err: confirmed.meta.and_then(|meta| meta.err),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrioritizationFee {
+    pub slot: u64,
+    /// Lowest price, in micro-lamports per compute unit, paid by a transaction in the slot.
+    pub prioritization_fee: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    url: String,
+    commitment: Commitment,
+    client: reqwest::Client,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> Self {
+        Self::new_with_commitment(url, Commitment::Confirmed)
+    }
+
+    pub fn new_with_commitment(url: &str, commitment: Commitment) -> Self {
+        Self {
+            url: url.to_string(),
+            commitment,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn send<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response = self.client.post(&self.url).json(&body).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::StatusCodeNotOk);
+        }
+        let response: RpcResponse<T> = response.json().await?;
+        match response {
+            RpcResponse {
+                error: Some(RpcError { code, message }),
+                ..
+            } => Err(Error::SolanaRpc { code, message }),
+            RpcResponse {
+                result: Some(result),
+                ..
+            } => Ok(result),
+            // A `null` result, which is only valid for optional results.
+            _ => serde_json::from_value(Value::Null).map_err(|_| Error::SolanaRpc {
+                code: 0,
+                message: format!("{} returned no result", method),
+            }),
+        }
+    }
+
+    pub async fn get_latest_blockhash(&self) -> Result<RpcContextual<LatestBlockhash>, Error> {
+        self.send(
+            "getLatestBlockhash",
+            json!([{ "commitment": self.commitment }]),
+        )
+        .await
+    }
+
+    pub async fn get_block_height(&self) -> Result<u64, Error> {
+        self.send("getBlockHeight", json!([{ "commitment": self.commitment }]))
+            .await
+    }
+
+    pub async fn get_balance(&self, pubkey: &Pubkey) -> Result<RpcContextual<u64>, Error> {
+        self.send(
+            "getBalance",
+            json!([pubkey.to_string(), { "commitment": self.commitment }]),
+        )
+        .await
+    }
+
+    pub async fn get_account_info(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<RpcContextual<Option<Account>>, Error> {
+        let response: RpcContextual<Option<RpcAccount>> = self
+            .send(
+                "getAccountInfo",
+                json!([
+                    pubkey.to_string(),
+                    { "commitment": self.commitment, "encoding": "base64" }
+                ]),
+            )
+            .await?;
+        Ok(RpcContextual {
+            context: response.context,
+            value: response.value.map(Account::try_from).transpose()?,
+        })
+    }
+
+    /// Accounts at `pubkeys`, in order, `None` for those that do not exist. The RPC accepts
+    /// up to [`MAX_MULTIPLE_ACCOUNTS`] keys per request.
+    pub async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<RpcContextual<Vec<Option<Account>>>, Error> {
+        let pubkeys: Vec<String> = pubkeys.iter().map(|pubkey| pubkey.to_string()).collect();
+        let response: RpcContextual<Vec<Option<RpcAccount>>> = self
+            .send(
+                "getMultipleAccounts",
+                json!([pubkeys, { "commitment": self.commitment, "encoding": "base64" }]),
+            )
+            .await?;
+        Ok(RpcContextual {
+            context: response.context,
+            value: response
+                .value
+                .into_iter()
+                .map(|account| account.map(Account::try_from).transpose())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub async fn get_minimum_balance_for_rent_exemption(
+        &self,
+        data_len: usize,
+    ) -> Result<u64, Error> {
+        self.send(
+            "getMinimumBalanceForRentExemption",
+            json!([data_len, { "commitment": self.commitment }]),
+        )
+        .await
+    }
+
+    /// Fee the cluster would charge for `message`, or `None` if its blockhash has expired.
+    pub async fn get_fee_for_message(
+        &self,
+        message: &Message,
+    ) -> Result<RpcContextual<Option<u64>>, Error> {
+        self.send(
+            "getFeeForMessage",
+            json!([
+                base64::encode(message.serialize()),
+                { "commitment": self.commitment }
+            ]),
+        )
+        .await
+    }
+
+    /// Priority fees paid in recent slots by transactions that lock all of `accounts` as
+    /// writable.
+    pub async fn get_recent_prioritization_fees(
+        &self,
+        accounts: &[Pubkey],
+    ) -> Result<Vec<PrioritizationFee>, Error> {
+        let accounts: Vec<String> = accounts.iter().map(|a| a.to_string()).collect();
+        self.send("getRecentPrioritizationFees", json!([accounts]))
+            .await
+    }
+
+    /// Submits a fully signed transaction, returning its first signature.
+    pub async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, Error> {
+        let signature: String = self
+            .send(
+                "sendTransaction",
+                json!([
+                    base64::encode(bincode::serialize(transaction)?),
+                    { "encoding": "base64", "preflightCommitment": self.commitment }
+                ]),
+            )
+            .await?;
+        Signature::from_str(&signature).map_err(|_| Error::InvalidSignature)
+    }
+
+    /// Simulates `transaction` without requiring signatures, using the cluster's latest
+    /// blockhash so that simulation can run before the user is asked to sign.
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<RpcContextual<SimulateTransactionResult>, Error> {
+        self.send(
+            "simulateTransaction",
+            json!([
+                base64::encode(bincode::serialize(transaction)?),
+                {
+                    "encoding": "base64",
+                    "commitment": self.commitment,
+                    "sigVerify": false,
+                    "replaceRecentBlockhash": true,
+                }
+            ]),
+        )
+        .await
+    }
+
+    /// Signatures of confirmed transactions that include `address`, newest first.
+    pub async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>, Error> {
+        self.send(
+            "getSignaturesForAddress",
+            json!([
+                address.to_string(),
+                { "commitment": self.commitment, "limit": limit }
+            ]),
+        )
+        .await
+    }
+
+    /// Fetches a confirmed transaction, or `None` if it is unknown to the cluster. Versioned
+    /// transactions are returned by the cluster but fail with
+    /// [`Error::UnsupportedTransactionVersion`], only legacy ones are decoded.
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<ConfirmedTransaction>, Error> {
+        let response: Option<RpcConfirmedTransaction> = self
+            .send(
+                "getTransaction",
+                json!([
+                    signature.to_string(),
+                    {
+                        "commitment": self.commitment,
+                        "encoding": "base64",
+                        "maxSupportedTransactionVersion": 0
+                    }
+                ]),
+            )
+            .await?;
+        response.map(ConfirmedTransaction::try_from).transpose()
+    }
+
+    /// Statuses of `signatures`, `None` for those the cluster has not seen. Without
+    /// `search_history` only the recent status cache is searched.
+    pub async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+        search_history: bool,
+    ) -> Result<RpcContextual<Vec<Option<SignatureStatus>>>, Error> {
+        let signatures: Vec<String> = signatures.iter().map(|s| s.to_string()).collect();
+        self.send(
+            "getSignatureStatuses",
+            json!([signatures, { "searchTransactionHistory": search_history }]),
+        )
+        .await
+    }
+
+    pub async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: Commitment,
+    ) -> Result<RpcContextual<bool>, Error> {
+        self.send(
+            "isBlockhashValid",
+            json!([blockhash.to_string(), { "commitment": commitment }]),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{block_on, serve, Request};
+    use std::sync::mpsc::Receiver;
+
+    fn client(responses: Vec<(u16, Value)>) -> (RpcClient, Receiver<Request>) {
+        let (url, requests) = serve(
+            responses
+                .into_iter()
+                .map(|(status, body)| (status, body.to_string()))
+                .collect(),
+        );
+        (RpcClient::new(&url), requests)
+    }
+
+    #[test]
+    fn returns_result() {
+        let (rpc, requests) = client(vec![(
+            200,
+            json!({ "jsonrpc": "2.0", "id": 1, "result": 42 }),
+        )]);
+        assert_eq!(block_on(rpc.get_block_height()).unwrap(), 42);
+        let request = requests.recv().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/")
+        );
+        let request: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(request["method"], "getBlockHeight");
+        assert_eq!(request["params"][0]["commitment"], "confirmed");
+    }
+
+    #[test]
+    fn returns_rpc_error() {
+        let (rpc, _requests) = client(vec![(
+            200,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": { "code": -32602, "message": "Invalid params" }
+            }),
+        )]);
+        match block_on(rpc.get_block_height()) {
+            Err(Error::SolanaRpc { code, message }) => {
+                assert_eq!(code, -32602);
+                assert_eq!(message, "Invalid params");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_null_only_for_optional_results() {
+        let null = json!({ "jsonrpc": "2.0", "id": 1, "result": null });
+        let (rpc, _requests) = client(vec![(200, null.clone()), (200, null)]);
+        let signature = Signature::default();
+        assert!(block_on(rpc.get_transaction(&signature)).unwrap().is_none());
+        assert!(matches!(
+            block_on(rpc.get_block_height()),
+            Err(Error::SolanaRpc { code: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn returns_missing_account() {
+        let (rpc, _requests) = client(vec![(
+            200,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": { "context": { "slot": 7 }, "value": null }
+            }),
+        )]);
+        let account = block_on(rpc.get_account_info(&Pubkey::default())).unwrap();
+        assert_eq!(account.context.slot, 7);
+        assert!(account.value.is_none());
+    }
+
+    #[test]
+    fn rejects_http_errors() {
+        for status in [429, 503] {
+            let (rpc, _requests) = client(vec![(status, json!({ "message": "slow down" }))]);
+            assert!(matches!(
+                block_on(rpc.get_block_height()),
+                Err(Error::StatusCodeNotOk)
+            ));
+        }
+    }
+}
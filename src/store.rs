@@ -1,8 +1,23 @@
+use crate::arweave::ArweaveClient;
+use crate::batch_transfer::BatchTransferRun;
+use crate::collection::CollectionRun;
 use crate::components::phantom_wallet::PhantomWallet;
+use crate::crypto::Provider;
+use crate::error::Error;
+use crate::notarization::NotarizationRecord;
+use crate::preview::TransactionPreview;
+use crate::priority_fee::PriorityFee;
+use crate::solana_rpc::{Commitment, RpcClient};
 use crate::transaction::Transaction;
-use std::collections::HashMap;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{collections::HashMap, fmt, rc::Rc, str::FromStr};
 use sycamore::prelude::*;
 
+const BATCH_TRANSFER_STORAGE_KEY: &str = "batch_transfer";
+const CLUSTER_STORAGE_KEY: &str = "cluster";
+const COLLECTION_RUN_STORAGE_KEY: &str = "collection_run";
+const PRIORITY_FEE_STORAGE_KEY: &str = "priority_fee";
+
 pub struct Count(pub i32);
 
 impl Default for Count {
@@ -11,30 +26,397 @@ impl Default for Count {
     }
 }
 
+/// Progress of the most recent transaction sent through Phantom.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfirmationStage {
+    Idle,
+    Sending,
+    Sent,
+    Processed,
+    Confirmed,
+    Finalized,
+    Expired,
+    Failed(String),
+}
+
+impl From<Commitment> for ConfirmationStage {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => ConfirmationStage::Processed,
+            Commitment::Confirmed => ConfirmationStage::Confirmed,
+            Commitment::Finalized => ConfirmationStage::Finalized,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Confirmation {
+    pub signature: Option<Signature>,
+    pub stage: ConfirmationStage,
+}
+
+impl Default for Confirmation {
+    fn default() -> Confirmation {
+        Confirmation {
+            signature: None,
+            stage: ConfirmationStage::Idle,
+        }
+    }
+}
+
+/// Solana cluster the app talks to. The RPC endpoint, explorer links and Arweave gateway
+/// are all derived from it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cluster {
+    MainnetBeta,
+    Devnet,
+    Testnet,
+    Localnet,
+    Custom(String),
+}
+
+impl Default for Cluster {
+    fn default() -> Cluster {
+        Cluster::Devnet
+    }
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cluster::MainnetBeta => write!(f, "mainnet-beta"),
+            Cluster::Devnet => write!(f, "devnet"),
+            Cluster::Testnet => write!(f, "testnet"),
+            Cluster::Localnet => write!(f, "localnet"),
+            Cluster::Custom(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// Parses a cluster name, or any `http(s)` URL as a custom cluster.
+impl FromStr for Cluster {
+    type Err = Error;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "mainnet-beta" => Ok(Cluster::MainnetBeta),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "localnet" => Ok(Cluster::Localnet),
+            url if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(Cluster::Custom(url.to_string()))
+            }
+            _ => Err(Error::InvalidCluster(str.to_string())),
+        }
+    }
+}
+
+impl Cluster {
+    pub fn rpc_url(&self) -> String {
+        match self {
+            Cluster::MainnetBeta => "https://api.mainnet-beta.solana.com".to_string(),
+            Cluster::Devnet => "https://api.devnet.solana.com".to_string(),
+            Cluster::Testnet => "https://api.testnet.solana.com".to_string(),
+            Cluster::Localnet => "http://localhost:8899".to_string(),
+            Cluster::Custom(url) => url.clone(),
+        }
+    }
+
+    pub fn arweave_gateway(&self) -> String {
+        match self {
+            Cluster::Localnet => "http://localhost:1984".to_string(),
+            _ => "https://arweave.net".to_string(),
+        }
+    }
+
+    fn explorer_query(&self) -> String {
+        match self {
+            Cluster::MainnetBeta => String::new(),
+            Cluster::Devnet | Cluster::Testnet => format!("?cluster={}", self),
+            Cluster::Localnet | Cluster::Custom(_) => format!(
+                "?cluster=custom&customUrl={}",
+                encode_uri_component(&self.rpc_url())
+            ),
+        }
+    }
+
+    pub fn explorer_tx_url(&self, signature: &Signature) -> String {
+        format!(
+            "https://explorer.solana.com/tx/{}{}",
+            signature,
+            self.explorer_query()
+        )
+    }
+
+    pub fn explorer_address_url(&self, address: &Pubkey) -> String {
+        format!(
+            "https://explorer.solana.com/address/{}{}",
+            address,
+            self.explorer_query()
+        )
+    }
+
+    /// Reads the last selected cluster from local storage.
+    fn load() -> Option<Cluster> {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        storage.get_item(CLUSTER_STORAGE_KEY).ok()??.parse().ok()
+    }
+
+    fn save(&self) {
+        if let Some(Ok(Some(storage))) = web_sys::window().map(|w| w.local_storage()) {
+            if storage
+                .set_item(CLUSTER_STORAGE_KEY, &self.to_string())
+                .is_err()
+            {
+                log::warn!("unable to persist cluster selection");
+            }
+        }
+    }
+}
+
+/// Percent-encodes everything except unreserved characters.
+fn encode_uri_component(str: &str) -> String {
+    str.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Reads the batch transfer in progress from local storage.
+fn load_batch_transfer() -> Option<BatchTransferRun> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    let json = storage.get_item(BATCH_TRANSFER_STORAGE_KEY).ok()??;
+    serde_json::from_str(&json).ok()
+}
+
+fn save_batch_transfer(run: &BatchTransferRun) {
+    if let Some(Ok(Some(storage))) = web_sys::window().map(|w| w.local_storage()) {
+        let saved = serde_json::to_string(run)
+            .ok()
+            .and_then(|json| storage.set_item(BATCH_TRANSFER_STORAGE_KEY, &json).ok());
+        if saved.is_none() {
+            log::warn!("unable to persist batch transfer progress");
+        }
+    }
+}
+
+/// Reads the collection launch in progress from local storage.
+fn load_collection_run() -> Option<CollectionRun> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    let json = storage.get_item(COLLECTION_RUN_STORAGE_KEY).ok()??;
+    serde_json::from_str(&json).ok()
+}
+
+fn save_collection_run(run: &CollectionRun) {
+    if let Some(Ok(Some(storage))) = web_sys::window().map(|w| w.local_storage()) {
+        let saved = serde_json::to_string(run)
+            .ok()
+            .and_then(|json| storage.set_item(COLLECTION_RUN_STORAGE_KEY, &json).ok());
+        if saved.is_none() {
+            log::warn!("unable to persist collection progress");
+        }
+    }
+}
+
+/// Reads the last selected priority fee level from local storage.
+fn load_priority_fee() -> Option<PriorityFee> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    storage
+        .get_item(PRIORITY_FEE_STORAGE_KEY)
+        .ok()??
+        .parse()
+        .ok()
+}
+
+fn save_priority_fee(priority_fee: PriorityFee) {
+    if let Some(Ok(Some(storage))) = web_sys::window().map(|w| w.local_storage()) {
+        if storage
+            .set_item(PRIORITY_FEE_STORAGE_KEY, &priority_fee.to_string())
+            .is_err()
+        {
+            log::warn!("unable to persist priority fee selection");
+        }
+    }
+}
+
+/// Returns an [`RpcClient`] for the currently selected [`Cluster`].
+pub fn rpc_client(ctx: ScopeRef) -> RpcClient {
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+    RpcClient::new(&cluster.get().rpc_url())
+}
+
+/// Arweave keypair loaded from a JWK keyfile, used to pay for uploads.
+#[derive(Clone, Default)]
+pub struct ArweaveWallet(pub Option<Rc<Provider>>);
+
+/// Returns an [`ArweaveClient`] for the gateway of the selected [`Cluster`].
+pub fn arweave_client(ctx: ScopeRef) -> ArweaveClient {
+    let cluster = ctx.use_context::<Signal<Cluster>>();
+    ArweaveClient::new(&cluster.get().arweave_gateway())
+}
+
+/// Returns the loaded Arweave keypair.
+pub fn arweave_provider(ctx: ScopeRef) -> Result<Rc<Provider>, Error> {
+    let arweave_wallet = ctx.use_context::<Signal<ArweaveWallet>>();
+    let provider = arweave_wallet.get().0.clone();
+    provider.ok_or(Error::ArweaveWalletNotLoaded)
+}
+
+/// Transactions waiting for the user to approve their previews. Calling `resolve` with `true`
+/// lets them go on to Phantom.
+#[derive(Debug, Clone)]
+pub struct PendingApproval {
+    pub previews: Vec<TransactionPreview>,
+    /// Why approval is being asked for again, such as an expired blockhash.
+    pub notice: Option<String>,
+    pub resolve: js_sys::Function,
+}
+
+impl PendingApproval {
+    pub fn resolve(&self, approved: bool) {
+        if let Err(error) = self
+            .resolve
+            .call1(&wasm_bindgen::JsValue::NULL, &approved.into())
+        {
+            log::error!("{:?}", error);
+        }
+    }
+}
+
+pub type Approval = Option<PendingApproval>;
+
+/// Last recoverable error, shown to the user until dismissed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorMessage(pub Option<String>);
+
 pub type Files = HashMap<String, gloo_file::File>;
 pub type FilesVec = Vec<(String, i32)>;
+
+/// Returns the selected file called `name`.
+pub fn selected_file(ctx: ScopeRef, name: &str) -> Result<gloo_file::File, Error> {
+    let files = ctx.use_context::<Signal<Files>>();
+    let file = files.get().get(name).cloned();
+    file.ok_or_else(|| Error::FileNotFound(name.to_string()))
+}
+/// Token mints created by the connected wallet during this session.
+pub type Mints = Vec<Pubkey>;
+/// NFT minted during this session, with the Arweave URIs of its metadata and asset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nft {
+    pub mint: Pubkey,
+    pub name: String,
+    pub metadata_uri: String,
+    pub image_uri: String,
+}
+
+pub type Nfts = Vec<Nft>;
+
+/// File notarized during this session. `slot` is filled in once the memo transaction is
+/// confirmed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notarization {
+    pub signature: Signature,
+    pub slot: Option<u64>,
+    pub file: String,
+    pub record: NotarizationRecord,
+}
+
+pub type Notarizations = Vec<Notarization>;
 pub type WalletConnected = bool;
 
 pub fn initialize_store(ctx: ScopeRef) {
+    ctx.provide_context_ref(ctx.create_signal(Approval::None));
+    ctx.provide_context_ref(ctx.create_signal(ArweaveWallet::default()));
+    ctx.provide_context_ref(ctx.create_signal(load_batch_transfer().unwrap_or_default()));
+    ctx.provide_context_ref(ctx.create_signal(Cluster::load().unwrap_or_default()));
+    ctx.provide_context_ref(ctx.create_signal(load_collection_run().unwrap_or_default()));
     ctx.provide_context_ref(ctx.create_signal(Count::default()));
     ctx.provide_context_ref(ctx.create_signal(Files::new()));
     ctx.provide_context_ref(ctx.create_signal(FilesVec::new()));
+    ctx.provide_context_ref(ctx.create_signal(Mints::new()));
+    ctx.provide_context_ref(ctx.create_signal(Nfts::new()));
+    ctx.provide_context_ref(ctx.create_signal(Notarizations::new()));
+    ctx.provide_context_ref(ctx.create_signal(load_priority_fee().unwrap_or_default()));
     ctx.provide_context_ref(ctx.create_signal(Transaction::default()));
     ctx.provide_context_ref(ctx.create_signal(PhantomWallet::default()));
+    ctx.provide_context_ref(ctx.create_signal(Confirmation::default()));
+    ctx.provide_context_ref(ctx.create_signal(ErrorMessage::default()));
 }
 pub enum Action {
+    /// Clears the approval once answered, unless it has already been replaced by another.
+    ApprovalClear(js_sys::Function),
+    ApprovalSet(Approval),
+    ArweaveWalletSet(Provider),
+    BatchTransferSet(BatchTransferRun),
+    ClusterSet(Cluster),
+    CollectionRunSet(CollectionRun),
+    ConfirmationSet(Confirmation),
     CountIncrement(i32),
+    ErrorSet(Option<String>),
     FilesSet(web_sys::FileList),
+    MintAdd(Pubkey),
+    NftAdd(Nft),
+    NotarizationAdd(Notarization),
+    NotarizationConfirmed {
+        signature: Signature,
+        slot: u64,
+    },
+    PriorityFeeSet(PriorityFee),
     TransactionSet(Transaction),
     WalletSet(PhantomWallet),
 }
 
 pub fn reducer(ctx: ScopeRef, action: Action) {
     match action {
+        Action::ApprovalClear(resolve) => {
+            let approval = ctx.use_context::<Signal<Approval>>();
+            let answered = matches!(&*approval.get(), Some(pending) if pending.resolve == resolve);
+            if answered {
+                approval.set(None);
+            }
+        }
+        Action::ApprovalSet(new_approval) => {
+            let approval = ctx.use_context::<Signal<Approval>>();
+            // Only one approval is shown at a time, an unanswered one is rejected.
+            if let (Some(pending), Some(_)) = (&*approval.get(), &new_approval) {
+                pending.resolve(false);
+            }
+            approval.set(new_approval);
+        }
+        Action::ArweaveWalletSet(provider) => {
+            let arweave_wallet = ctx.use_context::<Signal<ArweaveWallet>>();
+            arweave_wallet.set(ArweaveWallet(Some(Rc::new(provider))));
+        }
+        Action::BatchTransferSet(new_run) => {
+            let run = ctx.use_context::<Signal<BatchTransferRun>>();
+            save_batch_transfer(&new_run);
+            run.set(new_run);
+        }
+        Action::ClusterSet(new_cluster) => {
+            let cluster = ctx.use_context::<Signal<Cluster>>();
+            new_cluster.save();
+            cluster.set(new_cluster);
+        }
+        Action::CollectionRunSet(new_run) => {
+            let run = ctx.use_context::<Signal<CollectionRun>>();
+            save_collection_run(&new_run);
+            run.set(new_run);
+        }
+        Action::ConfirmationSet(new_confirmation) => {
+            let confirmation = ctx.use_context::<Signal<Confirmation>>();
+            confirmation.set(new_confirmation);
+        }
         Action::CountIncrement(increment) => {
             let count = ctx.use_context::<Signal<Count>>();
             count.set(Count(count.get().0 + increment));
         }
+        Action::ErrorSet(message) => {
+            let error = ctx.use_context::<Signal<ErrorMessage>>();
+            error.set(ErrorMessage(message));
+        }
         Action::FilesSet(file_list) => {
             let files = ctx.use_context::<Signal<Files>>();
             let files_vec = ctx.use_context::<Signal<FilesVec>>();
@@ -54,6 +436,39 @@ pub fn reducer(ctx: ScopeRef, action: Action) {
             });
             files.set(new_files);
         }
+        Action::MintAdd(mint) => {
+            let mints = ctx.use_context::<Signal<Mints>>();
+            let mut new_mints = (*mints.get()).clone();
+            new_mints.push(mint);
+            mints.set(new_mints);
+        }
+        Action::NftAdd(nft) => {
+            let nfts = ctx.use_context::<Signal<Nfts>>();
+            let mut new_nfts = (*nfts.get()).clone();
+            new_nfts.push(nft);
+            nfts.set(new_nfts);
+        }
+        Action::NotarizationAdd(notarization) => {
+            let notarizations = ctx.use_context::<Signal<Notarizations>>();
+            let mut new_notarizations = (*notarizations.get()).clone();
+            new_notarizations.push(notarization);
+            notarizations.set(new_notarizations);
+        }
+        Action::NotarizationConfirmed { signature, slot } => {
+            let notarizations = ctx.use_context::<Signal<Notarizations>>();
+            let mut new_notarizations = (*notarizations.get()).clone();
+            for notarization in new_notarizations.iter_mut() {
+                if notarization.signature == signature {
+                    notarization.slot = Some(slot);
+                }
+            }
+            notarizations.set(new_notarizations);
+        }
+        Action::PriorityFeeSet(new_priority_fee) => {
+            let priority_fee = ctx.use_context::<Signal<PriorityFee>>();
+            save_priority_fee(new_priority_fee);
+            priority_fee.set(new_priority_fee);
+        }
         Action::TransactionSet(transaction) => {
             let tx = ctx.use_context::<Signal<Transaction>>();
             tx.set(transaction);
@@ -69,6 +69,33 @@ pub mod stringify {
     }
 }
 
+/// Like [`stringify`], for optional values.
+pub mod option_stringify {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: std::str::FromStr,
+        <T as std::str::FromStr>::Err: std::fmt::Display,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| value.parse::<T>().map_err(serde::de::Error::custom))
+            .transpose()
+    }
+
+    pub fn serialize<S, T>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: std::fmt::Display,
+    {
+        value
+            .as_ref()
+            .map(|value| value.to_string())
+            .serialize(serializer)
+    }
+}
+
 impl Transaction {
     pub fn clone_with_no_data(&self) -> Result<Self, Error> {
         Ok(Self {